@@ -0,0 +1,402 @@
+use crate::error::CompilerError;
+
+use super::{expressions::ExpressionKind, Context, Expression, Type};
+
+/// A GCC-style `asm` statement. The template is spliced straight into our
+/// output, so it has to be written for NASM rather than GAS
+#[derive(Debug, Clone)]
+pub struct AsmStatement {
+    pub template: String,
+
+    /// We never move or remove code anyway, so this changes nothing
+    pub volatile: bool,
+
+    /// Whether it had operands (even empty ones), e.g. `asm("" : : : "rax")`.
+    /// Basic asm doesn't substitute anything into its template
+    pub extended: bool,
+
+    pub outputs: Vec<AsmOperand>,
+    pub inputs: Vec<AsmOperand>,
+
+    /// Registers the template changes, plus "memory" and "cc"
+    pub clobbers: Vec<String>,
+}
+
+/// An operand of an extended asm statement, e.g. `"=a"(lo)`
+#[derive(Debug, Clone)]
+pub struct AsmOperand {
+    pub constraint: String,
+    pub expr: Expression,
+}
+
+/// Where an operand has to be put, going by its constraint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsmConstraint {
+    /// A particular register, going by GCC's letters (`a` for RAX, `S` for
+    /// RSI, etc)
+    Register(char),
+
+    /// Any register (`r`, or `q`, which is the same thing on x86-64, or `g`,
+    /// which allows memory or a constant too)
+    AnyRegister,
+
+    /// The operand's own memory (`m`)
+    Memory,
+
+    /// A constant, written into the template (`i` or `n`)
+    Immediate,
+
+    /// The same place as the numbered output
+    Matching(usize),
+}
+
+/// A piece of an extended asm template
+#[derive(Debug, PartialEq, Eq)]
+pub enum TemplatePart<'a> {
+    Text(&'a str),
+
+    /// `%N`, where operands are numbered outputs first, then inputs
+    Operand(usize),
+}
+
+impl AsmOperand {
+    /// The constraint without its modifiers (`=`, `+` and `&`), which leaves
+    /// the letters for wherever it's allowed to go, e.g. the `rm` of `"=&rm"`
+    fn letters(&self) -> &str {
+        self.constraint.trim_start_matches(['=', '+', '&'])
+    }
+
+    fn modifiers(&self) -> &str {
+        &self.constraint[..self.constraint.len() - self.letters().len()]
+    }
+
+    /// Where this operand goes. When the constraint allows more than one
+    /// place, a register beats a constant, which beats memory
+    pub fn location(&self) -> AsmConstraint {
+        let letters = self.letters();
+
+        if let Ok(n) = letters.parse::<usize>() {
+            return AsmConstraint::Matching(n);
+        }
+
+        letters.chars()
+            .filter_map(|c| match c {
+                'r' | 'q' | 'g' => Some(AsmConstraint::AnyRegister),
+                'm' => Some(AsmConstraint::Memory),
+                'i' | 'n' => Some(AsmConstraint::Immediate),
+                c if is_register_letter(c) => Some(AsmConstraint::Register(c)),
+                _ => None,
+            })
+            .min_by_key(|location| match location {
+                AsmConstraint::AnyRegister => 0,
+                AsmConstraint::Register(_) => 1,
+                AsmConstraint::Immediate => 2,
+                _ => 3,
+            })
+            .expect("Asm constraints are checked to have a valid letter")
+    }
+
+    /// Whether this output is read before it's written (`+`)
+    pub fn is_read_write(&self) -> bool {
+        self.modifiers().contains('+')
+    }
+
+    /// Whether this output gets written before the inputs have all been read
+    /// (`&`), so it can't share a register with any of them
+    pub fn is_early_clobber(&self) -> bool {
+        self.modifiers().contains('&')
+    }
+
+    /// Checks the letters are all ones we know, and that there's at least one
+    fn verify_letters(&self, allowed: &str) -> Result<(), CompilerError> {
+        let letters = self.letters();
+
+        if letters.is_empty() || !letters.chars().all(|c| allowed.contains(c) || is_register_letter(c)) {
+            return Err(CompilerError::SemanticError("Invalid asm constraint"));
+        }
+
+        Ok(())
+    }
+
+    /// Memory operands need an address, which parameters don't have, since
+    /// they stay in the registers they're passed in
+    fn verify_memory(&self, context: &Context) -> Result<(), CompilerError> {
+        if !self.expr.is_lvalue(context) {
+            return Err(CompilerError::SemanticError("Asm memory operands must be lvalues"));
+        }
+
+        if self.expr.is_bit_field(context) {
+            return Err(CompilerError::SemanticError("Asm memory operands can't be bit-fields"));
+        }
+
+        match &self.expr.kind {
+            ExpressionKind::Identifier(n) if context.is_parameter(n) =>
+                Err(CompilerError::SemanticError("Asm memory operands can't be parameters")),
+            ExpressionKind::Identifier(n) if context.is_register(n) =>
+                Err(CompilerError::SemanticError("Asm memory operands can't be register variables")),
+            _ => Ok(()),
+        }
+    }
+
+    fn verify_output(&self, context: &mut Context) -> Result<(), CompilerError> {
+        let modifiers = self.modifiers();
+        let valid = matches!(modifiers, "=" | "+" | "=&" | "+&");
+
+        if !valid {
+            return Err(CompilerError::SemanticError("Asm output constraints must start with '=' or '+', then maybe '&'"));
+        }
+
+        self.verify_letters("rqgm")?;
+        let t = self.expr.verify_modifiable(context)?;
+
+        if self.location() == AsmConstraint::Memory {
+            self.verify_memory(context)?;
+        }
+
+        check_operand_type(&t, self.location())
+    }
+
+    fn verify_input(&self, context: &mut Context, outputs: &[AsmOperand]) ->
+        Result<(), CompilerError> {
+
+        if !self.modifiers().is_empty() {
+            return Err(CompilerError::SemanticError("Asm input constraints can't have modifiers"));
+        }
+
+        let matching = self.letters().chars().all(|c| c.is_ascii_digit());
+        if !matching {
+            self.verify_letters("rqgmin")?;
+        }
+
+        let t = self.expr.verify(context)?;
+
+        match self.location() {
+            AsmConstraint::Memory => self.verify_memory(context),
+
+            AsmConstraint::Immediate => self.expr.eval_constant(context).map(|_| ()),
+
+            AsmConstraint::Matching(n) => match outputs.get(n).map(|o| o.location()) {
+                None => Err(CompilerError::SemanticError("Asm input matches an output that doesn't exist")),
+                Some(AsmConstraint::Memory) =>
+                    Err(CompilerError::SemanticError("Asm inputs can only match register outputs")),
+                Some(_) => check_operand_type(&t, AsmConstraint::AnyRegister),
+            },
+
+            location => check_operand_type(&t, location),
+        }
+    }
+}
+
+/// Whether the constraint letter is one for a particular register
+fn is_register_letter(letter: char) -> bool {
+    matches!(letter, 'a' | 'b' | 'c' | 'd' | 'S' | 'D')
+}
+
+/// Registers can only hold scalars, though anything can be in memory
+fn check_operand_type(t: &Type, location: AsmConstraint) -> Result<(), CompilerError> {
+    let fits = match location {
+        AsmConstraint::Memory => true,
+        _ => t.is_integer() || matches!(t.unqualified(), Type::Pointer(_)),
+    };
+
+    if fits {
+        Ok(())
+    } else {
+        Err(CompilerError::SemanticError("Asm register operands must be integers or pointers"))
+    }
+}
+
+impl AsmStatement {
+    pub fn operands(&self) -> impl Iterator<Item = &AsmOperand> {
+        self.outputs.iter().chain(self.inputs.iter())
+    }
+
+    /// Where the operand goes, following a matching constraint to the output
+    /// it matches
+    pub fn resolve(&self, operand: &AsmOperand) -> AsmConstraint {
+        match operand.location() {
+            AsmConstraint::Matching(n) => self.outputs[n].location(),
+            location => location,
+        }
+    }
+
+    /// Splits the template up around its `%N`s. `%%` is a literal `%`
+    pub fn template_parts(&self) -> Result<Vec<TemplatePart<'_>>, CompilerError> {
+        let mut parts = vec![];
+        let mut rest = self.template.as_str();
+
+        while let Some(i) = rest.find('%') {
+            parts.push(TemplatePart::Text(&rest[..i]));
+            rest = &rest[i + 1..];
+
+            if let Some(after) = rest.strip_prefix('%') {
+                parts.push(TemplatePart::Text("%"));
+                rest = after;
+                continue;
+            }
+
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let n = rest[..digits].parse::<usize>()
+                .map_err(|_| CompilerError::SemanticError("Asm template has a '%' without an operand number"))?;
+
+            if n >= self.outputs.len() + self.inputs.len() {
+                return Err(CompilerError::SemanticError("Asm template refers to an operand that doesn't exist"));
+            }
+
+            parts.push(TemplatePart::Operand(n));
+            rest = &rest[digits..];
+        }
+
+        parts.push(TemplatePart::Text(rest));
+        parts.retain(|p| *p != TemplatePart::Text(""));
+
+        Ok(parts)
+    }
+
+    pub fn verify(&self, context: &mut Context) -> Result<(), CompilerError> {
+        if !self.extended {
+            return Ok(());
+        }
+
+        for output in &self.outputs {
+            output.verify_output(context)?;
+        }
+
+        for input in &self.inputs {
+            input.verify_input(context, &self.outputs)?;
+        }
+
+        // Two inputs (or two outputs) can't both need to be in one register,
+        // though an input can share with an output that isn't early clobber
+        for operands in [&self.outputs, &self.inputs] {
+            let mut letters: Vec<char> = operands.iter()
+                .filter_map(|o| match self.resolve(o) {
+                    AsmConstraint::Register(c) => Some(c),
+                    _ => None,
+                })
+                .collect();
+
+            let count = letters.len();
+            letters.sort_unstable();
+            letters.dedup();
+
+            if letters.len() != count {
+                return Err(CompilerError::SemanticError("Asm operands can't share a register"));
+            }
+        }
+
+        let clobbered: Vec<char> = self.outputs.iter()
+            .filter(|o| o.is_early_clobber())
+            .filter_map(|o| match o.location() {
+                AsmConstraint::Register(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+
+        let shared = self.inputs.iter()
+            .any(|i| matches!(i.location(), AsmConstraint::Register(c) if clobbered.contains(&c)));
+
+        if shared {
+            return Err(CompilerError::SemanticError("Asm early clobber outputs can't share a register with an input"));
+        }
+
+        self.template_parts()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::expressions::ExpressionKind, fs::read_file, parser::parse};
+
+    use super::*;
+
+    fn verify_file(ending: &str) -> Result<(), CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/asm/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(&mut Context::new())
+    }
+
+    fn expect_semantic_error(ending: &str) {
+        match verify_file(ending) {
+            Ok(_) => panic!("Verified when we shouldn't have"),
+            Err(e) => assert!(
+                matches!(e.root(), CompilerError::SemanticError(_)),
+                "Expected a semantic error, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn asm() -> Result<(), CompilerError> {
+        verify_file("asm.c")
+    }
+
+    #[test]
+    fn bad_constraint() {
+        expect_semantic_error("bad_constraint.c");
+    }
+
+    #[test]
+    fn constraints() -> Result<(), CompilerError> {
+        verify_file("constraints.c")
+    }
+
+    #[test]
+    fn bad_modifier() {
+        expect_semantic_error("bad_modifier.c");
+    }
+
+    #[test]
+    fn early_clobber() {
+        expect_semantic_error("early_clobber.c");
+    }
+
+    #[test]
+    fn param_memory() {
+        expect_semantic_error("param_memory.c");
+    }
+
+    #[test]
+    fn output_not_lvalue() {
+        expect_semantic_error("output_not_lvalue.c");
+    }
+
+    #[test]
+    fn missing_operand() {
+        expect_semantic_error("missing_operand.c");
+    }
+
+    #[test]
+    fn template_parts() {
+        let asm = AsmStatement {
+            template: "mov %0, 100%%\n\tadd %0, %1".to_string(),
+            volatile: false,
+            extended: true,
+            outputs: vec![AsmOperand {
+                constraint: "=r".to_string(),
+                expr: ExpressionKind::IntLiteral(0).into(),
+            }],
+            inputs: vec![AsmOperand {
+                constraint: "r".to_string(),
+                expr: ExpressionKind::IntLiteral(0).into(),
+            }],
+            clobbers: vec![],
+        };
+
+        assert_eq!(asm.template_parts().unwrap(), vec![
+            TemplatePart::Text("mov "),
+            TemplatePart::Operand(0),
+            TemplatePart::Text(", 100"),
+            TemplatePart::Text("%"),
+            TemplatePart::Text("\n\tadd "),
+            TemplatePart::Operand(0),
+            TemplatePart::Text(", "),
+            TemplatePart::Operand(1),
+        ]);
+    }
+}
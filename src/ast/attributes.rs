@@ -0,0 +1,154 @@
+use super::Expression;
+
+/// What a declaration's attributes ask for, from GCC's `__attribute__((...))`
+/// or C23's `[[...]]`. The parser warns about any we don't know, and drops them
+#[derive(Debug, Clone, Default)]
+pub struct Attributes {
+    /// `noreturn`, for functions that never return
+    pub noreturn: bool,
+
+    /// `nodiscard` (or GCC's `warn_unused_result`), for functions whose
+    /// result shouldn't be ignored
+    pub nodiscard: bool,
+
+    /// `unused` or `maybe_unused`, so we don't warn if it never gets used
+    pub unused: bool,
+
+    /// `packed`, for structs that shouldn't have any padding
+    pub packed: bool,
+
+    /// `aligned(n)`, which is like `_Alignas` except that asking for less
+    /// than the type's own alignment does nothing
+    pub aligned: Option<Expression>,
+
+    /// `section("name")`, to put a symbol somewhere other than the usual
+    /// text, data or BSS section
+    pub section: Option<String>,
+
+    /// `weak`, so that other definitions can override this one (or for
+    /// declarations, so that the symbol doesn't have to be defined at all)
+    pub weak: bool,
+
+    pub visibility: Option<Visibility>,
+
+    /// `constructor`, for functions to run before `main`
+    pub constructor: bool,
+
+    /// `destructor`, for functions to run after `main` returns
+    pub destructor: bool,
+}
+
+/// Who outside this object file can see a global symbol, from
+/// `visibility("...")`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Default,
+    Hidden,
+    Protected,
+    Internal,
+}
+
+impl Attributes {
+    /// Adds in what another declaration of the same thing asked for
+    pub fn merge(&mut self, other: &Attributes) {
+        self.noreturn |= other.noreturn;
+        self.nodiscard |= other.nodiscard;
+        self.unused |= other.unused;
+        self.packed |= other.packed;
+        self.weak |= other.weak;
+        self.constructor |= other.constructor;
+        self.destructor |= other.destructor;
+
+        if other.aligned.is_some() {
+            self.aligned.clone_from(&other.aligned);
+        }
+
+        if other.section.is_some() {
+            self.section.clone_from(&other.section);
+        }
+
+        if other.visibility.is_some() {
+            self.visibility = other.visibility;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(self.noreturn || self.nodiscard || self.unused || self.packed
+            || self.aligned.is_some() || self.has_symbol_only()
+            || self.constructor || self.destructor)
+    }
+
+    /// Whether any of these only make sense on functions
+    pub fn has_function_only(&self) -> bool {
+        self.noreturn || self.nodiscard || self.constructor || self.destructor
+    }
+
+    /// Whether any of these change how the symbol gets emitted, which only
+    /// matters for things with linkage
+    pub fn has_symbol_only(&self) -> bool {
+        self.section.is_some() || self.weak || self.visibility.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{Context, Span}, error::CompilerError, fs::read_file, parser::parse};
+
+    /// Verifies the file, giving back the messages it warned about
+    fn verify_file(ending: &str) -> Result<Vec<String>, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/attributes/");
+        p.push(ending);
+
+        let mut context = Context::new();
+        parse(&read_file(&p)?)?.verify(&mut context)?;
+
+        Ok(context.take_warnings().into_iter().map(|w| w.message).collect())
+    }
+
+    #[test]
+    fn attributes() -> Result<(), CompilerError> {
+        // Only ones we don't know anything about get warned about
+        assert_eq!(verify_file("attributes.c")?, ["Ignoring unknown attribute 'cold'"]);
+        Ok(())
+    }
+
+    #[test]
+    fn warnings() -> Result<(), CompilerError> {
+        assert_eq!(verify_file("warnings.c")?, [
+            "Ignoring unknown attribute 'frobnicate'",
+            "Ignoring attribute 'weak', which does nothing here",
+            "Only functions can be noreturn, nodiscard, constructors or destructors, so the attribute is ignored",
+            "Only structs can be packed, so the attribute is ignored",
+            "Attributes on typedefs are ignored",
+            "Only names with linkage can have a section, visibility or be weak, so the attribute is ignored",
+            "Ignoring the result of 'compute', which is nodiscard",
+            "'never' is never used",
+            "'counter' is never used",
+            "Returning from a noreturn function",
+            "'helper' is never used",
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn located_warnings() -> Result<(), CompilerError> {
+        // The parser's warnings point at the attribute they're about
+        let input = "int x __attribute__((frobnicate));";
+        let mut context = Context::new();
+        parse(input)?.verify(&mut context)?;
+
+        let warnings = context.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].span, Span::new(21, 31));
+        Ok(())
+    }
+
+    #[test]
+    fn bad_visibility() {
+        let e = verify_file("bad_visibility.c").expect_err("Not a visibility");
+        assert!(matches!(e, CompilerError::ParseError(_)));
+    }
+}
@@ -1,35 +1,121 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::{HashMap, HashSet}};
 
-use crate::error::CompilerError;
+use crate::error::{CompilerError, Warning};
 
-use super::Type;
+use super::{attributes::Attributes, declaration::Linkage, layout::{Constants, Layouts, TagEntry}, Span, Target, Type};
 
 #[derive(Debug)]
 pub struct Context<'a> {
     scope: HashMap<String, Type>,
 
+    // Values of the enum constants in `scope`
+    constants: HashMap<String, i64>,
+
+    // Linkage of the names in `scope` that have one
+    linkages: HashMap<String, Linkage>,
+
+    // Names in `linkages` that have been defined (not just declared)
+    definitions: HashSet<String>,
+
+    // Names in `scope` declared `register`, which can't have their address
+    // taken
+    registers: HashSet<String>,
+
+    // Names in `scope` that are the function's parameters, which live in
+    // registers rather than memory
+    parameters: HashSet<String>,
+
+    // Attributes of the names in `scope`, from every declaration of them
+    attributes: HashMap<String, Attributes>,
+
+    // Names in `scope` that haven't been used yet, but should be, and where
+    // they were declared. Uses can come from inner contexts, which only have
+    // a shared reference to us
+    unused: RefCell<HashMap<String, Span>>,
+
+    // Struct and union tags live in their own namespace
+    tags: HashMap<String, TagEntry>,
+
     // None - We're not in a function
     // Some(T) - We're in a function that returns T
     function_return: Option<Type>,
 
+    // Whether the function we're in takes a `...`
+    variadic: bool,
+
+    // Whether the function we're in is `noreturn`
+    noreturn: bool,
+
+    // Whether a `break` is allowed here (we're in a loop or switch)
+    breakable: bool,
+
+    // Whether a `case` or `default` is allowed here (we're in a switch)
+    in_switch: bool,
+
+    // Warnings from anywhere in the file, which only the outermost context
+    // keeps
+    warnings: RefCell<Vec<Warning>>,
+
+    // What we're compiling for, which decides e.g. whether `char` is signed
+    target: Target,
+
     parent: Option<&'a Context<'a>>
 }
 
+impl Default for Context<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> Context<'a> {
     pub fn new() -> Self {
         Self {
             scope: HashMap::new(),
+            constants: HashMap::new(),
+            linkages: HashMap::new(),
+            definitions: HashSet::new(),
+            registers: HashSet::new(),
+            parameters: HashSet::new(),
+            attributes: HashMap::new(),
+            unused: RefCell::new(HashMap::new()),
+            tags: HashMap::new(),
             function_return: None,
+            variadic: false,
+            noreturn: false,
+            breakable: false,
+            in_switch: false,
+            warnings: RefCell::new(vec![]),
+            target: Target::default(),
             parent: None,
         }
     }
 
+    /// A new outermost context, for a target other than the default
+    pub fn for_target(target: Target) -> Self {
+        Self { target, ..Self::new() }
+    }
+
     /// Creates a new "inner" context with this one as its parent. Used when
     /// entering a new, inner scope
     pub fn inner(&'a self) -> Self {
         Self {
             scope: HashMap::new(),
+            constants: HashMap::new(),
+            linkages: HashMap::new(),
+            definitions: HashSet::new(),
+            registers: HashSet::new(),
+            parameters: HashSet::new(),
+            attributes: HashMap::new(),
+            unused: RefCell::new(HashMap::new()),
+            tags: HashMap::new(),
             function_return: None,
+            variadic: false,
+            noreturn: false,
+            breakable: false,
+            in_switch: false,
+            warnings: RefCell::new(vec![]),
+            target: self.target,
             parent: Some(self),
         }
     }
@@ -46,6 +132,164 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    /// Adds a name with linkage. Unlike other names, these can be declared
+    /// more than once in the same scope, as long as every declaration agrees
+    /// and there's at most one definition
+    pub fn add_linked_name(&mut self, n: String, t: Type, linkage: Linkage,
+        defines: bool) -> Result<(), CompilerError> {
+
+        let previous = match self.scope.get(&n) {
+            Some(_) if !self.linkages.contains_key(&n) =>
+                return Err(CompilerError::SemanticError("Name defined twice within scope")),
+            Some(previous) => Some(previous),
+            None => self.parent.and_then(|p| p.lookup_linkage(&n))
+                .and_then(|_| self.get_type(&n)),
+        };
+
+        if previous.is_some_and(|p| !p.is_compatible(&t)) {
+            return Err(CompilerError::SemanticError("Conflicting types for redeclaration"));
+        }
+
+        // e.g. `int f();` doesn't forget what an earlier prototype said
+        let t = match previous {
+            Some(p) => t.composite(p),
+            None => t,
+        };
+
+        match (self.linkages.get(&n), linkage) {
+            (Some(Linkage::External), Linkage::Internal) =>
+                return Err(CompilerError::SemanticError("Static declaration follows non-static")),
+            (Some(Linkage::Internal), Linkage::External) =>
+                return Err(CompilerError::SemanticError("Non-static declaration follows static")),
+            _ => (),
+        }
+
+        if defines && !self.definitions.insert(n.clone()) {
+            return Err(CompilerError::SemanticError("Name defined twice"));
+        }
+
+        // Block scope `extern`s refer back to the same thing as outside, so
+        // they get its attributes too
+        if !self.attributes.contains_key(&n)
+            && let Some(inherited) = self.parent
+                .filter(|p| p.lookup_linkage(&n).is_some())
+                .and_then(|p| p.get_attributes(&n)) {
+
+            self.attributes.insert(n.clone(), inherited.clone());
+        }
+
+        self.scope.insert(n.clone(), t);
+        self.linkages.insert(n, linkage);
+
+        Ok(())
+    }
+
+    /// Gets the linkage of whatever the name refers to here, if it has one
+    pub fn lookup_linkage(&self, n: &str) -> Option<Linkage> {
+        // Whichever scope has the name decides, so locals hide linkage
+        if self.scope.contains_key(n) {
+            return self.linkages.get(n).copied();
+        }
+
+        self.parent.and_then(|p| p.lookup_linkage(n))
+    }
+
+    pub fn mark_register(&mut self, n: String) {
+        self.registers.insert(n);
+    }
+
+    pub fn is_register(&self, n: &str) -> bool {
+        if self.scope.contains_key(n) {
+            return self.registers.contains(n);
+        }
+
+        self.parent.is_some_and(|p| p.is_register(n))
+    }
+
+    pub fn mark_parameter(&mut self, n: String) {
+        self.parameters.insert(n);
+    }
+
+    pub fn is_parameter(&self, n: &str) -> bool {
+        if self.scope.contains_key(n) {
+            return self.parameters.contains(n);
+        }
+
+        self.parent.is_some_and(|p| p.is_parameter(n))
+    }
+
+    /// Adds what this declaration's attributes asked for to those of earlier
+    /// ones, e.g. `static void f(void) __attribute__((constructor));`
+    pub fn add_attributes(&mut self, n: &str, attributes: &Attributes) {
+        self.attributes.entry(n.to_string())
+            .or_default()
+            .merge(attributes);
+    }
+
+    /// Gets the attributes of whatever the name refers to here
+    pub fn get_attributes(&self, n: &str) -> Option<&Attributes> {
+        // Whichever scope has the name decides, same as for its type
+        if self.scope.contains_key(n) {
+            return self.attributes.get(n);
+        }
+
+        self.parent.and_then(|p| p.get_attributes(n))
+    }
+
+    /// Has `warn_unused` warn about the name if nothing uses it
+    pub fn expect_use(&mut self, n: String, span: Span) {
+        self.unused.borrow_mut().insert(n, span);
+    }
+
+    pub fn mark_used(&self, n: &str) {
+        if self.scope.contains_key(n) {
+            self.unused.borrow_mut().remove(n);
+
+            // A block scope `extern` refers to the same thing as any
+            // declaration outside with linkage, so that's been used too
+            if self.linkages.contains_key(n) && let Some(p) = self.parent {
+                p.mark_linked_used(n);
+            }
+        } else if let Some(p) = self.parent {
+            p.mark_used(n);
+        }
+    }
+
+    /// Marks the declarations of a name with linkage as used, skipping over
+    /// anything without linkage that happens to share its name
+    fn mark_linked_used(&self, n: &str) {
+        if self.linkages.contains_key(n) {
+            self.unused.borrow_mut().remove(n);
+        }
+
+        if let Some(p) = self.parent {
+            p.mark_linked_used(n);
+        }
+    }
+
+    /// Warns about the names in this scope that never got used. Call once
+    /// everything that could use them has been checked
+    pub fn warn_unused(&self) {
+        let mut unused: Vec<_> = self.unused.borrow_mut().drain().collect();
+        unused.sort_by_key(|(_, span)| span.start);
+
+        for (n, span) in unused {
+            self.warn(Warning::new(format!("'{}' is never used", n), span));
+        }
+    }
+
+    pub fn warn(&self, warning: Warning) {
+        match self.parent {
+            Some(p) => p.warn(warning),
+            None => self.warnings.borrow_mut().push(warning),
+        }
+    }
+
+    /// Everything we've warned about so far, in the order we found them
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.warnings.take()
+    }
+
     pub fn get_type(&self, n: &str) -> Option<&Type> {
         match self.scope.get(n) {
             Some(t) => Some(t),
@@ -77,4 +321,93 @@ impl<'a> Context<'a> {
 
         Ok(())
     }
+
+    /// Marks this context as the body of a variadic function, allowing
+    /// `va_start`
+    pub fn set_variadic(&mut self) {
+        self.variadic = true;
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        self.variadic || self.parent.is_some_and(|p| p.is_variadic())
+    }
+
+    /// Marks this context as the body of a function that shouldn't return
+    pub fn set_noreturn(&mut self) {
+        self.noreturn = true;
+    }
+
+    pub fn is_noreturn(&self) -> bool {
+        self.noreturn || self.parent.is_some_and(|p| p.is_noreturn())
+    }
+
+    /// Marks this context as the body of a loop, allowing `break`
+    pub fn enter_loop(&mut self) {
+        self.breakable = true;
+    }
+
+    /// Marks this context as the body of a switch, allowing `break`, `case`
+    /// and `default`
+    pub fn enter_switch(&mut self) {
+        self.breakable = true;
+        self.in_switch = true;
+    }
+
+    pub fn can_break(&self) -> bool {
+        self.breakable || self.parent.is_some_and(|p| p.can_break())
+    }
+
+    pub fn in_switch(&self) -> bool {
+        self.in_switch || self.parent.is_some_and(|p| p.in_switch())
+    }
+}
+
+impl Constants for Context<'_> {
+    fn lookup_constant(&self, name: &str) -> Option<i64> {
+        // Whichever scope has the name decides, so variables can shadow
+        // constants and vice versa
+        if self.scope.contains_key(name) {
+            return self.constants.get(name).copied();
+        }
+
+        self.parent.and_then(|p| p.lookup_constant(name))
+    }
+
+    fn lookup_type(&self, name: &str) -> Option<Type> {
+        self.get_type(name).cloned()
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+}
+
+impl Layouts for Context<'_> {
+    fn lookup_tag(&self, tag: &str) -> Option<TagEntry> {
+        match self.tags.get(tag) {
+            Some(t) => Some(t.clone()),
+            None => match self.parent {
+                Some(p) => p.lookup_tag(tag),
+                None => None,
+            }
+        }
+    }
+
+    fn lookup_tag_in_scope(&self, tag: &str) -> Option<TagEntry> {
+        self.tags.get(tag).cloned()
+    }
+
+    fn insert_tag(&mut self, tag: String, entry: TagEntry) {
+        self.tags.insert(tag, entry);
+    }
+
+    fn insert_constant(&mut self, name: String, value: i64) ->
+        Result<(), CompilerError> {
+
+        // Enum constants are ints
+        self.add_name(name.clone(), Type::Int32)?;
+        self.constants.insert(name, value);
+
+        Ok(())
+    }
 }
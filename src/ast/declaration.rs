@@ -1,48 +1,229 @@
 use log::trace;
 
-use crate::error::CompilerError;
+use crate::error::{CompilerError, Warning};
 
-use super::{Context, Expression, Statement, Type};
+use super::{attributes::Attributes, expressions::ExpressionKind, initializer::{resolve_initializer, InitItem, ItemTypes}, layout::{declare_tag_only, declare_types, Layouts}, statements::verify_labels, Context, Expression, Span, Statement, Type};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Declaration {
-    pub name: String,
+    /// None if we're only declaring a struct, e.g. `struct foo { ... };`
+    pub name: Option<String>,
     pub type_of: Type,
-    pub external: bool,
+
+    /// The storage class specifier it was declared with, if any
+    pub storage: Option<StorageClass>,
+
+    /// What any `_Alignas` specifiers asked for
+    pub alignments: Vec<Alignment>,
+
+    /// From `__attribute__((...))` or `[[...]]`, wherever they were written
+    pub attributes: Attributes,
+
+    /// The parser's already swapped uses of the name for the type, so all
+    /// a typedef does from here on out is declare any types it defines
+    pub typedef: bool,
+
     pub value: Option<DeclarationValue>,
+
+    /// Where the name was declared, or the whole declaration if there's no name
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageClass {
+    Extern,
+    Static,
+    Auto,
+    Register,
+}
+
+/// An `_Alignas` specifier, which either gives the alignment directly, e.g.
+/// `_Alignas(16)`, or copies a type's, e.g. `_Alignas(long)`
+#[derive(Debug, Clone)]
+pub enum Alignment {
+    Expression(Expression),
+    Type(Type),
+}
+
+impl Alignment {
+    /// Works out the alignment in bytes. Zero means it has no effect
+    pub fn eval(&self, layouts: &impl Layouts) -> Result<usize, CompilerError> {
+        match self {
+            Self::Type(t) => t.align_of(layouts)
+                .ok_or(CompilerError::SemanticError("Can't take the alignment of an incomplete type")),
+
+            Self::Expression(e) => match usize::try_from(e.eval_constant(layouts)?) {
+                Ok(align) if align == 0 || align.is_power_of_two() => Ok(align),
+                _ => Err(CompilerError::SemanticError("Alignment must be a power of two")),
+            },
+        }
+    }
+
+    /// Works out the strictest of several specifiers, which is the one that
+    /// counts. Zero means none of them have any effect
+    pub fn strictest(alignments: &[Alignment], layouts: &impl Layouts) ->
+        Result<usize, CompilerError> {
+
+        alignments.iter().try_fold(0, |align, a| Ok(align.max(a.eval(layouts)?)))
+    }
+}
+
+/// Whether other declarations of a name refer to the same thing. Internal
+/// linkage (from `static`) means only within this file, so the symbol isn't
+/// exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    External,
+    Internal,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DeclarationValue {
     Variable(Expression),
+    List(Box<[InitItem]>),
     Function(Box<[Statement]>),
+
+    /// A `_Static_assert`, which doesn't declare anything
+    StaticAssert(StaticAssert),
+}
+
+/// `_Static_assert(condition, "message")`, or C23's `static_assert`, where the
+/// message is optional
+#[derive(Debug, Clone)]
+pub struct StaticAssert {
+    pub condition: Expression,
+    pub message: Option<String>,
+}
+
+impl StaticAssert {
+    fn verify(&self, context: &mut Context) -> Result<(), CompilerError> {
+        // Evaluating it checks it's an integer constant expression, and types
+        // it the way C does (e.g. `sizeof(x) == 16` mixes unsigned long and
+        // int), which verifying it as an ordinary expression would reject
+        match self.condition.eval_constant(context)? {
+            0 => Err(CompilerError::StaticAssertion(self.message.clone())),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Declaration {
     pub fn verify(&self, context: &mut Context) -> Result<(), CompilerError> {
         trace!("Semantically checking {:?}", self);
+        self.verify_declaration(context).map_err(|e| e.at(self.span))
+    }
+
+    fn verify_declaration(&self, context: &mut Context) -> Result<(), CompilerError> {
+        if let Some(DeclarationValue::StaticAssert(x)) = &self.value {
+            return x.verify(context);
+        }
+
+        let name = match &self.name {
+            Some(n) => n,
+            None => return declare_tag_only(&self.type_of, context),
+        };
+
+        declare_types(&self.type_of, context)?;
+
+        if self.typedef {
+            if !self.attributes.is_empty() {
+                context.warn(Warning::new("Attributes on typedefs are ignored", self.span));
+            }
+
+            return Ok(());
+        }
+
+        // Arrays like `int a[] = { ... }` get their length from the list
+        let (type_of, fields) = match &self.value {
+            Some(DeclarationValue::List(items)) => {
+                let types = ItemTypes::verify(items, context)?;
+                let (t, fields) = resolve_initializer(&self.type_of, items, context,
+                    &|e| types.get(e))?;
+                (t, Some((fields, types)))
+            },
+            _ => (self.type_of.clone(), None),
+        };
+
+        let mut requested = 0;
+        for alignment in &self.alignments {
+            requested = requested.max(self.verify_alignment(alignment, &type_of, context)?);
+        }
+
+        if let Some(natural) = type_of.align_of(context)
+            && requested != 0 && requested < natural {
+
+            return Err(CompilerError::SemanticError("_Alignas can't weaken alignment"));
+        }
 
-        context.add_name(self.name.clone(), self.type_of.clone())?;
+        let linkage = self.linkage(context)?;
+        self.verify_attributes(&type_of, linkage, context)?;
 
-        match (self.external, &self.value) {
+        match &type_of {
+            Type::Function(f) => {
+                let by_value = f.args.iter()
+                    .map(|(_, t)| t)
+                    .chain([&f.return_type])
+                    .any(|t| matches!(t.unqualified(), Type::Struct(_)));
+
+                if by_value {
+                    return Err(CompilerError::NotSupported("Passing structs by value"));
+                }
+            },
+
+            t => if self.storage != Some(StorageClass::Extern)
+                && t.size_of(context).is_none() {
+                return Err(CompilerError::SemanticError("Variable has incomplete type"));
+            },
+        }
+
+        // Only names nothing else could use get warned about, which are
+        // locals and statics. Later declarations can still say they're unused
+        let first = context.get_type(name).is_none();
+        if first && linkage != Some(Linkage::External) {
+            context.expect_use(name.clone(), self.span);
+        }
+
+        // File scope variables without an initializer are only tentative
+        // definitions, which any number of declarations can share
+        match linkage {
+            Some(linkage) => context.add_linked_name(name.clone(),
+                type_of.clone(), linkage, self.value.is_some())?,
+            None => context.add_name(name.clone(), type_of.clone())?,
+        }
+
+        context.add_attributes(name, &self.attributes);
+        let attributes = context.get_attributes(name).cloned().unwrap_or_default();
+
+        // Constructors and destructors get called for us
+        if attributes.unused || attributes.constructor || attributes.destructor {
+            context.mark_used(name);
+        }
+
+        if self.storage == Some(StorageClass::Register) {
+            context.mark_register(name.clone());
+        }
+
+        // Statics get initialized before the program runs, same as globals
+        let is_static = context.return_type().is_none()
+            || self.storage == Some(StorageClass::Static);
+
+        let external = self.storage == Some(StorageClass::Extern);
+
+        match (external, &self.value) {
             // If its just a declaration (not definition), nothing to verify
             (_, None) => (),
 
             (false, Some(DeclarationValue::Variable(expr))) => {
-                let is_global = context.return_type().is_none();
-
-                if is_global {
-                    match expr {
-                        Expression::IntLiteral(_) => (),
-                        Expression::CharLiteral(_) => (),
-                        Expression::StringLiteral(_) => (),
-                        _ => return Err(CompilerError::SemanticError("Global variable assignment must be a literal\nConstant folding isn't currently supported")),
-                    }
-                } else {
-                    let t = expr.verify(context)?;
-                    if t != self.type_of {
-                        return Err(CompilerError::SemanticError("Declaration type must match"));
-                    }
+                let t = expr.verify(context)?;
+                verify_initializer(expr, &t, &self.type_of, is_static, context)?;
+            },
+
+            (false, Some(DeclarationValue::List(_))) => {
+                let (fields, types) = fields.expect("Lists get resolved");
+
+                for field in fields {
+                    verify_initializer(field.value, &types.get(field.value),
+                        &field.type_of, is_static, context)?;
                 }
             },
 
@@ -52,22 +233,328 @@ impl Declaration {
                 if let Type::Function(f) = &self.type_of {
                     inner.set_return_type(f.return_type.clone())?;
 
+                    if f.variadic {
+                        inner.set_variadic();
+                    }
+
+                    if attributes.noreturn {
+                        inner.set_noreturn();
+                    }
+
                     for (arg_n, arg_t) in &f.args {
                         inner.add_name(arg_n.clone(), arg_t.clone())?;
+                        inner.mark_parameter(arg_n.clone());
                     }
 
                     for stmt in stmts {
                         stmt.verify(&mut inner)?;
                     }
+
+                    inner.warn_unused();
+                    verify_labels(stmts)?;
                 } else {
                     panic!("Encountered weird enum varient");
                 }
             },
 
+            (_, Some(DeclarationValue::StaticAssert(_))) =>
+                panic!("Static asserts get checked before anything else"),
+
             (true, Some(_)) => return Err(CompilerError::SemanticError("Can't define extern ")),
         };
 
         Ok(())
     }
+
+    /// Works out the linkage of the declared name, following C's rules for
+    /// what each storage class means at file and block scope. None means it
+    /// has no linkage, like a local variable
+    fn linkage(&self, context: &Context) -> Result<Option<Linkage>, CompilerError> {
+        let file_scope = context.return_type().is_none();
+        let is_function = matches!(self.type_of, Type::Function(_));
+
+        // `extern` (and functions, which are implicitly extern) refer back
+        // to whatever's already visible, so `static` on an earlier
+        // declaration carries over
+        let inherited = || self.name.as_ref()
+            .and_then(|n| context.lookup_linkage(n))
+            .unwrap_or(Linkage::External);
+
+        let linkage = match (self.storage, file_scope, is_function) {
+            (Some(StorageClass::Static), true, _) => Some(Linkage::Internal),
+            (Some(StorageClass::Static), false, true) =>
+                return Err(CompilerError::SemanticError("Functions in a block can't be static")),
+            (Some(StorageClass::Static), false, false) => None,
+
+            (Some(StorageClass::Extern), _, _) | (None, _, true) => Some(inherited()),
+
+            (None, true, false) => Some(Linkage::External),
+            (None, false, false) => None,
+
+            (Some(StorageClass::Auto | StorageClass::Register), true, _) =>
+                return Err(CompilerError::SemanticError("auto and register aren't allowed outside functions")),
+            (Some(StorageClass::Auto | StorageClass::Register), false, true) =>
+                return Err(CompilerError::SemanticError("Functions can't be auto or register")),
+            (Some(StorageClass::Auto | StorageClass::Register), false, false) => None,
+        };
+
+        Ok(linkage)
+    }
+
+    /// Checks the alignment can apply to this declaration, and works it out
+    fn verify_alignment(&self, alignment: &Alignment, type_of: &Type,
+        context: &mut Context) -> Result<usize, CompilerError> {
+
+        if let Alignment::Type(t) = alignment {
+            declare_types(t, context)?;
+        }
+
+        if let Type::Function(_) = type_of {
+            return Err(CompilerError::SemanticError("Functions can't be aligned"));
+        }
+
+        if self.storage == Some(StorageClass::Register) {
+            return Err(CompilerError::SemanticError("Register variables can't be aligned"));
+        }
+
+        let requested = alignment.eval(context)?;
+
+        // Locals are only aligned as well as RBP is
+        let on_stack = context.return_type().is_some()
+            && !matches!(self.storage, Some(StorageClass::Static | StorageClass::Extern));
+
+        if on_stack && requested > 16 {
+            return Err(CompilerError::NotSupported("Aligning locals to more than 16 bytes"));
+        }
+
+        Ok(requested)
+    }
+
+    /// Warns about attributes that don't do anything here, and checks the
+    /// ones that do
+    fn verify_attributes(&self, type_of: &Type, linkage: Option<Linkage>,
+        context: &mut Context) -> Result<(), CompilerError> {
+
+        let is_function = matches!(type_of, Type::Function(_));
+        let mut ignored = vec![];
+
+        if !is_function && self.attributes.has_function_only() {
+            ignored.push("Only functions can be noreturn, nodiscard, constructors or destructors");
+        }
+
+        if linkage.is_none() && self.attributes.has_symbol_only() {
+            ignored.push("Only names with linkage can have a section, visibility or be weak");
+        }
+
+        if self.attributes.packed {
+            ignored.push("Only structs can be packed");
+        }
+
+        if let Some(aligned) = &self.attributes.aligned {
+            if is_function {
+                ignored.push("Functions can't be aligned");
+            } else {
+                // Unlike `_Alignas`, asking for less than it needs does nothing
+                self.verify_alignment(&Alignment::Expression(aligned.clone()),
+                    type_of, context)?;
+            }
+        }
+
+        for message in ignored {
+            context.warn(Warning::new(format!("{}, so the attribute is ignored", message),
+                self.span));
+        }
+
+        Ok(())
+    }
+
+    /// How the declared object (of type `type_of`, in case an initializer
+    /// completed it) has to be aligned, which `_Alignas` and the `aligned`
+    /// attribute can make stricter than its type's alignment. None if it isn't
+    /// an object
+    pub fn align_of(&self, type_of: &Type, layouts: &impl Layouts) -> Option<usize> {
+        let natural = type_of.align_of(layouts)?;
+
+        let requested = Alignment::strictest(&self.alignments, layouts).ok()?;
+
+        let attribute = match &self.attributes.aligned {
+            Some(e) => Alignment::Expression(e.clone()).eval(layouts).ok()?,
+            None => 0,
+        };
+
+        Some(natural.max(requested).max(attribute))
+    }
 }
 
+/// Checks that `expr`, which has already been verified as being of type
+/// `expr_t`, can initialize a variable of type `type_of`. Statics have to be
+/// initialized with constants, since they're set before the program runs
+pub fn verify_initializer(
+    expr: &Expression,
+    expr_t: &Type,
+    type_of: &Type,
+    is_static: bool,
+    context: &Context
+) -> Result<(), CompilerError> {
+
+    // Only a string literal can initialize an array without a list, which
+    // resolving the initializer already checked
+    if let Type::Array(_) = type_of {
+        return match expr.kind {
+            ExpressionKind::StringLiteral(_) => Ok(()),
+            _ => Err(CompilerError::SemanticError("Arrays need an initializer list or a string literal")),
+        };
+    }
+
+    // Integer constants get converted to whatever they initialize, e.g. in
+    // `long l = 1;` or `char *p = 0;`
+    let constant = expr_t.is_integer() && expr.eval_constant(context)
+        .is_ok_and(|value| type_of.is_integer()
+            || matches!(type_of.unqualified(), Type::Pointer(_)) && value == 0);
+
+    if !constant {
+        type_of.check_assign(expr_t, "Declaration type must match")?;
+    }
+
+    if !is_static {
+        return Ok(());
+    }
+
+    // Nothing of struct type is a constant, so those need a list
+    if !type_of.is_scalar() {
+        return Err(CompilerError::SemanticError("Static structs and unions need an initializer list"));
+    }
+
+    match expr.kind {
+        ExpressionKind::StringLiteral(_) => Ok(()),
+        _ if constant => Ok(()),
+        _ if type_of.is_integer() => expr.eval_constant(context).map(|_| ()),
+        _ => Err(CompilerError::NotSupported("Static pointers initialized with anything but a string literal")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::Target, fs::read_file, parser::{parse, parse_with_mode, LanguageMode}};
+
+    use super::*;
+
+    fn verify_file(ending: &str) -> Result<(), CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/declaration/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(&mut Context::new())
+    }
+
+    fn expect_semantic_error(ending: &str) -> Result<(), CompilerError> {
+        match verify_file(ending) {
+            Ok(_) => panic!("Verified when we shouldn't have"),
+            Err(e) => match e.root() {
+                CompilerError::SemanticError(_) => Ok(()),
+                _ => Err(e)
+            }
+        }
+    }
+
+    #[test]
+    fn linkage() -> Result<(), CompilerError> {
+        verify_file("linkage.c")
+    }
+
+    #[test]
+    fn static_follows_extern() -> Result<(), CompilerError> {
+        expect_semantic_error("static_follows_extern.c")
+    }
+
+    #[test]
+    fn nonstatic_follows_static() -> Result<(), CompilerError> {
+        expect_semantic_error("nonstatic_follows_static.c")
+    }
+
+    #[test]
+    fn redefined() -> Result<(), CompilerError> {
+        expect_semantic_error("redefined.c")
+    }
+
+    #[test]
+    fn conflicting_types() -> Result<(), CompilerError> {
+        expect_semantic_error("conflicting_types.c")
+    }
+
+    #[test]
+    fn register_address() -> Result<(), CompilerError> {
+        expect_semantic_error("register_address.c")
+    }
+
+    #[test]
+    fn static_block_function() -> Result<(), CompilerError> {
+        expect_semantic_error("static_block_function.c")
+    }
+
+    #[test]
+    fn static_assert() -> Result<(), CompilerError> {
+        verify_file("static_assert.c")
+    }
+
+    #[test]
+    fn c23_static_assert() -> Result<(), CompilerError> {
+        let input = read_file(&PathBuf::from("tests/files/unit/declaration/c23_static_assert.c"))?;
+        assert!(parse(&input).is_err());
+
+        parse_with_mode(&input, LanguageMode::C23)?.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn failed_static_assert() {
+        // The message's escapes get decoded
+        match verify_file("failed_static_assert.c").map_err(|e| e.root().to_string()) {
+            Err(e) => assert_eq!(e, "Static Assertion Failed: small isn't\tpadded"),
+            Ok(_) => panic!("Verified when we shouldn't have"),
+        }
+    }
+
+    #[test]
+    fn char_constants() -> Result<(), CompilerError> {
+        verify_file("char_constants.c")?;
+
+        // Whether plain char is signed comes from the target
+        let input = read_file(&PathBuf::from("tests/files/unit/declaration/char_constants.c"))?;
+        let unsigned = Target { char_signed: false, ..Target::default() };
+
+        match parse(&input)?.verify(&mut Context::for_target(unsigned)) {
+            Err(e) => assert_eq!(e.root().to_string(), "Static Assertion Failed: plain char is signed"),
+            Ok(_) => panic!("Verified when we shouldn't have"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn multichar_constants() -> Result<(), CompilerError> {
+        let input = read_file(&PathBuf::from("tests/files/unit/declaration/multichar.c"))?;
+        let mut context = Context::new();
+        parse(&input)?.verify(&mut context)?;
+
+        // They're allowed, like in GCC, but still get warned about
+        let warnings: Vec<String> = context.take_warnings().into_iter()
+            .map(|w| w.message)
+            .collect();
+
+        assert_eq!(warnings, [
+            "Multi-character character constant",
+            "Multi-character character constant",
+            "Multi-character character constant",
+            "Character constant too long for its type",
+            "Multi-character character constant",
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn nonconstant_static_assert() -> Result<(), CompilerError> {
+        expect_semantic_error("nonconstant_static_assert.c")
+    }
+}
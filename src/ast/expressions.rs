@@ -1,13 +1,25 @@
+use std::cell::{Cell, OnceCell};
+use std::cmp::Ordering;
+
 use log::trace;
 
 use crate::error::CompilerError;
 
-use super::{Context, Type};
+use super::{declaration::verify_initializer, initializer::{resolve_initializer, InitItem, ItemTypes}, layout::{declare_types, Constants, Layouts}, statements::StatementKind, types::ArrayType, Context, Span, Statement, Target, Type};
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+// TODO: +x, float literals
+#[derive(Debug, Clone)]
+pub enum ExpressionKind {
+    Comma(Box<BinaryExpr>),     // x, y
 
-// TODO: Struct/pointer subfield accessing, +x, float literals
-#[derive(Debug)]
-pub enum Expression {
     Assignment(Box<BinaryExpr>),// x = y
+    CompoundAssignment(Box<CompoundAssignExpr>), // x += y, x <<= y, etc
 
     Ternary(Box<TernaryExpr>),  // x ? y : z
 
@@ -49,19 +61,136 @@ pub enum Expression {
     LogicalNot(Box<UnaryExpr>), // !x
 
     SizeOf(Box<UnaryExpr>),     // sizeof x
+    SizeOfType(Box<Type>),      // sizeof(int)
+    AlignOf(Box<Type>),         // _Alignof(int)
 
     ArrayIndex(Box<BinaryExpr>),// x[y]
+    Member(Box<MemberExpr>),    // x.y
+    PointerMember(Box<MemberExpr>), // x->y
     
     FuncCall(Box<FuncCallExpr>),// x(...)
+    CompoundLiteral(Box<CompoundLiteralExpr>), // (int[]){1, 2}
+
+    VaStart(Box<BinaryExpr>),   // __builtin_va_start(ap, last)
+    VaArg(Box<VaArgExpr>),      // __builtin_va_arg(ap, int)
+    VaEnd(Box<UnaryExpr>),      // __builtin_va_end(ap)
+    VaCopy(Box<BinaryExpr>),    // __builtin_va_copy(dest, src)
+
+    Generic(Box<GenericExpr>),  // _Generic(x, int: y, default: z)
+    StatementExpr(Box<StatementExpr>), // ({ int x = y; x * 2; })
     
     Identifier(String),         // x
 
     IntLiteral(u64),            // 123
-    CharLiteral(u8),            // 'a'
-    StringLiteral(String),      // "hello world\n"
+    CharLiteral(CharLiteralExpr), // 'a'
+    StringLiteral(Box<StringLiteralExpr>), // "hello world\n"
+}
+
+/// Which prefix a char or string literal has, which decides how its
+/// characters are encoded and what type each code unit is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Plain,  // "a", kept as the source's UTF-8
+    Utf8,   // u8"a"
+    Utf16,  // u"a"
+    Utf32,  // U"a"
+    Wide,   // L"a"
+}
+
+impl Encoding {
+    pub fn from_prefix(prefix: &str) -> Self {
+        match prefix {
+            "u8" => Self::Utf8,
+            "u" => Self::Utf16,
+            "U" => Self::Utf32,
+            "L" => Self::Wide,
+            _ => Self::Plain,
+        }
+    }
+
+    /// The type of each code unit. `u8` ones are plain chars like before C23,
+    /// and `wchar_t` depends on the target
+    pub fn element_type(&self, target: &Target) -> Type {
+        match self {
+            Self::Plain | Self::Utf8 => Type::Char,
+            Self::Utf16 => Type::Uint16,
+            Self::Utf32 => Type::Uint32,
+            Self::Wide if target.wchar_utf16 => Type::Uint16,
+            Self::Wide => Type::Int32,
+        }
+    }
+
+    /// The biggest value a code unit can hold
+    pub fn max_unit(&self, target: &Target) -> u32 {
+        match self.element_type(target) {
+            Type::Char => u8::MAX as u32,
+            Type::Uint16 => u16::MAX as u32,
+            _ => u32::MAX,
+        }
+    }
+
+    /// Adds the code units for `c` onto the end of `units`
+    pub fn encode(&self, c: char, target: &Target, units: &mut Vec<u32>) {
+        match self.max_unit(target) {
+            0xff => units.extend(c.encode_utf8(&mut [0; 4]).bytes().map(u32::from)),
+            0xffff => units.extend(c.encode_utf16(&mut [0; 2]).iter().map(|u| *u as u32)),
+            _ => units.push(c as u32),
+        }
+    }
+}
+
+/// A char literal, holding a single code unit of its encoding
+#[derive(Debug, Clone, Copy)]
+pub struct CharLiteralExpr {
+    pub value: u32,
+    pub encoding: Encoding,
+
+    /// Whether it's a plain one with more than one char, e.g. `'ab'`, which
+    /// GCC packs into an int. `value` is that int, rather than a code unit
+    pub multichar: bool,
+}
+
+impl CharLiteralExpr {
+    /// Its value once it's been converted to its code unit's type, e.g.
+    /// `'\xff'` is -1 when chars are signed
+    pub fn eval(&self, target: &Target) -> i64 {
+        let t = match self.multichar {
+            true => Type::Int32,
+            false => self.encoding.element_type(target),
+        };
+
+        t.convert_constant(self.value as i64, target)
+    }
+
+    /// Plain character constants are ints, while prefixed ones have the type
+    /// of their code unit
+    pub fn type_of(&self, target: &Target) -> Type {
+        match self.encoding {
+            Encoding::Plain => Type::Int32,
+            e => e.element_type(target),
+        }
+    }
 }
 
-#[derive(Debug)]
+/// A string literal (or adjacent ones joined together), as the code units
+/// of its encoding, without the null terminator
+#[derive(Debug, Clone)]
+pub struct StringLiteralExpr {
+    pub units: Box<[u32]>,
+    pub encoding: Encoding,
+}
+
+impl StringLiteralExpr {
+    /// An array of its code units, including the null terminator
+    pub fn type_of(&self, target: &Target) -> Type {
+        Type::Array(Box::new(ArrayType {
+            element: self.encoding.element_type(target),
+            length: Some(ExpressionKind::IntLiteral(self.units.len() as u64 + 1).into()),
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TernaryExpr {
     pub condition: Expression,
     pub true_expr: Expression,
@@ -79,7 +208,7 @@ impl TernaryExpr {
         let true_t = self.true_expr.verify(context)?;
         let false_t = self.false_expr.verify(context)?;
 
-        if true_t != false_t {
+        if !true_t.is_compatible(&false_t) {
             return Err(CompilerError::SemanticError("Ternary arms must have same type"));
         }
 
@@ -87,7 +216,57 @@ impl TernaryExpr {
     }
 }
 
-#[derive(Debug)]
+/// The operators that can be combined with `=`, e.g. `+=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    ShiftLeft,
+    ShiftRight,
+    BitwiseAnd,
+    BitwiseXor,
+    BitwiseOr,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompoundAssignExpr {
+    pub op: AssignOp,
+    pub first: Expression,
+    pub second: Expression,
+}
+
+impl CompoundAssignExpr {
+    pub fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        let first_t = self.first.verify_modifiable(context)?;
+        let second_t = self.second.verify(context)?;
+
+        // A pointer can be moved along by some number of elements
+        if let Type::Pointer(inner) = first_t.unqualified() {
+            if !matches!(self.op, AssignOp::Add | AssignOp::Subtract) || !second_t.is_integer() {
+                return Err(CompilerError::SemanticError("Can only add or subtract an integer from a pointer"));
+            }
+
+            if inner.size_of(context).is_none() {
+                return Err(CompilerError::SemanticError("Pointer arithmetic needs a complete object type"));
+            }
+
+            return Ok(first_t);
+        }
+
+        // Otherwise both sides get converted to a common type, and the result
+        // back to the target's, so any integers will do
+        if !first_t.is_integer() || !second_t.is_integer() {
+            return Err(CompilerError::SemanticError("Binary operator needs integer operands"));
+        }
+
+        Ok(first_t)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct BinaryExpr {
     pub first: Expression,
     pub second: Expression,
@@ -98,15 +277,19 @@ impl BinaryExpr {
         let first_t = self.first.verify(context)?;
         let second_t = self.second.verify(context)?;
 
-        if first_t != second_t {
+        if !first_t.is_compatible(&second_t) {
             return Err(CompilerError::SemanticError("Binary operator types must match"));
         }
 
+        if !first_t.is_scalar() {
+            return Err(CompilerError::SemanticError("Binary operator needs scalar operands"));
+        }
+
         Ok(first_t)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UnaryExpr {
     pub expr: Expression
 }
@@ -118,7 +301,7 @@ impl UnaryExpr {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CastExpr {
     pub cast_to: Type,
     pub expr: Expression,
@@ -126,13 +309,68 @@ pub struct CastExpr {
 
 impl CastExpr {
     pub fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
-        // TODO: Verify that casting to given type is actually allowed
+        declare_types(&self.cast_to, context)?;
+
+        // TODO: Verify that casting from the given type is actually allowed
+        if !self.cast_to.is_scalar() && self.cast_to != Type::Void {
+            return Err(CompilerError::SemanticError("Can only cast to a scalar type"));
+        }
+
         self.expr.verify(context)?;
         Ok(self.cast_to.clone())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct MemberExpr {
+    pub expr: Expression,
+    pub member: String,
+
+    /// Set once verified, since that's when we know which member it is
+    bit_field: Cell<bool>,
+}
+
+impl MemberExpr {
+    pub fn new(expr: Expression, member: String) -> Self {
+        MemberExpr { expr, member, bit_field: Cell::new(false) }
+    }
+
+    pub fn is_bit_field(&self) -> bool {
+        self.bit_field.get()
+    }
+
+    /// Verifies a member access, where `through_pointer` means it's a `->`
+    pub fn verify(&self, context: &mut Context, through_pointer: bool) ->
+        Result<Type, CompilerError> {
+
+        let struct_t = match through_pointer {
+            true => match self.expr.verify(context)? {
+                Type::Pointer(inner) => *inner,
+                _ => return Err(CompilerError::SemanticError("Left side of -> must be a pointer")),
+            },
+            false => self.expr.verify_object(context)?,
+        };
+
+        let tag = match struct_t.unqualified() {
+            Type::Struct(s) => s.tag.clone(),
+            _ => return Err(CompilerError::SemanticError("Member access on a non-struct")),
+        };
+
+        let layout = context.get_layout(&tag)
+            .ok_or(CompilerError::SemanticError("Member access on an incomplete struct"))?;
+
+        // Members of a const struct are const too
+        match layout.get_member(&self.member) {
+            Some(m) => {
+                self.bit_field.set(m.bits.is_some());
+                Ok(m.type_of.clone().qualified(struct_t.qualifiers()))
+            },
+            None => Err(CompilerError::SemanticError("Struct has no member with that name")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct FuncCallExpr {
     pub func: Expression,
     pub args: Box<[Expression]>,
@@ -141,14 +379,38 @@ pub struct FuncCallExpr {
 impl FuncCallExpr {
     pub fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
 
-        let f_type = self.func.verify(context)?;
+        // Functions decay into pointers, so calling one directly is the same
+        // as calling through a pointer
+        let f_type = match self.func.verify(context)? {
+            Type::Pointer(inner) => *inner,
+            t => t,
+        };
 
         if let Type::Function(f) = f_type {
-            for ((_, t_expected), arg) in f.args.iter().zip(&self.args) {
+            // Without a prototype, every arg is like one in a `...`
+            let named = if f.prototyped { f.args.len() } else { 0 };
+
+            if self.args.len() < named {
+                return Err(CompilerError::SemanticError("Too few function args"));
+            }
+
+            if f.prototyped && !f.variadic && self.args.len() > named {
+                return Err(CompilerError::SemanticError("Too many function args"));
+            }
+
+            for ((_, t_expected), arg) in f.args.iter().zip(&self.args[..named]) {
                 let t_actual = arg.verify(context)?;
+                t_expected.check_assign(&t_actual, "Incorrect function args")?;
+            }
 
-                if t_actual != *t_expected {
-                    return Err(CompilerError::SemanticError("Incorrect function args"));
+            // The rest just get promoted, so anything scalar goes
+            for arg in &self.args[named..] {
+                match arg.verify(context)? {
+                    Type::Float | Type::Double =>
+                        return Err(CompilerError::NotSupported("Passing floats without a prototype")),
+                    t if !t.is_scalar() =>
+                        return Err(CompilerError::NotSupported("Passing structs by value")),
+                    _ => (),
                 }
             }
 
@@ -159,6 +421,236 @@ impl FuncCallExpr {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct VaArgExpr {
+    pub list: Expression,
+    pub type_of: Type,
+}
+
+impl VaArgExpr {
+    pub fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        verify_va_list(&self.list, context)?;
+        declare_types(&self.type_of, context)?;
+
+        match self.type_of.unqualified() {
+            Type::Float | Type::Double =>
+                Err(CompilerError::NotSupported("Floating point va_arg")),
+            t if t.is_scalar() => Ok(t.clone()),
+            _ => Err(CompilerError::SemanticError("va_arg needs a scalar type")),
+        }
+    }
+}
+
+/// A compound literal, e.g. `(struct point){ 1, 2 }`. It's an unnamed object
+/// that lives until the end of the block it's in, like any other local
+#[derive(Debug, Clone)]
+pub struct CompoundLiteralExpr {
+    pub type_of: Type,
+    pub items: Box<[InitItem]>,
+}
+
+impl CompoundLiteralExpr {
+    /// Gives back the literal's type, with the length filled in if it's an
+    /// array like `(int[]){ 1, 2 }`
+    fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        declare_types(&self.type_of, context)?;
+
+        if let Type::Function(_) = self.type_of.unqualified() {
+            return Err(CompilerError::SemanticError("Compound literals can't be functions"));
+        }
+
+        let types = ItemTypes::verify(&self.items, context)?;
+        let (type_of, fields) = resolve_initializer(&self.type_of, &self.items, context,
+            &|e| types.get(e))?;
+
+        if type_of.size_of(context).is_none() {
+            return Err(CompilerError::SemanticError("Compound literal has incomplete type"));
+        }
+
+        for field in fields {
+            verify_initializer(field.value, &types.get(field.value), &field.type_of,
+                false, context)?;
+        }
+
+        Ok(type_of)
+    }
+}
+
+/// A GNU statement expression, e.g. `({ int x = f(); x * 2; })`. Its value is
+/// the last statement's, if that's an expression, and `void` otherwise
+#[derive(Debug, Clone)]
+pub struct StatementExpr {
+    pub body: Box<[Statement]>,
+
+    /// Worked out by `verify`, since the value can use names that are only
+    /// in scope inside the body
+    type_of: OnceCell<Type>,
+}
+
+impl StatementExpr {
+    pub fn new(body: Box<[Statement]>) -> Self {
+        StatementExpr { body, type_of: OnceCell::new() }
+    }
+
+    /// The type of its value, once it's been verified
+    pub fn type_of(&self) -> Option<&Type> {
+        self.type_of.get()
+    }
+
+    /// The expression the value comes from, if there is one
+    pub fn value(&self) -> Option<&Expression> {
+        match &self.body.last()?.kind {
+            StatementKind::Expression(expr) => Some(expr),
+            _ => None,
+        }
+    }
+
+    fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        if context.return_type().is_none() {
+            return Err(CompilerError::SemanticError("Statement expressions are only allowed in functions"));
+        }
+
+        if self.has_jumps() {
+            return Err(CompilerError::NotSupported("Labels, gotos and cases in statement expressions"));
+        }
+
+        let mut inner = context.inner();
+
+        let (rest, value) = match self.value() {
+            Some(expr) => (&self.body[..self.body.len() - 1], Some(expr)),
+            None => (&self.body[..], None),
+        };
+
+        for stmt in rest {
+            stmt.verify(&mut inner)?;
+        }
+
+        // Checked on its own, since its result doesn't get ignored
+        let type_of = match value {
+            Some(expr) => expr.verify(&mut inner)?,
+            None => Type::Void,
+        };
+
+        inner.warn_unused();
+
+        Ok(self.type_of.get_or_init(|| type_of).clone())
+    }
+
+    /// Whether anything in the body could jump to a label, or be jumped to
+    /// from outside. Labels are only looked for in statements, not in the
+    /// expressions inside them
+    fn has_jumps(&self) -> bool {
+        let mut found = false;
+
+        for stmt in &self.body {
+            stmt.visit(&mut |s| {
+                found |= matches!(s.kind, StatementKind::Label(_) | StatementKind::Goto(_));
+                true
+            });
+
+            // Any cases in a switch of its own are fine
+            stmt.visit(&mut |s| {
+                found |= matches!(s.kind, StatementKind::Case(_) | StatementKind::Default(_));
+                !matches!(s.kind, StatementKind::Switch(_))
+            });
+        }
+
+        found
+    }
+}
+
+/// A `_Generic` selection. Which association it stands for depends on the
+/// type C gives the controlling expression (see `Expression::c_type`)
+#[derive(Debug, Clone)]
+pub struct GenericExpr {
+    pub controlling: Expression,
+
+    /// Each type along with what to use for it, with `None` for `default`
+    pub associations: Vec<(Option<Type>, Expression)>,
+}
+
+impl GenericExpr {
+    pub fn new(controlling: Expression, associations: Vec<(Option<Type>, Expression)>) -> Self {
+        GenericExpr { controlling, associations }
+    }
+
+    /// The type that decides which association gets picked, or None if the
+    /// controlling expression can't be typed from `scope`
+    pub fn controlling_type(&self, scope: &impl Layouts) -> Option<Type> {
+        self.controlling.c_type(scope)
+    }
+
+    /// Picks the association for a controlling expression of type `t`
+    pub fn select(&self, t: &Type) -> Option<&Expression> {
+        self.associations.iter()
+            .find(|(a, _)| a.as_ref().is_some_and(|a| a.is_compatible(t)))
+            .or_else(|| self.associations.iter().find(|(a, _)| a.is_none()))
+            .map(|(_, expr)| expr)
+    }
+
+    /// Checks the associations, and gives back the type of the one that gets
+    /// picked, without decaying (it could be e.g. an array)
+    fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        self.controlling.verify(context)?;
+        let controlling_t = self.controlling_type(context)
+            .expect("Verified expressions can be typed");
+
+        let mut default = None;
+        let mut matching = None;
+
+        for (i, (t, _)) in self.associations.iter().enumerate() {
+            let Some(t) = t else {
+                if default.replace(i).is_some() {
+                    return Err(CompilerError::SemanticError("_Generic can only have one default"));
+                }
+
+                continue;
+            };
+
+            declare_types(t, context)?;
+
+            if matches!(t, Type::Function(_)) || t.size_of(context).is_none() {
+                return Err(CompilerError::SemanticError("_Generic associations need complete object types"));
+            }
+
+            if self.associations[..i].iter().any(|(other, _)| other.as_ref().is_some_and(|o| o.is_compatible(t))) {
+                return Err(CompilerError::SemanticError("_Generic has more than one association for the same type"));
+            }
+
+            if t.is_compatible(&controlling_t) {
+                matching = Some(i);
+            }
+        }
+
+        let selected = matching.or(default)
+            .ok_or(CompilerError::SemanticError("No _Generic association matches the controlling expression's type"))?;
+
+        // The others never get evaluated, but they still have to make sense
+        let mut selected_t = Type::Void;
+        for (i, (_, expr)) in self.associations.iter().enumerate() {
+            let t = expr.verify_object(context)?;
+
+            if i == selected {
+                selected_t = t;
+            }
+        }
+
+        Ok(selected_t)
+    }
+}
+
+/// Checks that the arg to a `va_` builtin is a `va_list`. Being an array, it's
+/// a pointer by the time we see it, even if it was passed in as a param
+fn verify_va_list(expr: &Expression, context: &mut Context) ->
+    Result<(), CompilerError> {
+
+    if expr.verify(context)? != Type::va_list().decay() {
+        return Err(CompilerError::SemanticError("Expected a va_list"));
+    }
+
+    Ok(())
+}
+
 fn verify_bitshift(expr: &BinaryExpr, context: &mut Context) -> 
     Result<Type, CompilerError> {
 
@@ -172,87 +664,583 @@ fn verify_bitshift(expr: &BinaryExpr, context: &mut Context) ->
     Ok(val_t)
 }
 
+/// Evaluates both operands, converted to their common type
+fn eval_operands(expr: &BinaryExpr, scope: &impl Layouts) ->
+    Result<(i64, i64, Type), CompilerError> {
+
+    let (a, a_t) = expr.first.eval_typed(scope)?;
+    let (b, b_t) = expr.second.eval_typed(scope)?;
+    let common = a_t.common_type(&b_t, scope.target());
+    let target = scope.target();
+
+    Ok((common.convert_constant(a, target), common.convert_constant(b, target), common))
+}
+
+/// Operators whose result wraps around to fit the operands' common type.
+/// Unsigned values are kept as their bits, so these work for either
+fn eval_arithmetic(
+    expr: &BinaryExpr,
+    scope: &impl Layouts,
+    op: fn(i64, i64) -> i64
+) -> Result<(i64, Type), CompilerError> {
+
+    let (a, b, common) = eval_operands(expr, scope)?;
+    Ok((common.convert_constant(op(a, b), scope.target()), common))
+}
+
+fn eval_comparison(
+    expr: &BinaryExpr,
+    scope: &impl Layouts,
+    op: fn(Ordering) -> bool
+) -> Result<(i64, Type), CompilerError> {
+
+    let (a, b, common) = eval_operands(expr, scope)?;
+    let order = match common.is_signed(scope.target()) {
+        true => a.cmp(&b),
+        false => (a as u64).cmp(&(b as u64)),
+    };
+
+    Ok((op(order) as i64, Type::Int32))
+}
+
+fn eval_division(expr: &BinaryExpr, scope: &impl Layouts, remainder: bool) ->
+    Result<(i64, Type), CompilerError> {
+
+    let (a, b, common) = eval_operands(expr, scope)?;
+    if b == 0 {
+        return Err(CompilerError::SemanticError("Division by zero in constant expression"));
+    }
+
+    let value = match (common.is_signed(scope.target()), remainder) {
+        (true, false) => a.wrapping_div(b),
+        (true, true) => a.wrapping_rem(b),
+        (false, false) => ((a as u64) / (b as u64)) as i64,
+        (false, true) => ((a as u64) % (b as u64)) as i64,
+    };
+
+    Ok((common.convert_constant(value, scope.target()), common))
+}
+
+/// Shifts take the type of their (promoted) left operand, and only shift in
+/// the sign bit if it's signed
+fn eval_shift(expr: &BinaryExpr, scope: &impl Layouts, left: bool) ->
+    Result<(i64, Type), CompilerError> {
+
+    let (a, a_t) = expr.first.eval_typed(scope)?;
+    let by = expr.second.eval_constant(scope)? as u32;
+    let t = a_t.promoted();
+    let target = scope.target();
+    let a = t.convert_constant(a, target);
+
+    let value = match (left, t.is_signed(target)) {
+        (true, _) => a.wrapping_shl(by),
+        (false, true) => a.wrapping_shr(by),
+        (false, false) => (a as u64).wrapping_shr(by) as i64,
+    };
+
+    Ok((t.convert_constant(value, target), t))
+}
+
+fn eval_unary(expr: &UnaryExpr, scope: &impl Layouts, op: fn(i64) -> i64) ->
+    Result<(i64, Type), CompilerError> {
+
+    let (value, t) = expr.expr.eval_typed(scope)?;
+    let t = t.promoted();
+    let target = scope.target();
+    Ok((t.convert_constant(op(t.convert_constant(value, target)), target), t))
+}
+
+/// Expressions we make ourselves, rather than parse, don't come from anywhere
+/// in the source
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Self {
+        Expression { kind, span: Span::default() }
+    }
+}
+
 impl Expression {
-    pub fn is_lvalue(&self) -> bool {
-        match self {
-            Self::Identifier(_) | Self::Dereference(_) | Self::ArrayIndex(_)
-                => true,
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Expression { kind, span }
+    }
+
+    /// Evaluates an integer constant expression at compile time (e.g. for case
+    /// labels). Errors if the expression isn't constant
+    pub fn eval_constant(&self, scope: &impl Layouts) ->
+        Result<i64, CompilerError> {
+
+        self.eval_typed(scope).map(|(value, _)| value)
+    }
+
+    /// Like `eval_constant`, but also gives the constant's type. Operands get
+    /// converted like they would be at runtime, so e.g. `-1 < sizeof(int)` is
+    /// false, since the -1 becomes unsigned
+    fn eval_typed(&self, scope: &impl Layouts) ->
+        Result<(i64, Type), CompilerError> {
+
+        let value = match &self.kind {
+            ExpressionKind::Ternary(x) => if x.condition.eval_constant(scope)? != 0 {
+                x.true_expr.eval_typed(scope)
+            } else {
+                x.false_expr.eval_typed(scope)
+            },
+
+            // These short-circuit, so the other side doesn't need to be valid
+            ExpressionKind::LogicalOr(x) => Ok(((x.first.eval_constant(scope)? != 0 ||
+                x.second.eval_constant(scope)? != 0) as i64, Type::Int32)),
+            ExpressionKind::LogicalAnd(x) => Ok(((x.first.eval_constant(scope)? != 0 &&
+                x.second.eval_constant(scope)? != 0) as i64, Type::Int32)),
+
+            ExpressionKind::BitwiseOr(x) => eval_arithmetic(x, scope, |a, b| a | b),
+            ExpressionKind::BitwiseXor(x) => eval_arithmetic(x, scope, |a, b| a ^ b),
+            ExpressionKind::BitwiseAnd(x) => eval_arithmetic(x, scope, |a, b| a & b),
+
+            ExpressionKind::Equality(x) => eval_comparison(x, scope, Ordering::is_eq),
+            ExpressionKind::Inequality(x) => eval_comparison(x, scope, Ordering::is_ne),
+            ExpressionKind::LTCompare(x) => eval_comparison(x, scope, Ordering::is_lt),
+            ExpressionKind::GTCompare(x) => eval_comparison(x, scope, Ordering::is_gt),
+            ExpressionKind::LECompare(x) => eval_comparison(x, scope, Ordering::is_le),
+            ExpressionKind::GECompare(x) => eval_comparison(x, scope, Ordering::is_ge),
+
+            ExpressionKind::ShiftLeft(x) => eval_shift(x, scope, true),
+            ExpressionKind::ShiftRight(x) => eval_shift(x, scope, false),
+
+            ExpressionKind::Add(x) => eval_arithmetic(x, scope, i64::wrapping_add),
+            ExpressionKind::Subtract(x) => eval_arithmetic(x, scope, i64::wrapping_sub),
+            ExpressionKind::Multiply(x) => eval_arithmetic(x, scope, i64::wrapping_mul),
+            ExpressionKind::Divide(x) => eval_division(x, scope, false),
+            ExpressionKind::Modulo(x) => eval_division(x, scope, true),
+
+            ExpressionKind::Cast(x) => if x.cast_to.is_integer() {
+                let t = x.cast_to.unqualified().clone();
+                x.expr.eval_constant(scope).map(|v| (t.convert_constant(v, scope.target()), t))
+            } else {
+                Err(CompilerError::SemanticError("Constant expression can only be cast to an integer type"))
+            },
+
+            ExpressionKind::Negate(x) => eval_unary(x, scope, i64::wrapping_neg),
+            ExpressionKind::BitwiseNot(x) => eval_unary(x, scope, |v| !v),
+            ExpressionKind::LogicalNot(x) =>
+                Ok(((x.expr.eval_constant(scope)? == 0) as i64, Type::Int32)),
+
+            ExpressionKind::SizeOfType(t) => t.size_of(scope)
+                .map(|size| (size as i64, Type::Uint64))
+                .ok_or(CompilerError::SemanticError("Can't take the size of an incomplete type")),
+
+            ExpressionKind::AlignOf(t) => t.align_of(scope)
+                .map(|align| (align as i64, Type::Uint64))
+                .ok_or(CompilerError::SemanticError("Can't take the alignment of an incomplete type")),
+
+            // A literal's type is the first of these it fits in
+            ExpressionKind::IntLiteral(x) => Ok(match *x {
+                x if x <= i32::MAX as u64 => (x as i64, Type::Int32),
+                x if x <= i64::MAX as u64 => (x as i64, Type::Int64),
+                x => (x as i64, Type::Uint64),
+            }),
+
+            ExpressionKind::CharLiteral(x) =>
+                Ok((x.eval(scope.target()), x.type_of(scope.target()).promoted())),
+
+            ExpressionKind::Identifier(x) => scope.lookup_constant(x)
+                .map(|value| (value, Type::Int32))
+                .ok_or(CompilerError::SemanticError("Expected a constant expression")),
+
+            ExpressionKind::Generic(_) => match self.selected(scope) {
+                Some(expr) => expr.eval_typed(scope),
+                None => Err(CompilerError::SemanticError("Expected a constant expression")),
+            },
+
+            _ => Err(CompilerError::SemanticError("Expected a constant expression")),
+        };
+
+        value.map_err(|e| e.at(self.span))
+    }
+
+    pub fn is_lvalue(&self, scope: &impl Layouts) -> bool {
+        match &self.kind {
+            // Enum constants are just numbers
+            ExpressionKind::Identifier(x) => scope.lookup_constant(x).is_none(),
+
+            ExpressionKind::Dereference(_) | ExpressionKind::ArrayIndex(_) |
+                ExpressionKind::PointerMember(_) => true,
+
+            // e.g. f().x isn't an lvalue, but s.x is
+            ExpressionKind::Member(x) => x.expr.is_lvalue(scope),
+
+            ExpressionKind::CompoundLiteral(_) | ExpressionKind::StringLiteral(_) => true,
+
+            ExpressionKind::Generic(_) =>
+                self.selected(scope).is_some_and(|expr| expr.is_lvalue(scope)),
+
             _ => false,
         }
     }
 
-    /// Verifies the expression and returns its type
+    /// Whether this refers to a bit-field, which can't have its address or
+    /// size taken. Only known once it's been verified
+    pub fn is_bit_field(&self, scope: &impl Layouts) -> bool {
+        match &self.kind {
+            ExpressionKind::Member(x) | ExpressionKind::PointerMember(x) => x.is_bit_field(),
+            ExpressionKind::Generic(_) =>
+                self.selected(scope).is_some_and(|expr| expr.is_bit_field(scope)),
+            _ => false,
+        }
+    }
+
+    /// The association a `_Generic` picks. Checking and generating it both
+    /// work this out the same way, from whatever's in scope
+    pub fn selected(&self, scope: &impl Layouts) -> Option<&Expression> {
+        match &self.kind {
+            ExpressionKind::Generic(x) => x.controlling_type(scope)
+                .and_then(|t| x.select(&t)),
+            _ => None,
+        }
+    }
+
+    /// Works out the type of the expression without checking it, following
+    /// the same rules as `verify`, and without arrays or functions decaying.
+    /// None if it uses a name that isn't in scope, or a statement expression
+    /// that hasn't been verified
+    pub fn object_type(&self, scope: &impl Layouts) -> Option<Type> {
+        let t = match &self.kind {
+            ExpressionKind::Comma(x) => x.second.expr_type(scope)?,
+
+            ExpressionKind::CompoundAssignment(x) => x.first.expr_type(scope)?,
+
+            ExpressionKind::Assignment(x) | ExpressionKind::LogicalOr(x) |
+                ExpressionKind::LogicalAnd(x) | ExpressionKind::BitwiseOr(x) |
+                ExpressionKind::BitwiseXor(x) | ExpressionKind::BitwiseAnd(x) |
+                ExpressionKind::Equality(x) | ExpressionKind::Inequality(x) |
+                ExpressionKind::LTCompare(x) | ExpressionKind::GTCompare(x) |
+                ExpressionKind::LECompare(x) | ExpressionKind::GECompare(x) |
+                ExpressionKind::ShiftLeft(x) | ExpressionKind::ShiftRight(x) |
+                ExpressionKind::Add(x) | ExpressionKind::Subtract(x) |
+                ExpressionKind::Multiply(x) | ExpressionKind::Divide(x) |
+                ExpressionKind::Modulo(x) => x.first.expr_type(scope)?,
+
+            ExpressionKind::Ternary(x) => x.true_expr.expr_type(scope)?,
+
+            ExpressionKind::Cast(x) => x.cast_to.clone(),
+
+            ExpressionKind::PreInc(x) | ExpressionKind::PreDec(x) |
+                ExpressionKind::PostInc(x) | ExpressionKind::PostDec(x) |
+                ExpressionKind::Negate(x) | ExpressionKind::BitwiseNot(x) |
+                ExpressionKind::LogicalNot(x) => x.expr.expr_type(scope)?,
+
+            ExpressionKind::AddressOf(x) =>
+                Type::Pointer(Box::new(x.expr.object_type(scope)?)),
+
+            ExpressionKind::Dereference(x) => match x.expr.expr_type(scope)? {
+                Type::Pointer(inner) => *inner,
+                _ => return None,
+            },
+
+            ExpressionKind::SizeOf(_) | ExpressionKind::SizeOfType(_) |
+                ExpressionKind::AlignOf(_) => Type::Uint64,
+
+            ExpressionKind::ArrayIndex(x) => match x.first.expr_type(scope)? {
+                Type::Pointer(inner) => *inner,
+                _ => return None,
+            },
+
+            ExpressionKind::Member(x) | ExpressionKind::PointerMember(x) => {
+                let struct_t = match x.expr.expr_type(scope)? {
+                    Type::Pointer(inner) => *inner,
+                    t => t,
+                };
+
+                let layout = match struct_t.unqualified() {
+                    Type::Struct(s) => scope.get_layout(&s.tag)?,
+                    _ => return None,
+                };
+
+                layout.get_member(&x.member)?.type_of.clone()
+            },
+
+            ExpressionKind::FuncCall(x) => match x.func.expr_type(scope)? {
+                Type::Pointer(f) => match *f {
+                    Type::Function(f) => f.return_type,
+                    _ => return None,
+                },
+                _ => return None,
+            },
+
+            ExpressionKind::VaStart(_) | ExpressionKind::VaEnd(_) |
+                ExpressionKind::VaCopy(_) => Type::Void,
+
+            ExpressionKind::VaArg(x) => x.type_of.unqualified().clone(),
+
+            // Anything in the list we can't type can't be a whole aggregate
+            ExpressionKind::CompoundLiteral(x) => resolve_initializer(&x.type_of,
+                &x.items, scope, &|e| e.expr_type(scope).unwrap_or(Type::Void)).ok()?.0,
+
+            ExpressionKind::Generic(_) => self.selected(scope)?.object_type(scope)?,
+
+            ExpressionKind::StatementExpr(x) => x.type_of()?.clone(),
+
+            ExpressionKind::Identifier(id) => scope.lookup_type(id)?,
+
+            ExpressionKind::IntLiteral(_) => Type::Int32,
+            ExpressionKind::CharLiteral(x) => x.type_of(scope.target()),
+            ExpressionKind::StringLiteral(x) => x.type_of(scope.target()),
+        };
+
+        Some(t)
+    }
+
+    /// Same as `object_type`, but with arrays and functions decayed to pointers
+    pub fn expr_type(&self, scope: &impl Layouts) -> Option<Type> {
+        self.object_type(scope).map(Type::decay)
+    }
+
+    /// The type C gives the expression, which is what a `_Generic` picks its
+    /// association by. This doesn't follow the rest of the compiler: our
+    /// operators don't convert their operands, so e.g. `c + c` is a char
+    /// everywhere else, but here it's the int C makes it. The conversions are
+    /// the same ones constant expressions are evaluated with (see
+    /// `Type::common_type`). Going through lvalue conversion also drops any
+    /// qualifiers
+    pub fn c_type(&self, scope: &impl Layouts) -> Option<Type> {
+        let target = scope.target();
+
+        let converted = match &self.kind {
+            ExpressionKind::Add(x) | ExpressionKind::Subtract(x) |
+                ExpressionKind::Multiply(x) | ExpressionKind::Divide(x) |
+                ExpressionKind::Modulo(x) | ExpressionKind::BitwiseOr(x) |
+                ExpressionKind::BitwiseXor(x) | ExpressionKind::BitwiseAnd(x) => {
+
+                let (a, b) = (x.first.c_type(scope)?, x.second.c_type(scope)?);
+                (a.is_integer() && b.is_integer()).then(|| a.common_type(&b, target))
+            },
+
+            ExpressionKind::Ternary(x) => {
+                let (a, b) = (x.true_expr.c_type(scope)?, x.false_expr.c_type(scope)?);
+                (a.is_integer() && b.is_integer()).then(|| a.common_type(&b, target))
+            },
+
+            // Shifts only promote their left operand
+            ExpressionKind::ShiftLeft(x) | ExpressionKind::ShiftRight(x) =>
+                Some(x.first.c_type(scope)?).filter(Type::is_integer).map(|t| t.promoted()),
+
+            ExpressionKind::Negate(x) | ExpressionKind::BitwiseNot(x) =>
+                Some(x.expr.c_type(scope)?).filter(Type::is_integer).map(|t| t.promoted()),
+
+            ExpressionKind::Equality(_) | ExpressionKind::Inequality(_) |
+                ExpressionKind::LTCompare(_) | ExpressionKind::GTCompare(_) |
+                ExpressionKind::LECompare(_) | ExpressionKind::GECompare(_) |
+                ExpressionKind::LogicalAnd(_) | ExpressionKind::LogicalOr(_) |
+                ExpressionKind::LogicalNot(_) => Some(Type::Int32),
+
+            ExpressionKind::Comma(x) => Some(x.second.c_type(scope)?),
+
+            _ => None,
+        };
+
+        match converted {
+            Some(t) => Some(t),
+            None => self.expr_type(scope).map(|t| t.unqualified().clone()),
+        }
+    }
+
+    /// Calls `f` on this expression and then, if it returns true, on each of
+    /// the expressions nested inside it. Statements inside statement
+    /// expressions aren't looked into
+    pub fn visit<'a, F>(&'a self, f: &mut F)
+        where F: FnMut(&'a Expression) -> bool {
+
+        if !f(self) {
+            return;
+        }
+
+        match &self.kind {
+            ExpressionKind::Comma(x) | ExpressionKind::Assignment(x) |
+                ExpressionKind::LogicalOr(x) | ExpressionKind::LogicalAnd(x) |
+                ExpressionKind::BitwiseOr(x) | ExpressionKind::BitwiseXor(x) |
+                ExpressionKind::BitwiseAnd(x) | ExpressionKind::Equality(x) |
+                ExpressionKind::Inequality(x) | ExpressionKind::LTCompare(x) |
+                ExpressionKind::GTCompare(x) | ExpressionKind::LECompare(x) |
+                ExpressionKind::GECompare(x) | ExpressionKind::ShiftLeft(x) |
+                ExpressionKind::ShiftRight(x) | ExpressionKind::Add(x) |
+                ExpressionKind::Subtract(x) | ExpressionKind::Multiply(x) |
+                ExpressionKind::Divide(x) | ExpressionKind::Modulo(x) |
+                ExpressionKind::ArrayIndex(x) | ExpressionKind::VaStart(x) |
+                ExpressionKind::VaCopy(x) => {
+
+                x.first.visit(f);
+                x.second.visit(f);
+            },
+
+            ExpressionKind::CompoundAssignment(x) => {
+                x.first.visit(f);
+                x.second.visit(f);
+            },
+
+            ExpressionKind::Ternary(x) => {
+                x.condition.visit(f);
+                x.true_expr.visit(f);
+                x.false_expr.visit(f);
+            },
+
+            ExpressionKind::PreInc(x) | ExpressionKind::PreDec(x) |
+                ExpressionKind::PostInc(x) | ExpressionKind::PostDec(x) |
+                ExpressionKind::AddressOf(x) | ExpressionKind::Dereference(x) |
+                ExpressionKind::Negate(x) | ExpressionKind::BitwiseNot(x) |
+                ExpressionKind::LogicalNot(x) | ExpressionKind::SizeOf(x) |
+                ExpressionKind::VaEnd(x) => x.expr.visit(f),
+
+            ExpressionKind::Cast(x) => x.expr.visit(f),
+
+            ExpressionKind::Member(x) | ExpressionKind::PointerMember(x) =>
+                x.expr.visit(f),
+
+            ExpressionKind::FuncCall(x) => {
+                x.func.visit(f);
+                for arg in &x.args {
+                    arg.visit(f);
+                }
+            },
+
+            ExpressionKind::CompoundLiteral(x) => for item in &x.items {
+                item.value.visit(f);
+            },
+
+            ExpressionKind::VaArg(x) => x.list.visit(f),
+
+            ExpressionKind::Generic(x) => {
+                x.controlling.visit(f);
+                for (_, expr) in &x.associations {
+                    expr.visit(f);
+                }
+            },
+
+            ExpressionKind::SizeOfType(_) | ExpressionKind::AlignOf(_) |
+                ExpressionKind::StatementExpr(_) | ExpressionKind::Identifier(_) |
+                ExpressionKind::IntLiteral(_) | ExpressionKind::CharLiteral(_) |
+                ExpressionKind::StringLiteral(_) => (),
+        }
+    }
+
+    /// Verifies the expression and returns its type, once any arrays or
+    /// functions have decayed into pointers
     pub fn verify(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        Ok(self.verify_object(context)?.decay())
+    }
+
+    /// Checks that this is an lvalue we're allowed to write to, and returns
+    /// its type
+    pub fn verify_modifiable(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        self.check_modifiable(context).map_err(|e| e.at(self.span))
+    }
+
+    fn check_modifiable(&self, context: &mut Context) -> Result<Type, CompilerError> {
+        let t = self.verify_object(context)?;
+
+        if !self.is_lvalue(context) {
+            return Err(CompilerError::SemanticError("Must assign to lvalue"));
+        }
+
+        if let Type::Array(_) = t {
+            return Err(CompilerError::SemanticError("Can't assign to an array"));
+        }
+
+        if t.qualifiers().is_const {
+            return Err(CompilerError::SemanticError("Can't assign to a const"));
+        }
+
+        Ok(t)
+    }
+
+    /// Same as `verify`, but without decaying. Only `sizeof` and `&` see the
+    /// type of an object as it really is
+    fn verify_object(&self, context: &mut Context) -> Result<Type, CompilerError> {
         trace!("Semantically checking {:?}", self);
+        self.verify_kind(context).map_err(|e| e.at(self.span))
+    }
 
+    fn verify_kind(&self, context: &mut Context) -> Result<Type, CompilerError> {
         // TODO: Implicit casts
-        match self {
-            Self::Assignment(x) => {
-                if !x.first.is_lvalue() {
-                    return Err(CompilerError::SemanticError("Must assign to lvalue"));
-                }
+        match &self.kind {
+            ExpressionKind::Comma(x) => {
+                x.first.verify(context)?;
+                x.second.verify(context)
+            },
+
+            ExpressionKind::CompoundAssignment(x) => x.verify(context),
+
+            ExpressionKind::Assignment(x) => {
+                // Not using BinaryExpr::verify since we can assign structs
+                let first_t = x.first.verify_modifiable(context)?;
+                let second_t = x.second.verify(context)?;
 
-                x.verify(context)
+                first_t.check_assign(&second_t, "Assignment types must match")?;
+                Ok(first_t)
             },
 
-            Self::Ternary(x) => x.verify(context),
+            ExpressionKind::Ternary(x) => x.verify(context),
 
-            Self::LogicalOr(x) => x.verify(context),
-            Self::LogicalAnd(x) => x.verify(context),
-            Self::BitwiseOr(x) => x.verify(context),
-            Self::BitwiseXor(x) => x.verify(context),
-            Self::BitwiseAnd(x) => x.verify(context),
+            ExpressionKind::LogicalOr(x) => x.verify(context),
+            ExpressionKind::LogicalAnd(x) => x.verify(context),
+            ExpressionKind::BitwiseOr(x) => x.verify(context),
+            ExpressionKind::BitwiseXor(x) => x.verify(context),
+            ExpressionKind::BitwiseAnd(x) => x.verify(context),
 
-            Self::Equality(x) => x.verify(context),
-            Self::Inequality(x) => x.verify(context),
+            ExpressionKind::Equality(x) => x.verify(context),
+            ExpressionKind::Inequality(x) => x.verify(context),
 
-            Self::LTCompare(x) => x.verify(context),
-            Self::GTCompare(x) => x.verify(context),
-            Self::LECompare(x) => x.verify(context),
-            Self::GECompare(x) => x.verify(context),
+            ExpressionKind::LTCompare(x) => x.verify(context),
+            ExpressionKind::GTCompare(x) => x.verify(context),
+            ExpressionKind::LECompare(x) => x.verify(context),
+            ExpressionKind::GECompare(x) => x.verify(context),
 
-            Self::ShiftLeft(x) => verify_bitshift(x, context),
-            Self::ShiftRight(x) => verify_bitshift(x, context),
+            ExpressionKind::ShiftLeft(x) => verify_bitshift(x, context),
+            ExpressionKind::ShiftRight(x) => verify_bitshift(x, context),
 
-            Self::Add(x) => x.verify(context),
-            Self::Subtract(x) => x.verify(context),
+            ExpressionKind::Add(x) => x.verify(context),
+            ExpressionKind::Subtract(x) => x.verify(context),
 
-            Self::Multiply(x) => { 
+            ExpressionKind::Multiply(x) => { 
                 let t = x.verify(context)?;
-                if !t.is_signed() {
+                if !t.is_signed(context.target()) {
                     // TODO
                     Err(CompilerError::SemanticError("Can't multiply unsigned ints"))
                 } else { Ok(t) }
 
             }
 
-            Self::Divide(x) => { 
+            ExpressionKind::Divide(x) => { 
                 let t = x.verify(context)?;
-                if !t.is_signed() {
+                if !t.is_signed(context.target()) {
                     // TODO
                     Err(CompilerError::SemanticError("Can't divide unsigned ints"))
                 } else { Ok(t) }
 
             }
 
-            Self::Modulo(x) => x.verify(context),
+            ExpressionKind::Modulo(x) => x.verify(context),
 
-            Self::Cast(x) => x.verify(context),
+            ExpressionKind::Cast(x) => x.verify(context),
 
-            Self::PreInc(x) => x.verify(context),
-            Self::PreDec(x) => x.verify(context),
-            Self::PostInc(x) => x.verify(context),
-            Self::PostDec(x) => x.verify(context),
+            ExpressionKind::PreInc(x) | ExpressionKind::PreDec(x) |
+                ExpressionKind::PostInc(x) | ExpressionKind::PostDec(x) => x.expr.verify_modifiable(context),
 
-            Self::AddressOf(x) => {
-                if !x.expr.is_lvalue() {
+            ExpressionKind::AddressOf(x) => {
+                let t = x.expr.verify_object(context)?;
+
+                if !x.expr.is_lvalue(context) {
                     return Err(CompilerError::SemanticError("Can't do & on a non-lvalue"));
                 }
 
-                Ok(Type::Pointer(Box::new(x.verify(context)?)))
+                if x.expr.is_bit_field(context) {
+                    return Err(CompilerError::SemanticError("Can't take the address of a bit-field"));
+                }
+
+                if let ExpressionKind::Identifier(n) = &x.expr.kind && context.is_register(n) {
+                    return Err(CompilerError::SemanticError("Can't take the address of a register variable"));
+                }
+
+                Ok(Type::Pointer(Box::new(t)))
             },
 
-            Self::Dereference(x) => {
+            ExpressionKind::Dereference(x) => {
                 let t = x.expr.verify(context)?;
 
                 if let Type::Pointer(inner_t) = t {
@@ -262,7 +1250,7 @@ impl Expression {
                 }
             },
 
-            Self::Negate(x) => {
+            ExpressionKind::Negate(x) => {
                 let t = x.expr.verify(context)?;
 
                 if !t.is_integer() {
@@ -272,15 +1260,29 @@ impl Expression {
                 Ok(t)
             },
 
-            Self::BitwiseNot(x) => x.verify(context),
-            Self::LogicalNot(x) => x.verify(context),
+            ExpressionKind::BitwiseNot(x) => x.verify(context),
+            ExpressionKind::LogicalNot(x) => x.verify(context),
+
+            ExpressionKind::SizeOf(x) => {
+                if x.expr.verify_object(context)?.size_of(context).is_none() {
+                    return Err(CompilerError::SemanticError("Can't take the size of an incomplete type"));
+                }
+
+                if x.expr.is_bit_field(context) {
+                    return Err(CompilerError::SemanticError("Can't take the size of a bit-field"));
+                }
+
+                Ok(Type::Uint64)
+            },
+
+            ExpressionKind::SizeOfType(t) | ExpressionKind::AlignOf(t) => {
+                declare_types(t, context)?;
+                self.eval_constant(context)?;
 
-            Self::SizeOf(x) => {
-                x.verify(context)?;
                 Ok(Type::Uint64)
             },
 
-            Self::ArrayIndex(x) => {
+            ExpressionKind::ArrayIndex(x) => {
                 let array_t = x.first.verify(context)?;
                 if let Type::Pointer(inner) = array_t {
                     let index_t = x.second.verify(context)?;
@@ -295,19 +1297,58 @@ impl Expression {
                 Err(CompilerError::SemanticError("Cannot index into non-array/pointer"))
             }
 
-            Self::FuncCall(x) => x.verify(context),
+            ExpressionKind::Member(x) => x.verify(context, false),
+            ExpressionKind::PointerMember(x) => x.verify(context, true),
+
+            ExpressionKind::FuncCall(x) => x.verify(context),
+            ExpressionKind::CompoundLiteral(x) => x.verify(context),
+
+            ExpressionKind::VaStart(x) => {
+                if !context.is_variadic() {
+                    return Err(CompilerError::SemanticError("va_start outside a variadic function"));
+                }
+
+                verify_va_list(&x.first, context)?;
+
+                // Like GCC, we don't check that it's really the last param
+                if !matches!(x.second.kind, ExpressionKind::Identifier(_)) {
+                    return Err(CompilerError::SemanticError("va_start needs the last named param"));
+                }
+
+                x.second.verify(context)?;
+                Ok(Type::Void)
+            },
+
+            ExpressionKind::VaArg(x) => x.verify(context),
+
+            ExpressionKind::VaEnd(x) => {
+                verify_va_list(&x.expr, context)?;
+                Ok(Type::Void)
+            },
+
+            ExpressionKind::VaCopy(x) => {
+                verify_va_list(&x.first, context)?;
+                verify_va_list(&x.second, context)?;
+                Ok(Type::Void)
+            },
+
+            ExpressionKind::Generic(x) => x.verify(context),
+            ExpressionKind::StatementExpr(x) => x.verify(context),
 
-            Self::Identifier(x) => match context.get_type(x) {
-                Some(t) => Ok(t.clone()),
+            ExpressionKind::Identifier(x) => match context.get_type(x) {
+                Some(t) => {
+                    context.mark_used(x);
+                    Ok(t.clone())
+                },
                 None => Err(CompilerError::SemanticError("Undefined symbol")),
             }
 
             // TODO: Coerce literals into their actual type... somehow
             // For now we'll just make them all int32s and deal with it laterrr
-            Self::IntLiteral(_) => Ok(Type::Int32),
+            ExpressionKind::IntLiteral(_) => Ok(Type::Int32),
 
-            Self::CharLiteral(_) => Ok(Type::Uint8),
-            Self::StringLiteral(_) => Ok(Type::Pointer(Box::new(Type::Uint8))),
+            ExpressionKind::CharLiteral(x) => Ok(x.type_of(context.target())),
+            ExpressionKind::StringLiteral(x) => Ok(x.type_of(context.target())),
         }
     }
 }
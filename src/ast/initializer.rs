@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+
+use crate::error::CompilerError;
+
+use super::{expressions::{Encoding, ExpressionKind, StringLiteralExpr}, layout::{BitField, Layouts}, types::{ArrayType, TagKind}, Context, Expression, Target, Type};
+
+/// One entry in a brace-enclosed initializer list, e.g. `.x = 1`
+#[derive(Debug, Clone)]
+pub struct InitItem {
+    /// Empty if it just initializes whatever comes next
+    pub designators: Vec<Designator>,
+    pub value: Initializer,
+}
+
+/// Picks out which part of an object gets initialized
+#[derive(Debug, Clone)]
+pub enum Designator {
+    Member(String),     // .x
+    Index(Expression),  // [3]
+}
+
+#[derive(Debug, Clone)]
+pub enum Initializer {
+    Expression(Expression),
+    List(Box<[InitItem]>),
+}
+
+impl Initializer {
+    /// Visits each of the values it sets, the same as `Expression::visit`
+    pub fn visit<'a, F>(&'a self, f: &mut F)
+        where F: FnMut(&'a Expression) -> bool {
+
+        match self {
+            Self::Expression(expr) => expr.visit(f),
+            Self::List(items) => for item in items {
+                item.value.visit(f);
+            },
+        }
+    }
+}
+
+/// The types of the expressions in an initializer list. Which part of the
+/// object an expression initializes can depend on its type, e.g. a struct
+/// initializes a whole member rather than that member's first member
+pub struct ItemTypes(HashMap<*const Expression, Type>);
+
+impl ItemTypes {
+    /// Verifies every expression in the list, keeping track of their types
+    pub fn verify(items: &[InitItem], context: &mut Context) -> Result<Self, CompilerError> {
+        let mut types = ItemTypes(HashMap::new());
+        types.verify_items(items, context)?;
+        Ok(types)
+    }
+
+    fn verify_items(&mut self, items: &[InitItem], context: &mut Context) ->
+        Result<(), CompilerError> {
+
+        for item in items {
+            match &item.value {
+                Initializer::Expression(e) => {
+                    let t = e.verify(context)?;
+                    self.0.insert(e, t);
+                },
+                Initializer::List(inner) => self.verify_items(inner, context)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, expr: &Expression) -> Type {
+        self.0.get(&(expr as *const _)).expect("Expression is from the list").clone()
+    }
+}
+
+/// Something an initializer list sets, and where it goes within the object
+/// being initialized. That's a scalar, unless a string literal or struct sets
+/// a whole array or struct
+#[derive(Debug)]
+pub struct InitField<'a> {
+    pub offset: usize,
+    pub type_of: Type,
+    pub value: &'a Expression,
+
+    /// Set if it's a bit-field, in which case `offset` is the byte it starts in
+    pub bits: Option<BitField>,
+}
+
+/// Works out every scalar an initializer list sets, in the order they're set.
+/// Anything not listed is zero. Later fields can overwrite earlier ones, like
+/// in `{ [0] = 1, [0] = 2 }`.
+///
+/// Also returns the type being initialized, with the length filled in if it's
+/// an array like `int a[] = { ... }` that gets its length from the list.
+/// `expr_type` gives the type of each expression in the list
+pub fn resolve_initializer<'a>(
+    type_of: &Type,
+    items: &'a [InitItem],
+    layouts: &impl Layouts,
+    expr_type: &impl Fn(&Expression) -> Type,
+) -> Result<(Type, Vec<InitField<'a>>), CompilerError> {
+
+    let mut resolver = Resolver { layouts, expr_type, fields: vec![] };
+    let count = resolver.braced(type_of, 0, None, items)?;
+
+    let complete = match type_of {
+        Type::Array(a) if a.length.is_none() => {
+            if count == 0 {
+                return Err(CompilerError::SemanticError("Array length must be positive"));
+            }
+
+            Type::Array(Box::new(ArrayType {
+                element: a.element.clone(),
+                length: Some(ExpressionKind::IntLiteral(count as u64).into()),
+            }))
+        },
+        t => t.clone(),
+    };
+
+    Ok((complete, resolver.fields))
+}
+
+struct Resolver<'a, 'l, L: Layouts, F: Fn(&Expression) -> Type> {
+    layouts: &'l L,
+    expr_type: &'l F,
+    fields: Vec<InitField<'a>>,
+}
+
+impl<'a, L: Layouts, F: Fn(&Expression) -> Type> Resolver<'a, '_, L, F> {
+    /// Initializes an object from its own brace-enclosed list, returning how
+    /// many elements it set if it's an array
+    fn braced(&mut self, type_of: &Type, offset: usize, bits: Option<BitField>,
+        items: &'a [InitItem]) -> Result<usize, CompilerError> {
+
+        // A string literal can initialize a character array with or without
+        // braces, e.g. `char s[] = { "hi" };`
+        if let [InitItem { designators, value: Initializer::Expression(e) }] = items
+            && designators.is_empty()
+            && let Some(count) = self.string(type_of, offset, e)? {
+
+            return Ok(count);
+        }
+
+        if type_of.is_scalar() {
+            // Scalars can have braces too, e.g. `int x = { 1 };`
+            return match items {
+                [InitItem { designators, value: Initializer::Expression(e) }]
+                    if designators.is_empty() => {
+
+                    self.scalar(type_of, offset, bits, e);
+                    Ok(1)
+                },
+                [] => Err(CompilerError::SemanticError("Empty scalar initializer")),
+                _ => Err(CompilerError::SemanticError("Too many initializers for scalar")),
+            };
+        }
+
+        let mut pos = 0;
+        let count = self.aggregate(type_of, offset, items, &mut pos, true, None)?;
+
+        if pos < items.len() {
+            return Err(CompilerError::SemanticError("Too many initializers"));
+        }
+
+        Ok(count)
+    }
+
+    /// Initializes the members or elements of an aggregate, starting with the
+    /// item at `pos`. Without braces of its own, it only takes as many items
+    /// as it has room for and leaves any designated ones for its parent.
+    /// `pending` is set if the parent already used the first item's designators,
+    /// and holds whatever's left of them, e.g. the `.b` of `.a.b`
+    fn aggregate(
+        &mut self,
+        type_of: &Type,
+        offset: usize,
+        items: &'a [InitItem],
+        pos: &mut usize,
+        braced: bool,
+        mut pending: Option<&'a [Designator]>,
+    ) -> Result<usize, CompilerError> {
+
+        let mut current = 0;
+        let mut count = 0;
+
+        while let Some(item) = items.get(*pos) {
+            let designators = match pending.take() {
+                Some(rest) => rest,
+                None if item.designators.is_empty() => &[],
+                None if braced => &item.designators,
+                None => break,
+            };
+
+            let designated = match designators.split_first() {
+                Some((first, rest)) => {
+                    current = self.designate(type_of, first)?;
+                    Some(rest)
+                },
+                None => None,
+            };
+
+            let (sub_type, sub_offset, bits) =
+                match self.sub_object(type_of, current, designated.is_some())? {
+                    Some(s) => s,
+                    None if braced => return Err(CompilerError::SemanticError("Too many initializers")),
+                    None => break,
+                };
+
+            match designated {
+                Some(rest) if !rest.is_empty() => {
+                    if sub_type.is_scalar() {
+                        return Err(CompilerError::SemanticError("Can't designate inside a scalar"));
+                    }
+
+                    self.aggregate(&sub_type, offset + sub_offset, items, pos,
+                        false, Some(rest))?;
+                },
+                _ => self.element(&sub_type, offset + sub_offset, bits, items, pos)?,
+            }
+
+            current += 1;
+            count = count.max(current);
+        }
+
+        Ok(count)
+    }
+
+    /// Initializes a member or element from the item at `pos`
+    fn element(&mut self, type_of: &Type, offset: usize, bits: Option<BitField>,
+        items: &'a [InitItem], pos: &mut usize) -> Result<(), CompilerError> {
+
+        match &items[*pos].value {
+            Initializer::List(inner) => {
+                *pos += 1;
+                self.braced(type_of, offset, bits, inner)?;
+            },
+
+            // A string literal or struct can initialize the whole thing
+            Initializer::Expression(e) if self.whole(type_of, offset, e)? =>
+                *pos += 1,
+
+            // Otherwise without braces, an aggregate takes as many items as it
+            // needs
+            Initializer::Expression(_) if !type_of.is_scalar() => {
+                let start = *pos;
+                self.aggregate(type_of, offset, items, pos, false, Some(&[]))?;
+
+                if *pos == start {
+                    return Err(CompilerError::SemanticError("Can't initialize an empty struct"));
+                }
+            },
+
+            Initializer::Expression(e) => {
+                *pos += 1;
+                self.scalar(type_of, offset, bits, e);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn scalar(&mut self, type_of: &Type, offset: usize, bits: Option<BitField>,
+        value: &'a Expression) {
+
+        self.fields.push(InitField { offset, type_of: type_of.clone(), value, bits });
+    }
+
+    /// Initializes an aggregate from a single expression if it can be done in
+    /// one go, i.e. it's a string literal for an array or a struct of the same
+    /// type. Gives back whether it could
+    fn whole(&mut self, type_of: &Type, offset: usize, value: &'a Expression) ->
+        Result<bool, CompilerError> {
+
+        match type_of.unqualified() {
+            Type::Array(_) => Ok(self.string(type_of, offset, value)?.is_some()),
+
+            Type::Struct(_) if (self.expr_type)(value).unqualified()
+                .is_compatible(type_of.unqualified()) => {
+
+                self.scalar(type_of, offset, None, value);
+                Ok(true)
+            },
+
+            _ => Ok(false),
+        }
+    }
+
+    /// Initializes a character array from a string literal, if that's what
+    /// `value` is. Gives back how many elements that set, which includes the
+    /// null terminator if there's room for it
+    fn string(&mut self, type_of: &Type, offset: usize, value: &'a Expression) ->
+        Result<Option<usize>, CompilerError> {
+
+        let (Type::Array(a), ExpressionKind::StringLiteral(literal)) =
+            (type_of.unqualified(), &value.kind) else {
+
+            return Ok(None);
+        };
+
+        if !string_fits(&a.element, literal, self.layouts.target()) {
+            return Ok(None);
+        }
+
+        // The null terminator gets dropped if it'd only just not fit
+        let count = match a.get_length(self.layouts) {
+            Some(length) if literal.units.len() > length =>
+                return Err(CompilerError::SemanticError("String literal is too long for its array")),
+            Some(length) => length.min(literal.units.len() + 1),
+            None => literal.units.len() + 1,
+        };
+
+        let complete = Type::Array(Box::new(ArrayType {
+            element: a.element.clone(),
+            length: Some(ExpressionKind::IntLiteral(count as u64).into()),
+        }));
+
+        self.scalar(&complete, offset, None, value);
+        Ok(Some(count))
+    }
+
+    /// Finds which member or element a designator refers to
+    fn designate(&self, type_of: &Type, designator: &Designator) ->
+        Result<usize, CompilerError> {
+
+        match (type_of.unqualified(), designator) {
+            (Type::Struct(s), Designator::Member(name)) => self.layouts
+                .get_layout(&s.tag)
+                .and_then(|l| l.members.iter().position(|m| &m.name == name))
+                .ok_or(CompilerError::SemanticError("No such member to initialize")),
+
+            (Type::Array(a), Designator::Index(e)) => {
+                let index = usize::try_from(e.eval_constant(self.layouts)?)
+                    .map_err(|_| CompilerError::SemanticError("Array index out of bounds"))?;
+
+                match a.get_length(self.layouts) {
+                    Some(length) if index >= length =>
+                        Err(CompilerError::SemanticError("Array index out of bounds")),
+                    _ => Ok(index),
+                }
+            },
+
+            (_, Designator::Member(_)) =>
+                Err(CompilerError::SemanticError("Can't initialize a member of a non-struct")),
+
+            (_, Designator::Index(_)) =>
+                Err(CompilerError::SemanticError("Can't index into a non-array")),
+        }
+    }
+
+    /// Gets the type and offset of an aggregate's `index`th member or element
+    /// (and where its bits are, for a bit-field), or None if it doesn't
+    /// have one. Only a union's first member gets initialized by default
+    fn sub_object(&self, type_of: &Type, index: usize, designated: bool) ->
+        Result<Option<(Type, usize, Option<BitField>)>, CompilerError> {
+
+        let qualifiers = type_of.qualifiers();
+
+        let sub = match type_of.unqualified() {
+            Type::Struct(s) => {
+                let layout = self.layouts.get_layout(&s.tag)
+                    .ok_or(CompilerError::SemanticError("Can't initialize an incomplete type"))?;
+
+                if s.kind == TagKind::Union && index > 0 && !designated {
+                    return Ok(None);
+                }
+
+                layout.members.get(index).map(|m| (m.type_of.clone(), m.offset, m.bits))
+            },
+
+            Type::Array(a) => {
+                let in_bounds = a.get_length(self.layouts)
+                    .is_none_or(|length| index < length);
+
+                let size = a.element.size_of(self.layouts)
+                    .expect("Array elements are complete");
+
+                in_bounds.then(|| (a.element.clone(), index * size, None))
+            },
+
+            _ => panic!("Only aggregates have sub-objects"),
+        };
+
+        // Members of a const struct are const too
+        Ok(sub.map(|(t, offset, bits)| (t.qualified(qualifiers), offset, bits)))
+    }
+}
+
+/// Whether a string literal can initialize an array of `element`s. Plain and
+/// UTF-8 ones suit any character type, and the rest need their own type
+fn string_fits(element: &Type, literal: &StringLiteralExpr, target: &Target) -> bool {
+    match literal.encoding {
+        Encoding::Plain | Encoding::Utf8 =>
+            matches!(element.unqualified(), Type::Char | Type::Int8 | Type::Uint8),
+        _ => element.unqualified() == &literal.encoding.element_type(target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{declaration::DeclarationValue, Context, TranslationUnit}, fs::read_file, parser::parse};
+
+    use super::*;
+
+    fn verify_file(ending: &str, context: &mut Context) ->
+        Result<TranslationUnit, CompilerError> {
+
+        let mut p = PathBuf::from("tests/files/unit/initializer/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(context)?;
+        Ok(parsed)
+    }
+
+    fn expect_semantic_error(ending: &str) -> Result<(), CompilerError> {
+        match verify_file(ending, &mut Context::new()) {
+            Ok(_) => panic!("Verified when we shouldn't have"),
+            Err(e) => match e.root() {
+                CompilerError::SemanticError(_) => Ok(()),
+                _ => Err(e)
+            }
+        }
+    }
+
+    fn offsets(unit: &TranslationUnit, name: &str, context: &mut Context) ->
+        Result<Vec<usize>, CompilerError> {
+
+        let decl = unit.declarations.iter()
+            .find(|d| d.name.as_deref() == Some(name))
+            .unwrap();
+
+        match &decl.value {
+            Some(DeclarationValue::List(items)) => {
+                let types = ItemTypes::verify(items, context)?;
+                let (_, fields) = resolve_initializer(&decl.type_of, items, context,
+                    &|e| types.get(e))?;
+                Ok(fields.iter().map(|f| f.offset).collect())
+            },
+            _ => panic!("{} should have an initializer list", name),
+        }
+    }
+
+    #[test]
+    fn lists() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        let unit = verify_file("lists.c", &mut context)?;
+
+        let size = |n| context.get_type(n).unwrap().size_of(&context);
+        assert_eq!(size("primes"), Some(20));
+        assert_eq!(size("pts"), Some(32));
+
+        // `3` carries on after `.to.y`, into `tag`
+        assert_eq!(offsets(&unit, "diag", &mut context)?, [0, 4, 12, 16]);
+        assert_eq!(offsets(&unit, "grid", &mut context)?, [0, 4, 8, 20]);
+        assert_eq!(offsets(&unit, "pts", &mut context)?, [20, 24]);
+        Ok(())
+    }
+
+    #[test]
+    fn too_many() -> Result<(), CompilerError> {
+        expect_semantic_error("too_many.c")
+    }
+
+    #[test]
+    fn no_member() -> Result<(), CompilerError> {
+        expect_semantic_error("no_member.c")
+    }
+
+    #[test]
+    fn out_of_bounds() -> Result<(), CompilerError> {
+        expect_semantic_error("out_of_bounds.c")
+    }
+
+    #[test]
+    fn scalar_list() -> Result<(), CompilerError> {
+        expect_semantic_error("scalar_list.c")
+    }
+
+    #[test]
+    fn non_literal() -> Result<(), CompilerError> {
+        expect_semantic_error("non_literal.c")
+    }
+
+    #[test]
+    fn scalar_union() -> Result<(), CompilerError> {
+        expect_semantic_error("scalar_union.c")
+    }
+
+    #[test]
+    fn strings() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        let unit = verify_file("strings.c", &mut context)?;
+
+        // Unknown lengths come from the string, terminator included
+        let size = |n| context.get_type(n).unwrap().size_of(&context);
+        assert_eq!(size("greeting"), Some(3));
+        assert_eq!(size("braced"), Some(3));
+
+        assert_eq!(offsets(&unit, "rows", &mut context)?, [0, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn string_int() -> Result<(), CompilerError> {
+        expect_semantic_error("string_int.c")
+    }
+
+    #[test]
+    fn long_string() -> Result<(), CompilerError> {
+        expect_semantic_error("long_string.c")
+    }
+}
@@ -0,0 +1,799 @@
+use std::{collections::HashSet, rc::Rc};
+
+use crate::error::CompilerError;
+
+use super::{declaration::Alignment, types::{ArrayType, EnumType, Enumerator, StructMember, StructType, TagKind}, Expression, Target, Type};
+
+/// Where a struct member lives within the struct
+#[derive(Debug, Clone)]
+pub struct MemberLayout {
+    pub name: String,
+    pub type_of: Type,
+
+    /// For a bit-field, this is the byte holding its lowest bit
+    pub offset: usize,
+    pub bits: Option<BitField>,
+}
+
+/// Where a bit-field's value sits, counting up from the least significant bit
+/// of the byte it starts in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitField {
+    pub shift: usize,
+    pub width: usize,
+}
+
+impl BitField {
+    /// How many bytes the field spans, i.e. how much memory to touch when
+    /// reading or writing it
+    pub fn bytes(&self) -> usize {
+        (self.shift + self.width).div_ceil(8)
+    }
+
+    /// The bits of those bytes that belong to the field
+    pub fn mask(&self) -> u64 {
+        let low = match self.width {
+            64 => u64::MAX,
+            w => (1 << w) - 1,
+        };
+
+        low << self.shift
+    }
+}
+
+/// How a struct is laid out in memory, following the SysV x86-64 rules: each
+/// member is aligned to its own alignment, and the whole struct is padded out
+/// to a multiple of its strictest member's alignment. Unions are the same,
+/// except every member starts at 0. Packed structs have no padding at all, and
+/// packed members have none before them.
+///
+/// Bit-fields are packed in one after another, except that one never crosses
+/// the boundary of a unit of its type, and a zero width one pads out to the
+/// next unit. Unnamed bit-fields don't affect the struct's alignment
+#[derive(Debug, Clone)]
+pub struct StructLayout {
+    pub size: usize,
+    pub align: usize,
+    pub members: Box<[MemberLayout]>,
+}
+
+fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// Checks a bit-field's width is one its type can hold
+fn bit_field_width(member: &StructMember, width: &Expression, bytes: usize,
+    layouts: &impl Layouts) -> Result<usize, CompilerError> {
+
+    if !member.type_of.is_integer() {
+        return Err(CompilerError::SemanticError("Bit-fields must have an integer type"));
+    }
+
+    let max = match member.type_of.unqualified() {
+        Type::Bool => 1,
+        _ => bytes * 8,
+    };
+
+    let width = usize::try_from(width.eval_constant(layouts)?)
+        .ok()
+        .filter(|w| *w <= max)
+        .ok_or(CompilerError::SemanticError("Bit-field width doesn't fit its type"))?;
+
+    if width == 0 && member.name.is_some() {
+        return Err(CompilerError::SemanticError("Named bit-field has zero width"));
+    }
+
+    Ok(width)
+}
+
+impl StructLayout {
+    pub fn new(
+        s: &StructType,
+        members: &[StructMember],
+        layouts: &impl Layouts
+    ) -> Result<Self, CompilerError> {
+
+        let mut names = HashSet::new();
+        // Where the next member can start, in bits so that bit-fields can
+        // share bytes
+        let mut next: usize = 0;
+        let mut size = 0;
+        let mut align = 1;
+        let mut laid_out = vec![];
+
+        for member in members {
+            if let Some(name) = &member.name && !names.insert(name.as_str()) {
+                return Err(CompilerError::SemanticError("Struct member defined twice"));
+            }
+
+            let (member_size, natural_align) = match (
+                member.type_of.size_of(layouts),
+                member.type_of.align_of(layouts)
+            ) {
+                (Some(s), Some(a)) => (s, a),
+                _ => return Err(CompilerError::SemanticError("Struct member has incomplete type")),
+            };
+
+            // `_Alignas` still counts in a packed struct, like GCC's `aligned`
+            let requested = Alignment::strictest(&member.alignments, layouts)?;
+
+            if requested != 0 && member.width.is_some() {
+                return Err(CompilerError::SemanticError("Bit-fields can't be aligned"));
+            }
+
+            if requested != 0 && requested < natural_align {
+                return Err(CompilerError::SemanticError("_Alignas can't weaken alignment"));
+            }
+
+            // The member's own `aligned` only ever adds to what it needs, same
+            // as on a struct
+            let aligned = match &member.attributes.aligned {
+                Some(_) if member.width.is_some() =>
+                    return Err(CompilerError::SemanticError("Bit-fields can't be aligned")),
+                Some(e) => Alignment::Expression(e.clone()).eval(layouts)?,
+                None => 0,
+            };
+
+            let packed = s.packed || member.attributes.packed;
+            let member_align = match packed {
+                true => requested.max(aligned).max(1),
+                false => requested.max(aligned).max(natural_align),
+            };
+
+            let start = match s.kind {
+                TagKind::Struct => next,
+                _ => 0,
+            };
+
+            let Some(width) = &member.width else {
+                let offset = align_up(start.div_ceil(8), member_align);
+
+                align = align.max(member_align);
+                size = size.max(offset + member_size);
+                next = size * 8;
+
+                laid_out.push(MemberLayout {
+                    name: member.name.clone().expect("Only bit-fields can be unnamed"),
+                    type_of: member.type_of.clone(),
+                    offset,
+                    bits: None,
+                });
+
+                continue;
+            };
+
+            let width = bit_field_width(member, width, member_size, layouts)?;
+            let unit_bits = member_size * 8;
+
+            let start = if width == 0 {
+                align_up(start, natural_align * 8)
+            } else if !packed && start / unit_bits != (start + width - 1) / unit_bits {
+                align_up(start, unit_bits)
+            } else {
+                start
+            };
+
+            next = start + width;
+            size = size.max(next.div_ceil(8));
+
+            let Some(name) = &member.name else {
+                continue;
+            };
+
+            // Only packed bit-fields can cross a unit, and they still have to
+            // be readable in one go from the byte they start in
+            if start % 8 + width > unit_bits {
+                return Err(CompilerError::NotSupported("Packed bit-fields spanning more than one unit of their type"));
+            }
+
+            align = align.max(member_align);
+
+            laid_out.push(MemberLayout {
+                name: name.clone(),
+                type_of: member.type_of.clone(),
+                offset: start / 8,
+                bits: Some(BitField { shift: start % 8, width }),
+            });
+        }
+
+        // Like `_Alignas`, except asking for less than it needs does nothing
+        if let Some(aligned) = &s.aligned {
+            align = align.max(Alignment::Expression(aligned.clone()).eval(layouts)?);
+        }
+
+        Ok(Self {
+            size: align_up(size, align),
+            align,
+            members: laid_out.into(),
+        })
+    }
+
+    pub fn get_member(&self, name: &str) -> Option<&MemberLayout> {
+        self.members.iter().find(|m| m.name == name)
+    }
+}
+
+/// What a tag refers to
+#[derive(Debug, Clone)]
+pub struct TagEntry {
+    pub kind: TagKind,
+
+    /// None if it's been declared but not defined (yet). Enums get laid out
+    /// like an int, with no members
+    pub layout: Option<Rc<StructLayout>>,
+}
+
+/// Anything keeping track of which enum constants are in scope, so that they
+/// can be used in constant expressions
+pub trait Constants {
+    /// Finds the value of an enum constant, or None if the name isn't one
+    /// (including if it's a variable shadowing one)
+    fn lookup_constant(&self, name: &str) -> Option<i64>;
+
+    /// Finds the type of the variable, function or enum constant a name
+    /// refers to, which a `_Generic` needs to pick its association
+    fn lookup_type(&self, name: &str) -> Option<Type>;
+
+    /// What we're compiling for
+    fn target(&self) -> &Target;
+}
+
+/// Anything keeping track of which tags are in scope, so that we can work out
+/// the size of types
+pub trait Layouts: Constants {
+    /// Finds what a tag refers to from the current scope, if anything
+    fn lookup_tag(&self, tag: &str) -> Option<TagEntry>;
+
+    /// Like `lookup_tag`, but only looks in the current scope
+    fn lookup_tag_in_scope(&self, tag: &str) -> Option<TagEntry>;
+
+    /// Adds a tag to the current scope, replacing it if it's already there
+    fn insert_tag(&mut self, tag: String, entry: TagEntry);
+
+    /// Adds an enum constant to the current scope. Constants share a namespace
+    /// with variables, so this errors if the name's already taken
+    fn insert_constant(&mut self, name: String, value: i64) ->
+        Result<(), CompilerError>;
+
+    /// Gets the layout of a struct or union, if it's been defined
+    fn get_layout(&self, tag: &str) -> Option<Rc<StructLayout>> {
+        self.lookup_tag(tag).and_then(|t| t.layout)
+    }
+}
+
+fn check_kind(entry: &TagEntry, kind: TagKind) -> Result<(), CompilerError> {
+    if entry.kind != kind {
+        return Err(CompilerError::SemanticError("Tag used for a different kind of type"));
+    }
+
+    Ok(())
+}
+
+/// Handles a tag being mentioned without a definition, declaring it if we
+/// haven't seen it before
+fn mention_tag(
+    tag: &str,
+    kind: TagKind,
+    existing: Option<TagEntry>,
+    scope: &mut impl Layouts
+) -> Result<(), CompilerError> {
+
+    match existing {
+        Some(entry) => check_kind(&entry, kind),
+        None => {
+            scope.insert_tag(tag.to_string(), TagEntry { kind, layout: None });
+            Ok(())
+        },
+    }
+}
+
+/// Declares (and defines, if this is where they get defined) any structs that
+/// the given type mentions, the same way C does when it first comes across
+/// them
+pub fn declare_types(t: &Type, scope: &mut impl Layouts) ->
+    Result<(), CompilerError> {
+
+    match t {
+        Type::Pointer(inner) => declare_types(inner, scope),
+
+        Type::Qualified(inner, q) => {
+            if q.is_restrict && !matches!(**inner, Type::Pointer(_)) {
+                return Err(CompilerError::SemanticError("Only pointers can be restrict"));
+            }
+
+            declare_types(inner, scope)
+        },
+
+        Type::Array(a) => {
+            if let Type::Function(_) = a.element {
+                return Err(CompilerError::SemanticError("Can't have an array of functions"));
+            }
+
+            declare_types(&a.element, scope)?;
+
+            if let Some(length) = &a.length {
+                let length = length.eval_constant(scope)
+                    .map_err(|_| CompilerError::NotSupported("Variable length arrays"))?;
+
+                if length <= 0 {
+                    return Err(CompilerError::SemanticError("Array length must be positive"));
+                }
+            }
+
+            Ok(())
+        },
+
+        Type::Function(f) => {
+            if let Type::Array(_) | Type::Function(_) = f.return_type {
+                return Err(CompilerError::SemanticError("Functions can't return arrays or functions"));
+            }
+
+            declare_types(&f.return_type, scope)?;
+
+            for (_, arg_t) in &f.args {
+                declare_types(arg_t, scope)?;
+            }
+
+            Ok(())
+        },
+
+        Type::Struct(s) => match &s.members {
+            Some(members) => define_struct(s, members, scope),
+
+            // Mentioning a struct we've never heard of declares it
+            None => {
+                let existing = scope.lookup_tag(&s.tag);
+                mention_tag(&s.tag, s.kind, existing, scope)
+            },
+        },
+
+        Type::Enum(e) => match &e.members {
+            Some(members) => define_enum(e, members, scope),
+            None => {
+                let existing = scope.lookup_tag(&e.tag);
+                mention_tag(&e.tag, TagKind::Enum, existing, scope)
+            },
+        },
+
+        _ => Ok(()),
+    }
+}
+
+/// Handles a declaration that only declares a struct. Unlike just mentioning
+/// a struct, `struct foo;` always declares a new one in the current scope
+pub fn declare_tag_only(t: &Type, scope: &mut impl Layouts) ->
+    Result<(), CompilerError> {
+
+    match t {
+        Type::Struct(s) if s.members.is_none() => {
+            let existing = scope.lookup_tag_in_scope(&s.tag);
+            mention_tag(&s.tag, s.kind, existing, scope)
+        },
+
+        Type::Enum(e) if e.members.is_none() => {
+            let existing = scope.lookup_tag_in_scope(&e.tag);
+            mention_tag(&e.tag, TagKind::Enum, existing, scope)
+        },
+
+        Type::Struct(_) | Type::Enum(_) => declare_types(t, scope),
+
+        _ => Err(CompilerError::SemanticError("Declaration doesn't declare anything")),
+    }
+}
+
+fn define_struct(
+    s: &StructType,
+    members: &[StructMember],
+    scope: &mut impl Layouts
+) -> Result<(), CompilerError> {
+
+    if let Some(entry) = scope.lookup_tag_in_scope(&s.tag) {
+        check_kind(&entry, s.kind)?;
+
+        if entry.layout.is_some() {
+            return Err(CompilerError::SemanticError("Struct defined twice within scope"));
+        }
+    }
+
+    // Declare ourselves first so that members can point back to us
+    scope.insert_tag(s.tag.clone(), TagEntry { kind: s.kind, layout: None });
+
+    for member in members {
+        declare_types(&member.type_of, scope)?;
+    }
+
+    let layout = StructLayout::new(s, members, scope)?;
+    scope.insert_tag(s.tag.clone(), TagEntry {
+        kind: s.kind,
+        layout: Some(Rc::new(layout)),
+    });
+
+    Ok(())
+}
+
+fn define_enum(
+    e: &EnumType,
+    members: &[Enumerator],
+    scope: &mut impl Layouts
+) -> Result<(), CompilerError> {
+
+    if let Some(entry) = scope.lookup_tag_in_scope(&e.tag) {
+        check_kind(&entry, TagKind::Enum)?;
+
+        if entry.layout.is_some() {
+            return Err(CompilerError::SemanticError("Enum defined twice within scope"));
+        }
+    }
+
+    if members.is_empty() {
+        return Err(CompilerError::SemanticError("Enum must have at least one constant"));
+    }
+
+    // Constants without a value are one more than the last one
+    let mut next = 0;
+
+    for (name, value) in members {
+        let value = match value {
+            Some(expr) => expr.eval_constant(scope)?,
+            None => next,
+        };
+
+        if i32::try_from(value).is_err() {
+            return Err(CompilerError::SemanticError("Enum constant doesn't fit in an int"));
+        }
+
+        scope.insert_constant(name.clone(), value)?;
+        next = value + 1;
+    }
+
+    let layout = StructLayout {
+        size: 4,
+        align: 4,
+        members: Box::new([]),
+    };
+
+    scope.insert_tag(e.tag.clone(), TagEntry {
+        kind: TagKind::Enum,
+        layout: Some(Rc::new(layout)),
+    });
+
+    Ok(())
+}
+
+impl Type {
+    /// The size of the type in bytes, or None if it doesn't have one (e.g.
+    /// it's an incomplete struct or a function)
+    pub fn size_of(&self, layouts: &impl Layouts) -> Option<usize> {
+        match self {
+            Self::Void | Self::Function(_) => None,
+
+            Self::Bool | Self::Char => Some(1),
+            Self::Uint8  | Self::Int8  => Some(1),
+            Self::Uint16 | Self::Int16 => Some(2),
+            Self::Uint32 | Self::Int32 => Some(4),
+            Self::Uint64 | Self::Int64 => Some(8),
+
+            Self::Float => Some(4),
+            Self::Double => Some(8),
+
+            Self::Pointer(_) => Some(8),
+
+            Self::Array(a) => Some(a.element.size_of(layouts)? * a.get_length(layouts)?),
+
+            Self::Struct(s) => layouts.get_layout(&s.tag).map(|l| l.size),
+            Self::Enum(e) => layouts.get_layout(&e.tag).map(|l| l.size),
+            Self::VaListTag => Some(24),
+
+            Self::Qualified(inner, _) => inner.size_of(layouts),
+        }
+    }
+
+    /// The alignment of the type in bytes, or None if it doesn't have a size
+    pub fn align_of(&self, layouts: &impl Layouts) -> Option<usize> {
+        match self {
+            Self::Struct(s) => layouts.get_layout(&s.tag).map(|l| l.align),
+            Self::Enum(e) => layouts.get_layout(&e.tag).map(|l| l.align),
+            Self::Array(a) => a.element.align_of(layouts),
+            Self::VaListTag => Some(8),
+            Self::Qualified(inner, _) => inner.align_of(layouts),
+
+            // Everything else is aligned to its own size
+            _ => self.size_of(layouts),
+        }
+    }
+}
+
+impl ArrayType {
+    /// The number of elements, or None if it isn't known
+    pub fn get_length(&self, layouts: &impl Layouts) -> Option<usize> {
+        let length = self.length.as_ref()?.eval_constant(layouts).ok()?;
+        usize::try_from(length).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::Context, fs::read_file, parser::parse};
+
+    use super::*;
+
+    fn verify_file(ending: &str, context: &mut Context) ->
+        Result<(), CompilerError> {
+
+        let mut p = PathBuf::from("tests/files/unit/layout/");
+        p.push(ending);
+
+        parse(&read_file(&p)?)?.verify(context)
+    }
+
+    fn expect_semantic_error(ending: &str) -> Result<(), CompilerError> {
+        match verify_file(ending, &mut Context::new()) {
+            Ok(_) => panic!("Verified when we shouldn't have"),
+            Err(e) => match e.root() {
+                CompilerError::SemanticError(_) => Ok(()),
+                _ => Err(e)
+            }
+        }
+    }
+
+    #[test]
+    fn padding() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("structs.c", &mut context)?;
+
+        let point = context.get_layout("point").unwrap();
+        let offsets: Vec<usize> = point.members.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0, 4, 8]);
+        assert_eq!((point.size, point.align), (12, 4));
+
+        let node = context.get_layout("node").unwrap();
+        let offsets: Vec<usize> = node.members.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0, 8, 16]);
+        assert_eq!((node.size, node.align), (32, 8));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unions() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("unions.c", &mut context)?;
+
+        let value = context.get_layout("value").unwrap();
+        assert!(value.members.iter().all(|m| m.offset == 0));
+        assert_eq!((value.size, value.align), (16, 8));
+
+        let small = context.get_layout("small").unwrap();
+        assert_eq!((small.size, small.align), (4, 4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn packed() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("packed.c", &mut context)?;
+
+        let header = context.get_layout("header").unwrap();
+        let offsets: Vec<usize> = header.members.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0, 1, 5]);
+        assert_eq!((header.size, header.align), (13, 1));
+
+        let sizes: Vec<(usize, usize)> = ["padded", "both", "weak", "outer"].iter()
+            .map(|t| context.get_layout(t).unwrap())
+            .map(|l| (l.size, l.align))
+            .collect();
+        assert_eq!(sizes, [(32, 32), (12, 4), (8, 8), (14, 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn member_alignment() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("member_alignment.c", &mut context)?;
+
+        let spaced = context.get_layout("spaced").unwrap();
+        let offsets: Vec<usize> = spaced.members.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0, 8, 16]);
+        assert_eq!((spaced.size, spaced.align), (32, 16));
+
+        // Packing doesn't undo `_Alignas`
+        let tight = context.get_layout("tight").unwrap();
+        let offsets: Vec<usize> = tight.members.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0, 4]);
+        assert_eq!((tight.size, tight.align), (8, 4));
+
+        // Only declarations can be aligned, not type names
+        assert!(parse("int x = (_Alignas(4) int) 1;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn member_attributes() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("member_attributes.c", &mut context)?;
+
+        let layout = |tag: &str| {
+            let layout = context.get_layout(tag).unwrap();
+            let offsets: Vec<usize> = layout.members.iter().map(|m| m.offset).collect();
+            (offsets, layout.size, layout.align)
+        };
+
+        assert_eq!(layout("header"), (vec![0, 1, 6], 8, 2));
+        assert_eq!(layout("slot"), (vec![0, 16], 32, 16));
+
+        // Attributes before the type apply to every member it declares, and
+        // `aligned` can't weaken a member's alignment
+        assert_eq!(layout("mixed"), (vec![0, 2, 4, 8], 16, 8));
+        assert_eq!(layout("wire"), (vec![0, 4], 8, 4));
+
+        match verify_file("aligned_bitfield_attribute.c", &mut Context::new()) {
+            Err(e) => assert!(matches!(e.root(), CompilerError::SemanticError(_))),
+            Ok(_) => panic!("Verified when we shouldn't have"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn aligned_bitfield() -> Result<(), CompilerError> {
+        expect_semantic_error("aligned_bitfield.c")
+    }
+
+    #[test]
+    fn weak_member() -> Result<(), CompilerError> {
+        expect_semantic_error("weak_member.c")
+    }
+
+    #[test]
+    fn shadowed() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("shadowed.c", &mut context)?;
+
+        // The structs declared in the functions are types of their own, so
+        // the outer one keeps its layout
+        let outer = context.get_layout("s").unwrap();
+        assert_eq!((outer.size, outer.align), (4, 4));
+        Ok(())
+    }
+
+    #[test]
+    fn enums() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("enums.c", &mut context)?;
+
+        let values: Vec<Option<i64>> = ["RED", "GREEN", "BLUE", "NEGATIVE",
+            "AFTER_NEGATIVE", "COMPUTED"].iter()
+            .map(|n| context.lookup_constant(n))
+            .collect();
+
+        assert_eq!(values, [Some(0), Some(5), Some(6), Some(-3), Some(-2), Some(28)]);
+        assert_eq!(context.get_layout("color").map(|l| l.size), Some(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn arrays() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("arrays.c", &mut context)?;
+
+        let sizes: Vec<Option<usize>> = ["m", "table", "buf"].iter()
+            .map(|n| context.get_type(n).unwrap().size_of(&context))
+            .collect();
+        assert_eq!(sizes, [Some(48), Some(32), Some(5)]);
+
+        let packet = context.get_layout("packet").unwrap();
+        let offsets: Vec<usize> = packet.members.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, [0, 4]);
+        assert_eq!((packet.size, packet.align), (16, 4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sizeof() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("sizeof.c", &mut context)?;
+
+        let values: Vec<Option<i64>> = ["PAIR_SIZE", "PAIR_ALIGN", "PRODUCT"].iter()
+            .map(|n| context.lookup_constant(n))
+            .collect();
+        assert_eq!(values, [Some(16), Some(8), Some(12)]);
+
+        let table = context.get_type("table").unwrap();
+        assert_eq!(table.size_of(&context), Some(32));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bitfields() -> Result<(), CompilerError> {
+        let mut context = Context::new();
+        verify_file("bitfields.c", &mut context)?;
+
+        let placement = |tag| -> Vec<(usize, Option<BitField>)> {
+            context.get_layout(tag).unwrap().members.iter()
+                .map(|m| (m.offset, m.bits))
+                .collect()
+        };
+        let bits = |shift, width| Some(BitField { shift, width });
+
+        assert_eq!(placement("flags"), [(0, bits(0, 1)), (0, bits(1, 1)),
+            (0, bits(4, 3)), (0, bits(7, 5)), (2, None)]);
+        assert_eq!(placement("split"), [(0, bits(0, 5)), (1, bits(0, 5)),
+            (4, bits(0, 9)), (8, bits(0, 40)), (16, bits(0, 40))]);
+        assert_eq!(placement("wire"), [(0, bits(0, 4)), (0, bits(4, 4)),
+            (1, bits(0, 12)), (2, bits(4, 4))]);
+
+        // Accesses only cover the bytes a field spans, so none run off the
+        // end of a packed struct
+        let spans = |tag| -> Vec<usize> {
+            context.get_layout(tag).unwrap().members.iter()
+                .filter_map(|m| m.bits.map(|b| m.offset + b.bytes()))
+                .collect()
+        };
+        assert_eq!(spans("wire"), [1, 1, 3, 3]);
+        assert_eq!(spans("split"), [1, 2, 6, 13, 21]);
+
+        let sizes: Vec<(usize, usize)> = ["flags", "split", "wire", "bits", "lone"].iter()
+            .map(|t| context.get_layout(t).unwrap())
+            .map(|l| (l.size, l.align))
+            .collect();
+        assert_eq!(sizes, [(4, 4), (24, 8), (3, 1), (8, 4), (2, 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bad_bitfields() -> Result<(), CompilerError> {
+        for file in ["bitfield_width.c", "bitfield_type.c", "bitfield_zero.c"] {
+            expect_semantic_error(file)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn weak_alignment() -> Result<(), CompilerError> {
+        expect_semantic_error("weak_alignment.c")
+    }
+
+    #[test]
+    fn bad_alignment() -> Result<(), CompilerError> {
+        expect_semantic_error("bad_alignment.c")
+    }
+
+    #[test]
+    fn array_assign() -> Result<(), CompilerError> {
+        expect_semantic_error("array_assign.c")
+    }
+
+    #[test]
+    fn enum_assign() -> Result<(), CompilerError> {
+        expect_semantic_error("enum_assign.c")
+    }
+
+    #[test]
+    fn wrong_kind() -> Result<(), CompilerError> {
+        expect_semantic_error("wrong_kind.c")
+    }
+
+    #[test]
+    fn incomplete() -> Result<(), CompilerError> {
+        expect_semantic_error("incomplete.c")
+    }
+
+    #[test]
+    fn no_member() -> Result<(), CompilerError> {
+        expect_semantic_error("no_member.c")
+    }
+
+    #[test]
+    fn redefined() -> Result<(), CompilerError> {
+        expect_semantic_error("redefined.c")
+    }
+}
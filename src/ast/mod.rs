@@ -1,9 +1,15 @@
 mod context;
 pub mod translation_unit;
 pub mod types;
+pub mod layout;
 pub mod declaration;
+pub mod initializer;
 pub mod statements;
 pub mod expressions;
+pub mod span;
+pub mod asm;
+pub mod attributes;
+pub mod target;
 
 pub use translation_unit::TranslationUnit;
 pub use types::Type;
@@ -11,3 +17,5 @@ pub use declaration::Declaration;
 pub use expressions::Expression;
 pub use statements::Statement;
 pub use context::Context;
+pub use span::Span;
+pub use target::Target;
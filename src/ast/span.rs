@@ -0,0 +1,31 @@
+use std::fmt::Display;
+
+/// Where something is in the (preprocessed) source, as byte offsets. The
+/// preprocessor's source map turns these back into a file, line and column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// From the start of this span to the end of `other`
+    pub fn to(self, other: Span) -> Self {
+        Span { start: self.start, end: other.end }
+    }
+
+    /// Nodes we make ourselves, rather than parse, don't have a real span
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
@@ -1,27 +1,40 @@
+use std::collections::HashSet;
+
 use log::trace;
 
-use crate::error::CompilerError;
+use crate::error::{CompilerError, Warning};
+
+use super::{asm::AsmStatement, expressions::ExpressionKind, layout::Constants, Context, Declaration, Expression, Span, Type};
 
-use super::{Context, Declaration, Expression, Type};
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
 
-#[derive(Debug)]
-pub enum Statement {
+#[derive(Debug, Clone)]
+pub enum StatementKind {
     Compound(Box<[Statement]>),
-    Declaration(Declaration),
+    Declaration(Box<Declaration>),
     Expression(Box<Expression>),
     If(Box<IfStatement>),
     WhileLoop(Box<WhileLoopStatement>),
+    Switch(Box<SwitchStatement>),
+    Case(Box<CaseStatement>),
+    Default(Box<Statement>),
+    Label(Box<LabelStatement>),
+    Goto(String),
+    Break,
     Return(Box<Option<Expression>>),
+    Asm(Box<AsmStatement>),
 
     // TODO: 
-    // - Labels & Jumps
     // - Do-Whiles and Fors
-    // - Break, Continue
-    // - Switches
+    // - Continue
 }
 
 // If-Else can be done by chaining these
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct IfStatement {
     pub condition: Expression,
     pub if_block: Statement,
@@ -44,7 +57,7 @@ impl IfStatement {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WhileLoopStatement {
     pub condition: Expression,
     pub body: Statement,
@@ -56,13 +69,171 @@ impl WhileLoopStatement {
             return Err(CompilerError::SemanticError("While condition must resolve to an integer type"));
         }
 
-        self.body.verify(context)?;
+        let mut inner = context.inner();
+        inner.enter_loop();
+        self.body.verify(&mut inner)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SwitchStatement {
+    pub condition: Expression,
+    pub body: Statement,
+}
+
+impl SwitchStatement {
+    /// Gets the case labels belonging to this switch in the order they appear,
+    /// and how many default labels it has
+    pub fn get_cases(&self) -> (Vec<&CaseStatement>, usize) {
+        let mut cases = vec![];
+        let mut defaults = 0;
+
+        self.body.visit(&mut |s| match &s.kind {
+            StatementKind::Case(x) => {
+                cases.push(&**x);
+                true
+            },
+            StatementKind::Default(_) => {
+                defaults += 1;
+                true
+            },
+            // Any labels in here belong to the nested switch, not us
+            StatementKind::Switch(_) => false,
+            _ => true,
+        });
+
+        (cases, defaults)
+    }
+
+    fn verify(&self, context: &mut Context) -> Result<(), CompilerError> {
+        let cond_t = self.condition.verify(context)?;
+        if !cond_t.is_integer() {
+            return Err(CompilerError::SemanticError("Switch condition must resolve to an integer type"));
+        }
+
+        let (cases, defaults) = self.get_cases();
+
+        if defaults > 1 {
+            return Err(CompilerError::SemanticError("Multiple default labels in one switch"));
+        }
+
+        // The case values get verified along with the body, which has to
+        // happen before they can be evaluated
+        let mut inner = context.inner();
+        inner.enter_switch();
+        self.body.verify(&mut inner)?;
+
+        // Case values get converted to the type of the condition once it's
+        // been promoted, so compare them after converting
+        let cond_t = cond_t.promoted();
+        let mut seen = HashSet::new();
+        for case in cases {
+            let value = cond_t.convert_constant(case.value.eval_constant(context)?,
+                context.target());
+
+            if !seen.insert(value) {
+                return Err(CompilerError::SemanticError("Duplicate case value in switch"));
+            }
+        }
 
         Ok(())
     }
 }
 
-fn verify_compound(stmts: &Box<[Statement]>, context: &mut Context) ->
+#[derive(Debug, Clone)]
+pub struct CaseStatement {
+    pub value: Expression,
+    pub body: Statement,
+}
+
+impl CaseStatement {
+    fn verify(&self, context: &mut Context) -> Result<(), CompilerError> {
+        if !context.in_switch() {
+            return Err(CompilerError::SemanticError("Case label outside of switch"));
+        }
+
+        if !self.value.verify(context)?.is_integer() {
+            return Err(CompilerError::SemanticError("Case value must be an integer"));
+        }
+
+        self.body.verify(context)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LabelStatement {
+    pub name: String,
+    pub body: Statement,
+}
+
+/// Finds all the labels in a function body. Unlike other names, labels are
+/// visible throughout the whole function regardless of block scope
+pub fn find_labels(body: &[Statement]) -> Vec<&LabelStatement> {
+    let mut labels = vec![];
+
+    for stmt in body {
+        stmt.visit(&mut |s| {
+            if let StatementKind::Label(x) = &s.kind {
+                labels.push(&**x);
+            }
+
+            true
+        });
+    }
+
+    labels
+}
+
+/// Checks that every label in a function body is unique, and that every goto
+/// has a label to go to
+pub fn verify_labels(body: &[Statement]) -> Result<(), CompilerError> {
+    let mut names = HashSet::new();
+    let mut duplicate = None;
+    for stmt in body {
+        stmt.visit(&mut |s| {
+            if let StatementKind::Label(x) = &s.kind && !names.insert(x.name.as_str()) {
+                duplicate.get_or_insert(s.span);
+            }
+
+            true
+        });
+    }
+
+    if let Some(span) = duplicate {
+        return Err(CompilerError::SemanticError("Label defined twice within function").at(span));
+    }
+
+    let mut undefined = None;
+    for stmt in body {
+        stmt.visit(&mut |s| {
+            if let StatementKind::Goto(name) = &s.kind && !names.contains(name.as_str()) {
+                undefined.get_or_insert(s.span);
+            }
+
+            true
+        });
+    }
+
+    if let Some(span) = undefined {
+        return Err(CompilerError::SemanticError("Goto to undefined label").at(span));
+    }
+
+    Ok(())
+}
+
+fn verify_default(body: &Statement, context: &mut Context) ->
+    Result<(), CompilerError> {
+
+    if !context.in_switch() {
+        return Err(CompilerError::SemanticError("Default label outside of switch"));
+    }
+
+    body.verify(context)
+}
+
+fn verify_compound(stmts: &[Statement], context: &mut Context) ->
     Result<(), CompilerError> {
 
     let mut inner = context.inner();
@@ -71,6 +242,8 @@ fn verify_compound(stmts: &Box<[Statement]>, context: &mut Context) ->
         stmt.verify(&mut inner)?;
     }
 
+    inner.warn_unused();
+
     Ok(())
 }
 
@@ -89,9 +262,7 @@ fn verify_return(expr: &Option<Expression>, context: &mut Context) ->
             } 
 
             let actual_type = ex.verify(context)?;
-            if expected_type != actual_type {
-                return Err(CompilerError::SemanticError("Tried to return incorrect type"));
-            }
+            expected_type.check_assign(&actual_type, "Tried to return incorrect type")?;
         },
 
         None => {
@@ -105,18 +276,151 @@ fn verify_return(expr: &Option<Expression>, context: &mut Context) ->
 }
 
 impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Statement { kind, span }
+    }
+
+    /// Calls `f` on this statement and then, if it returns true, on each of
+    /// the statements nested inside it
+    pub fn visit<'a, F>(&'a self, f: &mut F)
+        where F: FnMut(&'a Statement) -> bool {
+
+        if !f(self) {
+            return;
+        }
+
+        match &self.kind {
+            StatementKind::Compound(stmts) => for s in stmts {
+                s.visit(f);
+            },
+            StatementKind::If(x) => {
+                x.if_block.visit(f);
+                if let Some(e) = &x.else_block {
+                    e.visit(f);
+                }
+            },
+            StatementKind::WhileLoop(x) => x.body.visit(f),
+            StatementKind::Switch(x) => x.body.visit(f),
+            StatementKind::Case(x) => x.body.visit(f),
+            StatementKind::Default(x) => x.visit(f),
+            StatementKind::Label(x) => x.body.visit(f),
+            _ => (),
+        }
+    }
+
     pub fn verify(&self, context: &mut Context) -> Result<(), CompilerError> {
         trace!("Semantically checking {:?}", self);
+        self.verify_kind(context).map_err(|e| e.at(self.span))
+    }
+
+    fn verify_kind(&self, context: &mut Context) -> Result<(), CompilerError> {
+        match &self.kind {
+            StatementKind::Compound(stmts) => verify_compound(stmts, context)?,
+            StatementKind::Declaration(decl) => decl.verify(context)?,
+            StatementKind::Expression(expr) => {
+                expr.verify(context)?;
 
-        match self {
-            Self::Compound(stmts) => verify_compound(stmts, context)?,
-            Self::Declaration(decl) => decl.verify(context)?,
-            Self::Expression(expr) => { expr.verify(context)?; },
-            Self::If(x) => x.verify(context)?,
-            Self::WhileLoop(x) => x.verify(context)?,
-            Self::Return(expr) => verify_return(expr.as_ref(), context)?,
+                if let ExpressionKind::FuncCall(call) = &expr.kind
+                    && let ExpressionKind::Identifier(f) = &call.func.kind
+                    && context.get_attributes(f).is_some_and(|a| a.nodiscard) {
+
+                    context.warn(Warning::new(
+                        format!("Ignoring the result of '{}', which is nodiscard", f), self.span));
+                }
+            },
+            StatementKind::If(x) => x.verify(context)?,
+            StatementKind::WhileLoop(x) => x.verify(context)?,
+            StatementKind::Switch(x) => x.verify(context)?,
+            StatementKind::Case(x) => x.verify(context)?,
+            StatementKind::Default(x) => verify_default(x, context)?,
+            StatementKind::Label(x) => x.body.verify(context)?,
+            // The label itself gets checked along with the rest of the function
+            StatementKind::Goto(_) => (),
+            StatementKind::Break => if !context.can_break() {
+                return Err(CompilerError::SemanticError("Break outside of loop or switch"));
+            },
+            StatementKind::Return(expr) => {
+                if context.is_noreturn() {
+                    context.warn(Warning::new("Returning from a noreturn function", self.span));
+                }
+
+                verify_return(expr.as_ref(), context)?
+            },
+            StatementKind::Asm(x) => x.verify(context)?,
         };
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{fs::read_file, parser::parse};
+
+    use super::*;
+
+    fn verify_file(ending: &str) -> Result<(), CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/statements/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(&mut Context::new())
+    }
+
+    fn expect_semantic_error(ending: &str) -> Result<(), CompilerError> {
+        match verify_file(ending) {
+            Ok(_) => panic!("Verified when we shouldn't have"),
+            Err(e) => match e.root() {
+                CompilerError::SemanticError(_) => Ok(()),
+                _ => Err(e)
+            }
+        }
+    }
+
+    #[test]
+    fn switch() -> Result<(), CompilerError> {
+        verify_file("switch.c")
+    }
+
+    #[test]
+    fn duplicate_case() -> Result<(), CompilerError> {
+        expect_semantic_error("duplicate_case.c")
+    }
+
+    #[test]
+    fn stray_case() -> Result<(), CompilerError> {
+        expect_semantic_error("stray_case.c")
+    }
+
+    #[test]
+    fn stray_break() -> Result<(), CompilerError> {
+        expect_semantic_error("stray_break.c")
+    }
+
+    #[test]
+    fn goto() -> Result<(), CompilerError> {
+        verify_file("goto.c")
+    }
+
+    #[test]
+    fn duplicate_label() -> Result<(), CompilerError> {
+        expect_semantic_error("duplicate_label.c")
+    }
+
+    #[test]
+    fn undefined_label() -> Result<(), CompilerError> {
+        expect_semantic_error("undefined_label.c")
+    }
+
+    #[test]
+    fn error_span() {
+        // Points at the goto, not the if it's in or the whole function
+        match verify_file("undefined_label.c") {
+            Err(CompilerError::Spanned { span, .. }) =>
+                assert_eq!(span, Span::new(26, 36)),
+            x => panic!("Expected an error with a span, got {:?}", x),
+        }
+    }
+}
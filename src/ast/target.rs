@@ -0,0 +1,17 @@
+/// What the target decides about C's types, which the AST can't know itself.
+/// The default is x86-64 Linux
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    /// Whether plain `char` is signed
+    pub char_signed: bool,
+
+    /// Whether `wchar_t` is a 16-bit UTF-16 code unit, rather than a 32-bit
+    /// int holding a whole code point
+    pub wchar_utf16: bool,
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target { char_signed: true, wchar_utf16: false }
+    }
+}
@@ -1,18 +1,28 @@
-use crate::error::CompilerError;
+use crate::error::{CompilerError, Warning};
 
 use super::{Context, Declaration};
 
 #[derive(Debug)]
 pub struct TranslationUnit {
     pub declarations: Box<[Declaration]>,
+
+    /// What the parser warned about, which gets passed on to the context
+    /// this is checked in, along with everything else
+    pub warnings: Vec<Warning>,
 }
 
 impl TranslationUnit {
     pub fn verify(&self, context: &mut Context) -> Result<(), CompilerError> {
+        for warning in &self.warnings {
+            context.warn(warning.clone());
+        }
+
         for decl in &self.declarations {
             decl.verify(context)?;
         }
-        
+
+        context.warn_unused();
+
         Ok(())
     }
 }
@@ -1,7 +1,16 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use std::mem::discriminant;
+
+use crate::error::CompilerError;
+
+use super::{attributes::Attributes, declaration::Alignment, expressions::ExpressionKind, Expression, Target};
+
+#[derive(Debug, Clone)]
 pub enum Type {
     Void,
 
+    Bool,  // _Bool, which only ever holds 0 or 1
+    Char,  // plain char, signed or not depending on the target
+
     Uint8, // unsigned char
     Uint16,// unsigned short
     Uint32,// unsigned int
@@ -15,28 +24,261 @@ pub enum Type {
     Float,
     Double,
 
-    // Arrays are pointers
     Pointer(Box<Type>),
 
+    Array(Box<ArrayType>),
+
     Function(Box<FunctionType>),
 
-    // TODO: Structs, enums
+    Struct(Box<StructType>), // Also unions
+
+    Enum(Box<EnumType>),
+
+    /// What a `va_list` is an array of. Only the `va_` builtins look inside
+    /// it, so it's just three QWords, and nothing else has this type
+    VaListTag,
+
+    /// Never directly holds an array (its elements get the qualifiers) or
+    /// another qualified type
+    Qualified(Box<Type>, Qualifiers),
 }
 
+impl PartialEq for Type {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // Enums are just ints as far as type checking goes
+            (Self::Enum(_) | Self::Int32, Self::Enum(_) | Self::Int32) => true,
+
+            (Self::Pointer(a), Self::Pointer(b)) => a == b,
+            (Self::Array(a), Self::Array(b)) => a == b,
+            (Self::Function(a), Self::Function(b)) => a == b,
+            (Self::Struct(a), Self::Struct(b)) => a == b,
+            (Self::Qualified(a, qa), Self::Qualified(b, qb)) => a == b && qa == qb,
+
+            _ => discriminant(self) == discriminant(other),
+        }
+    }
+}
+
+impl Eq for Type {}
+
 impl Type {
     pub fn is_integer(&self) -> bool {
-        match self {
+        matches!(self.unqualified(),
+            Self::Bool  | Self::Char  |
             Self::Uint8 | Self::Uint16 | Self::Uint32 | Self::Uint64 |
-            Self::Int8  | Self::Int16  | Self::Int32  | Self::Int64 => true,
+            Self::Int8  | Self::Int16  | Self::Int32  | Self::Int64 |
+            Self::Enum(_))
+    }
+
+    /// The type of `__builtin_va_list`. SysV makes it an array of one struct
+    /// holding two offsets and two pointers
+    pub fn va_list() -> Type {
+        Type::Array(Box::new(ArrayType {
+            element: Type::VaListTag,
+            length: Some(ExpressionKind::IntLiteral(1).into()),
+        }))
+    }
+
+    /// Whether this is an arithmetic or pointer type, i.e. one that fits in a
+    /// register and can be operated on directly
+    pub fn is_scalar(&self) -> bool {
+        self.is_integer() || matches!(self.unqualified(),
+            Self::Float | Self::Double | Self::Pointer(_))
+    }
+
+    /// Whether it's a signed integer type. Plain char's up to the target
+    pub fn is_signed(&self, target: &Target) -> bool {
+        match self.unqualified() {
+            Self::Char => target.char_signed,
+            t => matches!(t,
+                Self::Int8 | Self::Int16 | Self::Int32 | Self::Int64 | Self::Enum(_)),
+        }
+    }
+
+    /// The type after integer promotion. Anything narrower than an int
+    /// becomes one, since an int can hold all of its values
+    pub fn promoted(&self) -> Type {
+        match self.unqualified() {
+            Self::Bool | Self::Char | Self::Uint8 | Self::Uint16 |
+                Self::Int8 | Self::Int16 | Self::Enum(_) => Self::Int32,
+            t => t.clone(),
+        }
+    }
+
+    /// The type the usual arithmetic conversions bring two integer operands
+    /// to. Both get promoted, then the narrower one goes to the wider one's
+    /// type, and if they're just as wide, unsigned wins
+    pub fn common_type(&self, other: &Type, target: &Target) -> Type {
+        let (a, b) = (self.promoted(), other.promoted());
+        let is_long = |t: &Type| matches!(t, Self::Int64 | Self::Uint64);
+
+        match (is_long(&a), is_long(&b)) {
+            (true, false) => a,
+            (false, true) => b,
+            _ if !a.is_signed(target) => a,
+            _ => b,
+        }
+    }
+
+    /// A copy of this type that only refers to any structs or enums it defines,
+    /// so that using it again doesn't define them a second time
+    pub fn as_reference(&self) -> Type {
+        match self {
+            Self::Pointer(inner) => Self::Pointer(Box::new(inner.as_reference())),
+
+            Self::Array(a) => Self::Array(Box::new(ArrayType {
+                element: a.element.as_reference(),
+                length: a.length.clone(),
+            })),
+
+            Self::Function(f) => Self::Function(Box::new(FunctionType {
+                return_type: f.return_type.as_reference(),
+                args: f.args.iter()
+                    .map(|(n, t)| (n.clone(), t.as_reference()))
+                    .collect(),
+                variadic: f.variadic,
+                prototyped: f.prototyped,
+            })),
+
+            Self::Struct(s) => Self::Struct(Box::new(StructType {
+                kind: s.kind,
+                tag: s.tag.clone(),
+                members: None,
+                packed: false,
+                aligned: None,
+            })),
+
+            Self::Enum(e) => Self::Enum(Box::new(EnumType {
+                tag: e.tag.clone(),
+                members: None,
+            })),
+
+            Self::Qualified(inner, q) =>
+                Self::Qualified(Box::new(inner.as_reference()), *q),
+
+            t => t.clone(),
+        }
+    }
+
+    /// The type a value of this type turns into when it's used in an
+    /// expression. Arrays become a pointer to their first element, functions
+    /// a pointer to themselves, and qualifiers are dropped since they only
+    /// matter for the object itself
+    pub fn decay(self) -> Type {
+        let unqualified = match self {
+            Self::Qualified(inner, _) => *inner,
+            t => t,
+        };
+
+        match unqualified {
+            Self::Array(a) => Self::Pointer(Box::new(a.element)),
+            f @ Self::Function(_) => Self::Pointer(Box::new(f)),
+            t => t,
+        }
+    }
+
+    /// The type without any qualifiers
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Self::Qualified(inner, _) => inner,
+            t => t,
+        }
+    }
 
-            _ => false
+    pub fn qualifiers(&self) -> Qualifiers {
+        match self {
+            Self::Qualified(_, q) => *q,
+            _ => Qualifiers::default(),
         }
     }
 
-    pub fn is_signed(&self) -> bool {
+    /// Adds qualifiers to the type. Qualifying an array qualifies its
+    /// elements instead
+    pub fn qualified(self, qualifiers: Qualifiers) -> Type {
+        if qualifiers.is_empty() {
+            return self;
+        }
+
         match self {
-            Self::Int8 | Self::Int16 | Self::Int32 | Self::Int64 => true,
-            _ => false
+            Self::Array(mut a) => {
+                a.element = a.element.qualified(qualifiers);
+                Self::Array(a)
+            },
+
+            Self::Qualified(inner, q) => Self::Qualified(inner, q.union(qualifiers)),
+
+            t => Self::Qualified(Box::new(t), qualifiers),
+        }
+    }
+
+    /// Whether two types are compatible (C11 6.2.7), i.e. whether they could
+    /// both be the type of the same thing. Unlike `==` this isn't transitive,
+    /// since e.g. `int[]` is compatible with both `int[2]` and `int[3]`
+    pub fn is_compatible(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Self::Pointer(a), Self::Pointer(b)) => a.is_compatible(b),
+            (Self::Array(a), Self::Array(b)) => a.is_compatible(b),
+            (Self::Function(a), Self::Function(b)) => a.is_compatible(b),
+            (Self::Qualified(a, qa), Self::Qualified(b, qb)) => qa == qb && a.is_compatible(b),
+            _ => self == other,
+        }
+    }
+
+    /// Checks that a value of type `from` can be stored in an object of this
+    /// type, giving `mismatch` as the error if the types are different. A
+    /// pointer can gain qualifiers on what it points to, but never lose them.
+    /// Anything scalar can go in a `_Bool`, becoming 0 or 1
+    pub fn check_assign(&self, from: &Type, mismatch: &'static str) ->
+        Result<(), CompilerError> {
+
+        match (self.unqualified(), from.unqualified()) {
+            (Self::Pointer(to), Self::Pointer(from)) => {
+                if !to.unqualified().is_compatible(from.unqualified()) {
+                    return Err(CompilerError::SemanticError(mismatch));
+                }
+
+                if !to.qualifiers().contains(from.qualifiers()) {
+                    return Err(CompilerError::SemanticError("Conversion discards qualifiers"));
+                }
+
+                Ok(())
+            },
+
+            (to, from) if to.is_compatible(from) => Ok(()),
+
+            (Self::Bool, from) if from.is_scalar() => Ok(()),
+
+            _ => Err(CompilerError::SemanticError(mismatch)),
+        }
+    }
+
+    /// Combines this type with an earlier declaration's (which it's known to
+    /// be compatible with), keeping a prototype or an array length if only the
+    /// earlier one had it
+    pub fn composite(self, previous: &Type) -> Type {
+        match (&self, previous) {
+            (Self::Function(f), Self::Function(p)) if !f.prototyped && p.prototyped =>
+                previous.clone(),
+            (Self::Array(a), Self::Array(p)) if a.length.is_none() && p.length.is_some() =>
+                previous.clone(),
+            _ => self,
+        }
+    }
+
+    /// Converts an integer constant to this type, wrapping it the same way a
+    /// cast would at runtime. Non-integer types leave the value untouched
+    pub fn convert_constant(&self, value: i64, target: &Target) -> i64 {
+        match self.unqualified() {
+            Self::Bool => (value != 0) as i64,
+            Self::Char if self.is_signed(target) => value as i8 as i64,
+            Self::Char | Self::Uint8 => value as u8 as i64,
+            Self::Uint16 => value as u16 as i64,
+            Self::Uint32 => value as u32 as i64,
+            Self::Int8 => value as i8 as i64,
+            Self::Int16 => value as i16 as i64,
+            Self::Int32 | Self::Enum(_) => value as i32 as i64,
+            _ => value,
         }
     }
 }
@@ -45,21 +287,352 @@ impl Type {
 pub struct FunctionType {
     pub return_type: Type,
     pub args: Box<[(String, Type)]>,
+
+    /// Whether it takes more args after these, e.g. `int printf(const char *, ...)`
+    pub variadic: bool,
+
+    /// False for `int f()` and old-style definitions, which don't say what
+    /// args the function takes, so calls to it aren't checked
+    pub prototyped: bool,
 }
 
 impl PartialEq for FunctionType {
     fn eq(&self, other: &Self) -> bool {
-        if self.return_type != other.return_type {
+        // Doesnt matter if arg names match, only types
+        self.return_type == other.return_type
+            && self.variadic == other.variadic
+            && self.prototyped == other.prototyped
+            && self.args.len() == other.args.len()
+            && self.args.iter().zip(&other.args).all(|((_, t1), (_, t2))| t1 == t2)
+    }
+}
+
+impl FunctionType {
+    pub fn is_compatible(&self, other: &FunctionType) -> bool {
+        if !self.return_type.is_compatible(&other.return_type) {
             return false;
         }
 
-        // Doesnt matter if arg names match, only types
-        for ((_, t1), (_, t2)) in self.args.iter().zip(&other.args) {
-            if t1 != t2 {
-                return false;
+        // Without a prototype, any args are fine
+        if !self.prototyped || !other.prototyped {
+            return true;
+        }
+
+        // Qualifiers on the args themselves don't matter to the caller
+        self.variadic == other.variadic
+            && self.args.len() == other.args.len()
+            && self.args.iter().zip(&other.args)
+                .all(|((_, t1), (_, t2))| t1.unqualified().is_compatible(t2.unqualified()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayType {
+    pub element: Type,
+
+    /// None if it isn't given, e.g. `extern int a[];`. Enum constants can be
+    /// used here, so it only gets worked out once we know what they are
+    pub length: Option<Expression>,
+}
+
+/// An array's length as far as comparing types goes. Lengths that aren't plain
+/// numbers can't be compared without their constants, so those are all alike
+#[derive(PartialEq)]
+enum LengthKey {
+    Unknown,
+    Known(u64),
+    Unevaluated,
+}
+
+impl PartialEq for ArrayType {
+    fn eq(&self, other: &Self) -> bool {
+        self.element == other.element && self.length_key() == other.length_key()
+    }
+}
+
+impl ArrayType {
+    fn length_key(&self) -> LengthKey {
+        match self.length.as_ref().map(|l| &l.kind) {
+            None => LengthKey::Unknown,
+            Some(ExpressionKind::IntLiteral(n)) => LengthKey::Known(*n),
+            Some(_) => LengthKey::Unevaluated,
+        }
+    }
+
+    /// An array of unknown length is compatible with any other length, and
+    /// lengths we can't compare yet get the benefit of the doubt
+    pub fn is_compatible(&self, other: &ArrayType) -> bool {
+        if !self.element.is_compatible(&other.element) {
+            return false;
+        }
+
+        match (self.length_key(), other.length_key()) {
+            (LengthKey::Known(a), LengthKey::Known(b)) => a == b,
+            _ => true,
+        }
+    }
+}
+
+/// Codegen does every load and store exactly as written, so `volatile` doesn't
+/// need handling there yet. Anything that starts optimizing those away has to
+/// leave volatile objects alone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Qualifiers {
+    pub is_const: bool,
+    pub is_volatile: bool,
+    pub is_restrict: bool,
+}
+
+impl Qualifiers {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn union(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers {
+            is_const: self.is_const || other.is_const,
+            is_volatile: self.is_volatile || other.is_volatile,
+            is_restrict: self.is_restrict || other.is_restrict,
+        }
+    }
+
+    /// Whether we have every qualifier `other` has
+    pub fn contains(&self, other: Qualifiers) -> bool {
+        self.union(other) == *self
+    }
+}
+
+/// What sort of type a tag (e.g. the `foo` in `struct foo`) refers to. They
+/// all share the same namespace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagKind {
+    Struct,
+    Union,
+    Enum,
+}
+
+/// Structs and unions only differ in how their members are laid out, so they
+/// share a representation
+#[derive(Debug, Clone)]
+pub struct StructType {
+    pub kind: TagKind,
+
+    /// Identifies which struct this is. The parser gives a struct shadowing
+    /// another with the same tag an id of its own, and anonymous structs a
+    /// unique made-up one, so they can be looked up just like any other
+    pub tag: String,
+
+    /// The struct's members, if this is where it gets defined. Otherwise it's
+    /// just referring to a struct defined elsewhere
+    pub members: Option<Box<[StructMember]>>,
+
+    /// From `__attribute__((packed))`, for a struct without any padding
+    pub packed: bool,
+
+    /// From `__attribute__((aligned(n)))`, for a struct aligned more strictly
+    /// than its members need
+    pub aligned: Option<Expression>,
+}
+
+impl PartialEq for StructType {
+    fn eq(&self, other: &Self) -> bool {
+        // A struct's definition and references to it are the same type
+        self.kind == other.kind && self.tag == other.tag
+    }
+}
+
+impl Eq for StructType {}
+
+/// A member of a struct or union. Bit-fields have a width, and unnamed ones
+/// only take up space (or, with a width of 0, pad to the next unit)
+#[derive(Debug, Clone)]
+pub struct StructMember {
+    pub name: Option<String>,
+    pub type_of: Type,
+    pub width: Option<Expression>,
+
+    /// What any `_Alignas` specifiers asked for
+    pub alignments: Vec<Alignment>,
+
+    /// Only `packed` and `aligned` get this far, and change how the member is
+    /// laid out like they would for a whole struct
+    pub attributes: Attributes,
+}
+
+/// An enum constant, with its value if it's given explicitly
+pub type Enumerator = (String, Option<Expression>);
+
+/// Enums are ints with some named constants to go with them
+#[derive(Debug, Clone)]
+pub struct EnumType {
+    /// Anonymous enums get a made-up tag, same as structs
+    pub tag: String,
+
+    /// The enum's constants, if this is where it gets defined. Otherwise it's
+    /// just referring to an enum defined elsewhere
+    pub members: Option<Box<[Enumerator]>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::Context, fs::read_file, parser::{parse, parse_with_mode, LanguageMode}};
+
+    use super::*;
+
+    fn verify_file(ending: &str) -> Result<(), CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/types/");
+        p.push(ending);
+
+        parse(&read_file(&p)?)?.verify(&mut Context::new())
+    }
+
+    fn expect_semantic_error(ending: &str) -> Result<(), CompilerError> {
+        match verify_file(ending) {
+            Ok(_) => panic!("Verified when we shouldn't have"),
+            Err(e) => match e.root() {
+                CompilerError::SemanticError(_) => Ok(()),
+                _ => Err(e)
             }
         }
+    }
+
+    #[test]
+    fn qualifiers() -> Result<(), CompilerError> {
+        verify_file("qualifiers.c")
+    }
+
+    #[test]
+    fn qualified_arrays() {
+        let array = Type::Array(Box::new(ArrayType {
+            element: Type::Int32,
+            length: Some(ExpressionKind::IntLiteral(3).into()),
+        }));
+        let constant = Qualifiers { is_const: true, ..Qualifiers::default() };
+
+        match array.qualified(constant) {
+            Type::Array(a) => assert_eq!(a.element.qualifiers(), constant),
+            t => panic!("Expected an array, got {:?}", t),
+        }
+    }
+
+    #[test]
+    fn const_assign() -> Result<(), CompilerError> {
+        expect_semantic_error("const_assign.c")
+    }
+
+    #[test]
+    fn const_member() -> Result<(), CompilerError> {
+        expect_semantic_error("const_member.c")
+    }
+
+    #[test]
+    fn const_struct() -> Result<(), CompilerError> {
+        expect_semantic_error("const_struct.c")
+    }
+
+    #[test]
+    fn const_pointee() -> Result<(), CompilerError> {
+        expect_semantic_error("const_pointee.c")
+    }
+
+    #[test]
+    fn discard_assign() -> Result<(), CompilerError> {
+        expect_semantic_error("discard_assign.c")
+    }
+
+    #[test]
+    fn discard_call() -> Result<(), CompilerError> {
+        expect_semantic_error("discard_call.c")
+    }
+
+    #[test]
+    fn restrict_int() -> Result<(), CompilerError> {
+        expect_semantic_error("restrict_int.c")
+    }
+
+    #[test]
+    fn specifiers() -> Result<(), CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/types/specifiers.c");
+        let unit = parse(&read_file(&p)?)?;
+
+        let types: Vec<Type> = unit.declarations.iter()
+            .take(12)
+            .map(|d| d.type_of.unqualified().clone())
+            .collect();
+        assert_eq!(types, [Type::Uint32, Type::Int16, Type::Int64, Type::Int64,
+            Type::Uint64, Type::Uint64, Type::Int64, Type::Int32, Type::Int8,
+            Type::Uint8, Type::Char, Type::Bool]);
+        assert!(unit.declarations[6].type_of.qualifiers().is_const);
+
+        p.set_file_name("invalid_specifiers.c");
+        assert!(matches!(parse(&read_file(&p)?), Err(CompilerError::ParseError(_))));
+
+        verify_file("specifiers.c")
+    }
+
+    #[test]
+    fn bool_keyword() -> Result<(), CompilerError> {
+        // Only C23 takes `bool` for itself, so it can still be a typedef name
+        let input = read_file(&PathBuf::from("tests/files/unit/types/bool_name.c"))?;
+        let unit = parse(&input)?;
+        assert_eq!(unit.declarations[1].type_of, Type::Int32);
+        verify_file("bool_name.c")?;
+
+        assert!(parse_with_mode(&input, LanguageMode::C23).is_err());
+        let unit = parse_with_mode("bool flag;", LanguageMode::C23)?;
+        assert_eq!(unit.declarations[0].type_of, Type::Bool);
+        Ok(())
+    }
+
+    #[test]
+    fn plain_char() {
+        // Its own type, but with the target's signedness (x86 is signed)
+        let unsigned = Target { char_signed: false, ..Target::default() };
+        assert_ne!(Type::Char, Type::Int8);
+        assert_ne!(Type::Char, Type::Uint8);
+        assert!(Type::Char.is_signed(&Target::default()));
+        assert_eq!(Type::Char.convert_constant(200, &Target::default()), -56);
+        assert!(!Type::Char.is_signed(&unsigned));
+        assert_eq!(Type::Char.convert_constant(200, &unsigned), 200);
+    }
+
+    #[test]
+    fn compatibility() -> Result<(), CompilerError> {
+        let array = |length: Option<u64>| Type::Array(Box::new(ArrayType {
+            element: Type::Int32,
+            length: length.map(|l| ExpressionKind::IntLiteral(l).into()),
+        }));
+
+        // `==` is exact, while an unknown length is compatible with any other
+        assert_ne!(array(None), array(Some(2)));
+        assert!(array(None).is_compatible(&array(Some(2))));
+        assert!(array(None).is_compatible(&array(Some(3))));
+        assert!(!array(Some(2)).is_compatible(&array(Some(3))));
+
+        let function = |prototyped| Type::Function(Box::new(FunctionType {
+            return_type: Type::Int32,
+            args: Box::new([(String::from("x"), Type::Int32)]),
+            variadic: false,
+            prototyped,
+        }));
+        assert_ne!(function(false), function(true));
+        assert!(function(false).is_compatible(&function(true)));
+
+        expect_semantic_error("incompatible.c")?;
+        verify_file("compatible.c")
+    }
+
+    #[test]
+    fn bool_conversions() -> Result<(), CompilerError> {
+        assert_eq!(Type::Bool.convert_constant(256, &Target::default()), 1);
+        assert_eq!(Type::Bool.convert_constant(0, &Target::default()), 0);
+
+        let pointer = Type::Pointer(Box::new(Type::Int32));
+        Type::Bool.check_assign(&pointer, "Mismatch")?;
+        assert!(Type::Int32.check_assign(&Type::Bool, "Mismatch").is_err());
 
-        true
+        verify_file("bool.c")
     }
 }
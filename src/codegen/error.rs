@@ -1,7 +1,29 @@
 use thiserror::Error;
 
+use crate::ast::Span;
+
 #[derive(Error, Debug)]
 pub enum CodegenError {
     #[error("Out of scratch registers")]
     OutOfScratch,
+
+    #[error("Unknown register \"{0}\" in asm clobbers")]
+    UnknownClobber(String),
+
+    #[error("Asm operand is in a register it also clobbers")]
+    ClobberedOperand,
+
+    #[error("{error} (at {span})")]
+    Spanned { span: Span, error: Box<CodegenError> },
+}
+
+impl CodegenError {
+    /// Points the error at `span`, unless a nested node already has
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            Self::Spanned { .. } => self,
+            _ if span.is_empty() => self,
+            error => Self::Spanned { span, error: Box::new(error) },
+        }
+    }
 }
@@ -4,7 +4,7 @@ use error::CodegenError;
 use target_lexicon::Architecture;
 use x86_64::X86_64Generator;
 
-use crate::{ast::TranslationUnit, error::CompilerError};
+use crate::{ast::{Target, TranslationUnit}, error::CompilerError};
 
 pub mod triple;
 pub mod error;
@@ -35,11 +35,11 @@ pub trait Generator {
     ) -> Result<(), CompilerError>;
 }
 
-pub fn get_generator(arch: &Architecture) -> 
+pub fn get_generator(arch: &Architecture, target: Target) ->
     Result<Box<dyn Generator>, CompilerError> {
 
     match arch {
-        Architecture::X86_64 => Ok(Box::new(X86_64Generator::new())),
+        Architecture::X86_64 => Ok(Box::new(X86_64Generator::new(target))),
         _ => Err(CompilerError::NotSupported("Targeting non x86_64")),
     }
 }
@@ -1,4 +1,6 @@
-use target_lexicon::{Architecture, Triple};
+use target_lexicon::{Architecture, OperatingSystem, Triple, Vendor};
+
+use crate::ast::Target;
 
 pub fn get_triple() -> Triple {
     let mut triple = Triple::host();
@@ -6,3 +8,27 @@ pub fn get_triple() -> Triple {
 
     triple
 }
+
+/// Whether plain `char` is signed on the target. It is on x86, but most other
+/// architectures make it unsigned, apart from on Apple's platforms
+pub fn char_is_signed(triple: &Triple) -> bool {
+    match triple.architecture {
+        Architecture::X86_32(_) | Architecture::X86_64 => true,
+        Architecture::Aarch64(_) => triple.vendor == Vendor::Apple,
+        _ => false,
+    }
+}
+
+/// Whether `wchar_t` is a 16-bit UTF-16 code unit, like on Windows. Everywhere
+/// else it's a 32-bit int holding a whole code point
+pub fn wchar_is_utf16(triple: &Triple) -> bool {
+    triple.operating_system == OperatingSystem::Windows
+}
+
+/// What the AST needs to know about the target
+pub fn get_target(triple: &Triple) -> Target {
+    Target {
+        char_signed: char_is_signed(triple),
+        wchar_utf16: wchar_is_utf16(triple),
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{ast::{asm::{AsmConstraint, AsmStatement, TemplatePart}, expressions::ExpressionKind, Expression}, codegen::error::CodegenError};
+
+use super::{helpers::get_size, instance::{GeneratorInstance, Scratch}, instructions::Instr, registers::{Register, RegisterSize, SizedRegister}};
+
+/// Where an asm operand is while the template runs
+enum Operand {
+    /// A register its constraint asked for by name
+    Register(SizedRegister),
+
+    /// Whichever scratch register we picked for an `r`
+    Scratch(Scratch),
+
+    /// Memory at the address in the scratch
+    Memory(Scratch, RegisterSize),
+
+    Immediate(i64),
+
+    /// Wherever the numbered output is
+    Matching(usize),
+}
+
+impl Operand {
+    fn register(&self) -> Option<SizedRegister> {
+        match self {
+            Operand::Register(reg) => Some(reg.clone()),
+            Operand::Scratch(scratch) => Some(scratch.reg.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Writes operand `n` the way it goes in the template. Operands are numbered
+/// outputs first, then inputs
+fn render(outputs: &[Operand], inputs: &[Operand], n: usize) -> String {
+    let operand = if n < outputs.len() {
+        &outputs[n]
+    } else {
+        &inputs[n - outputs.len()]
+    };
+
+    match operand {
+        Operand::Register(reg) => reg.to_string(),
+        Operand::Scratch(scratch) => scratch.reg.to_string(),
+        Operand::Memory(addr, size) => format!("{} [{}]", size, addr.reg),
+        Operand::Immediate(value) => value.to_string(),
+        Operand::Matching(m) => render(outputs, inputs, *m),
+    }
+}
+
+/// The registers an asm statement says it clobbers. "memory" and "cc" don't
+/// matter to us, since we never keep values in either across a statement
+fn get_clobbered(asm: &AsmStatement) -> Result<Vec<Register>, CodegenError> {
+    asm.clobbers.iter()
+        .filter(|c| !matches!(c.as_str(), "memory" | "cc"))
+        .map(|c| Register::from_name(c.trim_start_matches('%'))
+            .ok_or_else(|| CodegenError::UnknownClobber(c.clone())))
+        .collect()
+}
+
+impl GeneratorInstance {
+    pub fn gen_asm(&mut self, asm: &AsmStatement) -> Result<(), CodegenError> {
+        if !asm.extended {
+            self.add_asm_lines(&asm.template);
+            return Ok(());
+        }
+
+        let clobbered = get_clobbered(asm)?;
+
+        let fixed: Vec<Register> = asm.operands()
+            .filter_map(|o| match asm.resolve(o) {
+                AsmConstraint::Register(c) => Register::from_constraint(c),
+                _ => None,
+            })
+            .collect();
+
+        if fixed.iter().any(|reg| clobbered.contains(reg)) {
+            return Err(CodegenError::ClobberedOperand);
+        }
+
+        let mut used: Vec<Register> = fixed.iter().chain(&clobbered).copied().collect();
+        used.sort_by_key(|reg| *reg as u8);
+        used.dedup();
+
+        // Hold on to any of these we use as scratch, so that working out the
+        // operands doesn't put anything in them
+        let mut held: Vec<Scratch> = used.iter()
+            .filter_map(|&reg| self.claim_scratch(reg, RegisterSize::QWord))
+            .collect();
+
+        // Values that need moving into registers right before the template
+        let mut loads: Vec<(SizedRegister, Scratch)> = vec![];
+
+        let mut outputs = vec![];
+        for output in &asm.outputs {
+            let size = get_size(&self.get_expr_type(&output.expr), self);
+
+            let operand = match output.location() {
+                AsmConstraint::Register(c) => Operand::Register(SizedRegister {
+                    reg: Register::from_constraint(c).unwrap(),
+                    size,
+                }),
+                AsmConstraint::AnyRegister => Operand::Scratch(self.alloc_scratch(size)?),
+                AsmConstraint::Memory => Operand::Memory(self.gen_address(&output.expr)?, size),
+                _ => unreachable!("Outputs are checked to be registers or memory"),
+            };
+
+            // Memory already has the value in it
+            if output.is_read_write() && let Some(reg) = operand.register() {
+                loads.push((reg, self.gen_expr(&output.expr)?));
+            }
+
+            outputs.push(operand);
+        }
+
+        let mut inputs = vec![];
+        for input in &asm.inputs {
+            let size = get_size(&self.get_expr_type(&input.expr), self);
+
+            let operand = match input.location() {
+                AsmConstraint::Register(c) => {
+                    let reg = SizedRegister { reg: Register::from_constraint(c).unwrap(), size };
+                    loads.push((reg.clone(), self.gen_expr(&input.expr)?));
+                    Operand::Register(reg)
+                },
+                AsmConstraint::AnyRegister => Operand::Scratch(self.gen_expr(&input.expr)?),
+                AsmConstraint::Memory => Operand::Memory(self.gen_address(&input.expr)?, size),
+                AsmConstraint::Immediate => Operand::Immediate(input.expr.eval_constant(self)
+                    .expect("Asm immediates must be constant")),
+                AsmConstraint::Matching(n) => {
+                    let reg = outputs[n].register()
+                        .expect("Asm inputs can only match register outputs");
+                    loads.push((reg, self.gen_expr(&input.expr)?));
+                    Operand::Matching(n)
+                },
+            };
+
+            inputs.push(operand);
+        }
+
+        // Arguments live in registers, so save any that are about to be
+        // overwritten
+        let saved: Vec<String> = used.iter()
+            .filter(|reg| self.arg_regs.contains(reg))
+            .map(|&reg| SizedRegister { reg, size: RegisterSize::QWord }.to_string())
+            .collect();
+
+        for reg in &saved {
+            self.add_instr(Instr::Push(reg.clone()));
+        }
+
+        for (dest, value) in loads {
+            let dest = SizedRegister { reg: dest.reg, size: value.reg.size };
+            self.add_instr(Instr::Mov(dest.to_string(), value.reg.to_string()));
+        }
+
+        let mut text = String::new();
+        for part in asm.template_parts().expect("Asm template must be valid") {
+            match part {
+                TemplatePart::Text(s) => text.push_str(s),
+                TemplatePart::Operand(n) => text.push_str(&render(&outputs, &inputs, n)),
+            }
+        }
+
+        self.add_asm_lines(&text);
+        drop(inputs);
+
+        // Outputs can be arguments, so get them out of the way before we
+        // restore those
+        let mut results = vec![];
+        for (output, operand) in asm.outputs.iter().zip(outputs) {
+            let value = match operand {
+                // If it's one we're holding, it can just stay there
+                Operand::Register(reg) => match held.iter().position(|h| h.reg.reg == reg.reg) {
+                    Some(i) => {
+                        let mut value = held.swap_remove(i);
+                        value.reg.size = reg.size;
+                        value
+                    },
+                    None => {
+                        let value = self.alloc_scratch(reg.size)?;
+                        self.add_instr(Instr::Mov(value.reg.to_string(), reg.to_string()));
+                        value
+                    },
+                },
+                Operand::Scratch(value) => value,
+                _ => continue,
+            };
+
+            results.push((&output.expr, value));
+        }
+
+        for reg in saved.iter().rev() {
+            self.add_instr(Instr::Pop(reg.clone()));
+        }
+
+        for (target, value) in results {
+            self.gen_store(target, &value.reg)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a value into an lvalue
+    fn gen_store(&mut self, target: &Expression, value: &SizedRegister) ->
+        Result<(), CodegenError> {
+
+        if let ExpressionKind::Identifier(id) = &target.kind {
+            let symbol = self.get_symbol(id).expect("Undefined").asm_rep;
+            self.add_instr(Instr::Mov(symbol, value.to_string()));
+        } else if let Some(bits) = self.get_bit_field(target) {
+            let addr = self.gen_address(target)?;
+            let copy = self.alloc_scratch(value.size)?;
+            self.add_instr(Instr::Mov(copy.reg.to_string(), value.to_string()));
+
+            let type_of = self.get_expr_type(target);
+            self.gen_bit_field_store(&addr, copy, &type_of, bits)?;
+        } else {
+            let addr = self.gen_address(target)?;
+            self.add_instr(Instr::Mov(
+                format!("{} [{}]", value.size, addr.reg),
+                value.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Adds each line of an asm template as an instruction
+    fn add_asm_lines(&mut self, text: &str) {
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            self.add_instr(Instr::Raw(line.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{Context, Target}, codegen::{error::CodegenError, x86_64::{tests::find_lines, X86_64Generator}, Generator}, error::CompilerError, fs::read_file, parser::parse};
+
+    fn generate(ending: &str) -> Result<String, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/asm/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(&mut Context::new())?;
+
+        Ok(X86_64Generator::new(Target::default()).generate(&parsed)?)
+    }
+
+    #[test]
+    fn templates() -> Result<(), CompilerError> {
+        let asm = generate("asm.c")?;
+
+        assert!(asm.contains("\trdtsc\n"));
+        assert!(asm.contains("\tcpuid\n"));
+        assert!(asm.contains("\tmfence\n\tlfence\n"));
+        assert!(!asm.contains('%'));
+
+        // The `+` output gets loaded first, and stored back after
+        find_lines(&asm, &["mov %0, EDI", "mov %1, ESI", "mov %2, %0", "add %2, %1", "mov EDI, %2"]);
+
+        // The immediate gets substituted in, and the input goes wherever the
+        // output it matches is
+        find_lines(&asm, &["mov %0, EDI", "mov %1, %0", "shl %1, 3", "mov EDI, %1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn constraints() -> Result<(), CompilerError> {
+        let asm = generate("constraints.c")?;
+
+        // An early clobber output gets a register of its own
+        let regs = find_lines(&asm, &[
+            "mov %0, EDI", "mov %1, %0", "and %1, 15", "mov DWORD [RBP - 4], %1"]);
+        assert_ne!(regs[0], regs[1]);
+
+        // Given the choice, operands go in registers
+        find_lines(&asm, &[
+            "mov %0, QWORD [counter]", "mov %1, %0", "inc %1", "mov QWORD [counter], %1"]);
+        find_lines(&asm, &[
+            "mov %0, DWORD [RBP - 4]", "mov %1, EDI", "mov %2, %0", "add %2, %1",
+            "mov DWORD [RBP - 4], %2"]);
+        find_lines(&asm, &["mov %0, EDI", "mov %1, %0", "mov %2, %2", "mov BYTE [RBP - 1], %2"]);
+
+        // Unless memory's the only option
+        find_lines(&asm, &["lea %0, [RBP - 8]", "not QWORD [%0]"]);
+        find_lines(&asm, &[
+            "lea %0, [counter]", "mov %1, 2", "movsxd %2, %1", "add QWORD [%0], %2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn saves_args() -> Result<(), CompilerError> {
+        let asm = generate("asm.c")?;
+
+        // cpuid overwrites RCX and RDX, which hold two of its pointers
+        assert!(asm.contains("\tpush RCX\n\tpush RDX\n"));
+        assert!(asm.contains("\tpop RDX\n\tpop RCX\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_clobber() {
+        let e = generate("unknown_clobber.c").expect_err("Unknown register");
+        assert!(matches!(e.root(), CompilerError::CodegenError(CodegenError::UnknownClobber(_))));
+    }
+
+    #[test]
+    fn clobbered_operand() {
+        let e = generate("clobbered_operand.c").expect_err("RAX is an operand");
+        assert!(matches!(e.root(), CompilerError::CodegenError(CodegenError::ClobberedOperand)));
+    }
+}
@@ -1,55 +1,124 @@
 
-use crate::{ast::{declaration::DeclarationValue, Declaration, Expression, Type}, codegen::error::CodegenError};
+use crate::{ast::{declaration::{DeclarationValue, StorageClass}, initializer::{resolve_initializer, InitField, InitItem}, layout::{declare_tag_only, declare_types, Constants}, statements::find_labels, expressions::{CompoundLiteralExpr, ExpressionKind, StringLiteralExpr}, Declaration, Expression, Type}, codegen::error::CodegenError};
 
-use super::{helpers::get_size, instance::{GeneratorInstance, ScopeVariable}, instructions::Instr, registers::{SizedRegister, ARG_REGS}};
+use super::{helpers::{get_bytes, get_global_asm, get_memory_asm, get_size}, instance::{GeneratorInstance, Scratch, ScopeVariable}, instructions::Instr, registers::{RegisterSize, SizedRegister, ARG_REGS}};
 
 impl GeneratorInstance {
     pub fn gen_declaration(&mut self, decl: &Declaration) -> 
         Result<(), CodegenError> {
         
-        let symbol = decl.name.clone();
+        // Static asserts were already checked when verifying
+        if let Some(DeclarationValue::StaticAssert(_)) = decl.value {
+            return Ok(());
+        }
+
+        // Already verified, so declaring types can't fail
+        let symbol = match &decl.name {
+            Some(n) => n.clone(),
+            None => {
+                declare_tag_only(&decl.type_of, self).unwrap();
+                return Ok(());
+            },
+        };
+
+        declare_types(&decl.type_of, self).unwrap();
+
+        if decl.typedef {
+            return Ok(());
+        }
+
+        // Already verified, so the initializer's fine. Arrays like
+        // `int a[] = { ... }` get their length from it
+        let type_of = match &decl.value {
+            Some(DeclarationValue::List(items)) =>
+                self.resolve_initializer(&decl.type_of, items).0,
+            _ => decl.type_of.clone(),
+        };
+
+        let align = decl.align_of(&type_of, self).unwrap_or(1);
+
+        let is_static = decl.storage == Some(StorageClass::Static);
+
+        if is_static && self.global_scope() {
+            self.set_internal(symbol.clone());
+        }
+
+        // Static locals live alongside the globals, under a label that can't
+        // clash with any other function's
+        if is_static && !self.global_scope() {
+            let label = format!("{}.{}", symbol, self.new_label());
+            let asm_rep = get_global_asm(&label, &type_of, self);
+            self.add_attributes(&label, &decl.attributes);
+
+            self.add_symbol_with_asm(symbol, ScopeVariable {
+                asm_rep,
+                type_of: type_of.clone(),
+            });
+
+            self.gen_static_data(label, &type_of, decl.value.as_ref(), align);
+            return Ok(());
+        }
+
+        if self.global_scope() {
+            self.add_attributes(&symbol, &decl.attributes);
+        }
 
         match &decl.value {
             None => {
-                if decl.external {
-                    self.add_extern(symbol.clone(), decl.type_of.clone());
-                } else {
-                    if self.global_scope() {
-                        self.add_global(symbol.clone(), decl.type_of.clone());
+                let external = decl.storage == Some(StorageClass::Extern)
+                    || matches!(type_of, Type::Function(_));
 
-                        match decl.type_of {
-                            Type::Function(_) => (),
-                            _ => self.add_bss(symbol, &decl.type_of),
-                        };
-                    } else {
-                        self.add_local(symbol, decl.type_of.clone());
-                    }
+                if external {
+                    self.add_extern(symbol, type_of.clone());
+                } else if self.global_scope() {
+                    self.add_global(symbol.clone(), type_of.clone());
+                    self.add_tentative(symbol, type_of.clone(), align);
+                } else {
+                    self.add_local(symbol, type_of.clone(), align);
                 }
             },
 
             Some(val) => match (self.global_scope(), val)  {
                 (true, DeclarationValue::Function(stmts)) => {
                     self.add_global(symbol.clone(), decl.type_of.clone());
-                    self.add_fn_label(symbol);
+                    self.define_function(&symbol);
+                    self.enter_code_section(&symbol);
+                    self.add_fn_label(symbol.clone());
 
                     let ret_label = self.new_label();
                     self.return_label = Some(ret_label);
+
+                    // Labels are function-wide, so set them all up front in
+                    // case we goto one before it's defined
+                    for label in find_labels(stmts) {
+                        let id = self.new_label();
+                        self.user_labels.insert(label.name.clone(), id);
+                    }
+
                     let _s = self.enter_scope();
 
                     self.add_instr(Instr::Push("RBP".to_string()));
                     self.add_instr(
                         Instr::Mov("RBP".to_string(), "RSP".to_string())
                     );
+                    self.begin_frame();
 
-                    let args = if let Type::Function(ftype) = &decl.type_of {
-                        &ftype.args
+                    let ftype = if let Type::Function(ftype) = &decl.type_of {
+                        ftype
                     } else {
                         panic!("Function decl type must be func");
                     };
 
+                    self.return_type = ftype.return_type.clone();
+                    let args = &ftype.args;
+
+                    if ftype.variadic {
+                        self.gen_save_area(args.len());
+                    }
+
                     for (i, (arg_n, arg_t)) in args.iter().enumerate() {
                         let symbol = arg_n.clone();
-                        let size = get_size(arg_t);
+                        let size = get_size(arg_t, self);
 
                         let asm_rep = if i < 6 {
                             let reg = SizedRegister {
@@ -76,46 +145,438 @@ impl GeneratorInstance {
                         self.gen_statement(stmt)?;
                     }
 
+                    self.reserve_frame();
                     self.add_label(ret_label);
                     self.add_instr(
                         Instr::Mov("RSP".to_string(), "RBP".to_string())
                     );
                     self.add_instr(Instr::Pop("RBP".to_string()));
                     self.add_instr(Instr::Ret);
+                    self.leave_code_section(&symbol);
 
                     self.return_label = None;
+                    self.save_area = None;
                     self.arg_regs.clear();
+                    self.user_labels.clear();
                 },
 
-                (true, DeclarationValue::Variable(e)) => {
-                    self.add_global(symbol.clone(), decl.type_of.clone());
-                    
-                    let asm = match e {
-                        // TODO: Handle sizes
-                        Expression::IntLiteral(i) => format!("dd {}", i),
+                (true, val) => {
+                    self.add_global(symbol.clone(), type_of.clone());
+                    self.define_global(&symbol);
+                    self.gen_static_data(symbol, &type_of, Some(val), align);
+                },
+
+                (false, DeclarationValue::Function(_)) => 
+                    panic!("Can't define local function!"),
+
+                (_, DeclarationValue::StaticAssert(_)) =>
+                    panic!("Static asserts don't declare anything"),
+
+                (false, DeclarationValue::Variable(e)) => {
+                    let asm_var = self.add_local(symbol, type_of.clone(), align);
+                    let mut asm_val = self.gen_expr(e)?;
+                    let from = self.get_expr_type(e);
+                    self.gen_convert(&mut asm_val, &from, &type_of);
+
+                    if let Type::Struct(_) = type_of.unqualified() {
+                        let dest = self.gen_var_address(&asm_var)?;
+                        let size = get_bytes(&type_of, self);
+                        self.gen_copy(&dest.reg, &asm_val.reg, size)?;
+                    } else {
+                        self.add_instr(Instr::Mov(asm_var, asm_val.reg.to_string()));
+                    }
+                },
+
+                (false, DeclarationValue::List(items)) => {
+                    let asm_var = self.add_local(symbol, type_of.clone(), align);
+                    let dest = self.gen_var_address(&asm_var)?;
+                    self.gen_init_list(&dest, &type_of, items)?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Works out what an already verified initializer list sets
+    pub fn resolve_initializer<'a>(&self, type_of: &Type, items: &'a [InitItem]) ->
+        (Type, Vec<InitField<'a>>) {
+
+        resolve_initializer(type_of, items, self, &|e| self.get_expr_type(e))
+            .expect("Initializers were verified")
+    }
+
+    /// Gives a compound literal its slot in the frame, which lasts until the
+    /// end of the enclosing block, and fills it in, giving back its address
+    pub fn gen_compound_literal(&mut self, literal: &CompoundLiteralExpr) ->
+        Result<Scratch, CodegenError> {
+
+        // Already verified, so the list's fine
+        let (type_of, _) = self.resolve_initializer(&literal.type_of, &literal.items);
+
+        let align = type_of.align_of(self).unwrap_or(1);
+        let rbp_offset = self.alloc_stack(get_bytes(&type_of, self), align);
+
+        let dest = self.gen_var_address(&format!("[RBP - {}]", rbp_offset))?;
+        self.gen_init_list(&dest, &type_of, &literal.items)?;
+
+        Ok(dest)
+    }
+
+    /// Fills in the object at `dest` from a brace-enclosed list
+    fn gen_init_list(&mut self, dest: &Scratch, type_of: &Type, items: &[InitItem])
+        -> Result<(), CodegenError> {
+
+        // Whatever the list doesn't cover is zero
+        let size = get_bytes(type_of, self);
+        self.gen_zero(&dest.reg, size)?;
+
+        let (_, fields) = self.resolve_initializer(type_of, items);
+
+        for field in fields {
+            let mut value = self.gen_expr(field.value)?;
+            let from = self.get_expr_type(field.value);
+            self.gen_convert(&mut value, &from, &field.type_of);
+            let location = format!("[{} + {}]", dest.reg, field.offset);
+
+            // A string literal or struct setting a whole array or struct gets
+            // copied in from its address
+            if !field.type_of.is_scalar() {
+                let addr = self.alloc_scratch(RegisterSize::QWord)?;
+                self.add_instr(Instr::Lea(addr.reg.to_string(), location));
+
+                let size = get_bytes(&field.type_of, self);
+                self.gen_copy(&addr.reg, &value.reg, size)?;
+                continue;
+            }
+
+            match field.bits {
+                Some(bits) => {
+                    let addr = self.alloc_scratch(RegisterSize::QWord)?;
+                    self.add_instr(Instr::Lea(addr.reg.to_string(), location));
+                    self.gen_bit_field_store(&addr, value, &field.type_of, bits)?;
+                },
+                None => self.add_instr(Instr::Mov(
+                    get_memory_asm(&location, &field.type_of, self),
+                    value.reg.to_string())),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl GeneratorInstance {
+    /// Lays out a global or static local in the data section, or in the BSS
+    /// section if there's no initializer
+    fn gen_static_data(&mut self, label: String, type_of: &Type,
+        value: Option<&DeclarationValue>, align: usize) {
+
+        match value {
+            None => self.add_bss(label, type_of, align),
+
+            Some(DeclarationValue::Variable(e)) => {
+                let directive = get_directive(get_bytes(type_of, self));
+                let value = self.get_static_value(&label, type_of, e);
+
+                self.add_data_aligned(label, format!("{} {}", directive, value),
+                    align);
+            },
+
+            Some(DeclarationValue::List(items)) => {
+                let size = get_bytes(type_of, self);
+
+                let (_, fields) = self.resolve_initializer(type_of, items);
+
+                // Later fields replace whatever earlier ones they overlap,
+                // except bit-fields, which get merged in a byte at a time with
+                // whatever bit-fields share those bytes. Each field is placed
+                // as a line of data, or each byte of a bit-field as a value
+                let mut placed: Vec<(usize, usize, String, Option<u8>)> = vec![];
+
+                for field in fields {
+                    if let Some(bits) = field.bits {
+                        let value = field.value.eval_constant(self)
+                            .expect("Static initializers are constant");
+                        let value = field.type_of.convert_constant(value, self.target()) as u64;
+                        let value = (value << bits.shift) & bits.mask();
+
+                        for i in 0..bits.bytes() {
+                            let offset = field.offset + i;
+                            let mask = (bits.mask() >> (i * 8)) as u8;
+
+                            let old = placed.iter()
+                                .find(|(o, _, _, byte)| *o == offset && byte.is_some())
+                                .and_then(|(_, _, _, byte)| *byte);
 
-                        Expression::CharLiteral(c) => format!("db {}", c),
+                            placed.retain(|(o, size, _, _)| o + size <= offset
+                                || offset < *o);
 
-                        Expression::StringLiteral(s) => format!("db {}, 0", s),
+                            let byte = old.unwrap_or(0) & !mask | (value >> (i * 8)) as u8;
+                            placed.push((offset, 1, byte.to_string(), Some(byte)));
+                        }
 
-                        _ => panic!("Must init global w/ a literal"),
+                        continue;
+                    }
+
+                    let bytes = get_bytes(&field.type_of, self);
+
+                    placed.retain(|(offset, size, _, _)| offset + size <= field.offset
+                        || field.offset + bytes <= *offset);
+
+                    let line = match &field.value.kind {
+                        // A string literal filling in a character array
+                        ExpressionKind::StringLiteral(s) if !field.type_of.is_scalar() => {
+                            let unit_bytes = get_bytes(&s.encoding.element_type(self.target()), self);
+                            string_data(s, unit_bytes, bytes / unit_bytes)
+                        },
+
+                        _ => {
+                            let value = self.get_static_value(&label, &field.type_of,
+                                field.value);
+                            format!("{} {}", get_directive(bytes), value)
+                        },
                     };
 
-                    self.add_data(symbol, asm);
+                    placed.push((field.offset, bytes, line, None));
                 }
 
-                (false, DeclarationValue::Function(_)) => 
-                    panic!("Can't define local function!"),
+                placed.sort_by_key(|(offset, _, _, _)| *offset);
 
-                (false, DeclarationValue::Variable(e)) => {
-                    let asm_var = self.add_local(symbol, decl.type_of.clone());
-                    let asm_val = self.gen_expr(e)?;
+                let mut lines: Vec<String> = vec![];
+                let mut used = 0;
+                let mut in_bytes = false;
 
-                    self.add_instr(Instr::Mov(asm_var, asm_val.reg.to_string()));
+                for (offset, bytes, line, byte) in placed {
+                    if offset > used {
+                        lines.push(format!("times {} db 0", offset - used));
+                        in_bytes = false;
+                    }
+
+                    // Runs of bit-field bytes go on one line
+                    match (byte, lines.last_mut()) {
+                        (Some(_), Some(last)) if in_bytes => *last += &format!(", {}", line),
+                        (Some(_), _) => lines.push(format!("db {}", line)),
+                        (None, _) => lines.push(line),
+                    }
+
+                    in_bytes = byte.is_some();
+                    used = offset + bytes;
                 }
-            }
+
+                if size > used {
+                    lines.push(format!("times {} db 0", size - used));
+                }
+
+                self.add_data_aligned(label, lines.join("\n\t"), align);
+            },
+
+            Some(DeclarationValue::Function(_)) =>
+                panic!("Functions aren't data"),
+
+            Some(DeclarationValue::StaticAssert(_)) =>
+                panic!("Static asserts aren't data"),
+        }
+    }
+
+    /// Gets the value to put in the data section for a literal being stored
+    /// in an object of type `type_of`. String literals get their own label
+    /// next to the object they're in, with the object holding a pointer to it
+    fn get_static_value(&mut self, label: &str, type_of: &Type, value: &Expression)
+        -> String {
+
+        match &value.kind {
+            ExpressionKind::StringLiteral(s) => {
+                let string_label = format!("{}.{}", label, self.new_label());
+                self.add_string(string_label.clone(), s);
+                string_label
+            },
+
+            _ => {
+                let value = value.eval_constant(self)
+                    .expect("Static initializers are constant");
+                type_of.convert_constant(value, self.target()).to_string()
+            },
         }
+    }
+
+    /// Puts a string literal's code units in the data section under `label`,
+    /// with a null terminator
+    pub fn add_string(&mut self, label: String, literal: &StringLiteralExpr) {
+        let bytes = get_bytes(&literal.encoding.element_type(self.target()), self);
+        let data = string_data(literal, bytes, literal.units.len() + 1);
+
+        self.add_data_aligned(label, data, bytes);
+    }
+}
+
+/// The data for the first `count` of a string literal's code units (each
+/// `bytes` big), counting its null terminator
+fn string_data(literal: &StringLiteralExpr, bytes: usize, count: usize) -> String {
+    let units: Vec<String> = literal.units.iter()
+        .chain([&0])
+        .take(count)
+        .map(u32::to_string)
+        .collect();
+
+    format!("{} {}", get_directive(bytes), units.join(", "))
+}
+
+/// The data directive for a scalar of the given size
+fn get_directive(bytes: usize) -> &'static str {
+    match bytes {
+        1 => "db",
+        2 => "dw",
+        4 => "dd",
+        8 => "dq",
+        _ => panic!("Static initializers must be scalars"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{Context, Target}, codegen::{x86_64::X86_64Generator, Generator}, error::CompilerError, fs::read_file, parser::parse};
+
+    fn generate(ending: &str) -> Result<String, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(&mut Context::new())?;
+
+        Ok(X86_64Generator::new(Target::default()).generate(&parsed)?)
+    }
+
+    #[test]
+    fn linkage() -> Result<(), CompilerError> {
+        let asm = generate("declaration/linkage.c")?;
+
+        assert!(asm.contains("GLOBAL counter\n"));
+        assert!(asm.contains("EXTERN elsewhere\n"));
+
+        // Statics (and whatever inherits their linkage) stay in this file
+        assert!(!asm.contains("GLOBAL helper"));
+        assert!(!asm.contains("GLOBAL hidden"));
+        assert!(!asm.contains("EXTERN helper"));
+
+        // Tentative definitions share one spot in the BSS
+        assert_eq!(asm.matches("GLOBAL shared\n").count(), 1);
+        assert_eq!(asm.matches("shared: resb").count(), 1);
+
+        // Static locals get their own labels
+        assert!(asm.contains("count.1: resb 4"));
+        assert!(asm.contains("start.2: dd 5"));
+        Ok(())
+    }
+
+    #[test]
+    fn initializer_lists() -> Result<(), CompilerError> {
+        let asm = generate("initializer/lists.c")?;
+
+        // Gaps between what's listed get zeroed
+        assert!(asm.contains("grid: dd 1\n\tdd 2\n\tdd 3\n\ttimes 8 db 0\n\tdd 9\n"));
+        assert!(asm.contains("pts: times 20 db 0\n\tdd 1\n\tdd 4\n\ttimes 4 db 0\n"));
+        assert!(asm.contains("n: dq 7\n"));
+
+        // Locals get zeroed, then have each field stored
+        assert!(asm.contains(" + 8], "));
+        Ok(())
+    }
+
+    #[test]
+    fn union_initializers() -> Result<(), CompilerError> {
+        let asm = generate("initializer/unions.c")?;
+
+        // The first member gets initialized, even when it's an aggregate
+        assert!(asm.contains("both: dd 1\n\tdd 2\n"));
+        assert!(asm.contains("first: db 4\n\tdb 5\n\ttimes 6 db 0\n"));
+        assert!(asm.contains("picked: dq 6\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn string_initializers() -> Result<(), CompilerError> {
+        let asm = generate("initializer/strings.c")?;
+
+        // The terminator only goes in if there's room, and any more gets zeroed
+        assert!(asm.contains("greeting: db 104, 105, 0\n"));
+        assert!(asm.contains("exact: db 104, 105, 0\n"));
+        assert!(asm.contains("roomy: db 104, 105, 0\n\ttimes 2 db 0\n"));
+        assert!(asm.contains("braced: db 104, 105, 0\n"));
+        assert!(asm.contains("rows: db 97, 98, 0\n\tdb 99, 100, 0\n"));
+
+        // Locals get copied from the string, and structs copied in whole
+        assert!(asm.contains(": db 104, 101, 121, 0\n"));
+        assert_eq!(asm.matches("\tmov DWORD [").count(), 6);
+        assert_eq!(asm.matches("\tmov QWORD [").count(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn alignment() -> Result<(), CompilerError> {
+        let asm = generate("layout/sizeof.c")?;
+
+        // _Alignas can only make things stricter
+        assert!(asm.contains("alignb 16\naligned: resb 4\n"));
+        assert!(asm.contains("alignb 8\npadded: resb 1\n"));
+        assert!(asm.contains("alignb 32\nwide: resb 4\n"));
+
+        // It can go anywhere in the specifiers, and the strictest one wins
+        assert!(asm.contains("alignb 16\nmid: resb 4\n"));
+
+        // The buffer gets bumped from 8 below RBP to 16, so n comes after it,
+        // and the whole frame's reserved up front
+        assert!(asm.contains("\tmov QWORD [RBP - 24], "));
+        assert!(asm.contains("\tmov RBP, RSP\n\tsub RSP, 32\n"));
+
+        // sizeof's operand never runs, so x never gets set to 5
+        assert!(!asm.contains(", 5\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn attributes() -> Result<(), CompilerError> {
+        let asm = generate("attributes/attributes.c")?;
+
+        assert!(asm.contains("GLOBAL counter:weak hidden\n"));
+        assert!(asm.contains("EXTERN maybe:weak\n"));
+        assert!(asm.contains("alignb 16\naligned_more: resb 4\n"));
+
+        // Data in a section of its own gets switched into and back out of
+        assert!(asm.contains("SECTION .rodata.magic progbits alloc noexec write align=16\n\
+            align 4, db 0\nmagic: dd 42\nSECTION .data\n"));
+
+        assert!(asm.contains("SECTION .init_array progbits alloc noexec write align=8\n\tdq init\n"));
+        assert!(asm.contains("SECTION .fini_array progbits alloc noexec write align=8\n\tdq fini\n"));
+
+        // Static constructors still aren't exported
+        assert!(!asm.contains("GLOBAL init"));
+        Ok(())
+    }
+
+    #[test]
+    fn bool_conversions() -> Result<(), CompilerError> {
+        let asm = generate("types/bool.c")?;
+
+        // Constants get converted up front, and take up a single byte
+        assert!(asm.contains("set: db 1\n"));
+        assert!(asm.contains("unset: resb 1\n"));
+
+        // Everything else gets compared against 0, apart from the copy of a
+        // value that's already a _Bool
+        assert_eq!(asm.matches("setne ").count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn static_assert() -> Result<(), CompilerError> {
+        let asm = generate("declaration/static_assert.c")?;
 
+        // They don't take up any space, at file scope or in a function
+        assert!(!asm.contains("SECTION .data\n\t"));
+        assert!(asm.contains("\tsub RSP, 16\n"));
         Ok(())
     }
 }
@@ -1,11 +1,43 @@
 use std::collections::VecDeque;
 
-use crate::{ast::{expressions::BinaryExpr, Expression, Type}, codegen::{error::CodegenError, x86_64::registers::Register}};
+use crate::{ast::{expressions::{AssignOp, BinaryExpr, CompoundAssignExpr, ExpressionKind, MemberExpr}, layout::{BitField, Constants, Layouts, MemberLayout}, types::FunctionType, Expression, Type}, codegen::{error::CodegenError, x86_64::registers::Register}};
 
-use super::{helpers::get_size, instance::{GeneratorInstance, Scratch}, instructions::Instr, registers::{RegisterSize, SizedRegister, ARG_REGS}};
+use super::{helpers::{get_bytes, get_memory_asm, get_memory_operand, get_size}, instance::{GeneratorInstance, Scratch}, instructions::Instr, registers::{RegisterSize, SizedRegister, ARG_REGS}};
 
 enum ComparisonType { Eq, Ne, Lt, Gt, Le, Ge }
 
+/// The instruction for an operation that x86 can do in one go, which takes the
+/// destination and then the source. The destination is both an operand and
+/// where the result goes, and can be in memory
+fn simple_operation(op: AssignOp) -> Option<fn(String, String) -> Instr> {
+    match op {
+        AssignOp::Add => Some(Instr::Add),
+        AssignOp::Subtract => Some(Instr::Sub),
+        AssignOp::BitwiseAnd => Some(Instr::And),
+        AssignOp::BitwiseXor => Some(Instr::Xor),
+        AssignOp::BitwiseOr => Some(Instr::Or),
+        _ => None,
+    }
+}
+
+/// Splits a bit-field's bytes into as few loads or stores as we can do them
+/// in, biggest first, as (offset, size) pairs
+fn bit_field_chunks(bytes: usize) -> Vec<(usize, RegisterSize)> {
+    let mut chunks = vec![];
+    let mut offset = 0;
+
+    for chunk in [RegisterSize::QWord, RegisterSize::DWord,
+        RegisterSize::Word, RegisterSize::Byte] {
+
+        if bytes - offset >= chunk as usize {
+            chunks.push((offset, chunk));
+            offset += chunk as usize;
+        }
+    }
+
+    chunks
+}
+
 impl GeneratorInstance {
     fn get_binary_scratches(&mut self, args: &BinaryExpr) -> 
         Result<(Scratch, Scratch), CodegenError> {
@@ -52,16 +84,628 @@ impl GeneratorInstance {
         Ok(result)
     }
 
+    fn gen_binary(&mut self, args: &BinaryExpr, op: AssignOp) ->
+        Result<Scratch, CodegenError> {
+
+        let signed = self.get_expr_type(&args.first).is_signed(self.target());
+        let (a, b) = self.get_binary_scratches(args)?;
+
+        self.gen_operation(op, a, b, signed)
+    }
+
+    /// Applies `op` to `a` and `b`, leaving the result in `a`. Signedness only
+    /// matters for right shifts and division
+    fn gen_operation(&mut self, op: AssignOp, a: Scratch, b: Scratch, signed: bool)
+        -> Result<Scratch, CodegenError> {
+
+        if let Some(instr) = simple_operation(op) {
+            self.add_instr(instr(a.reg.to_string(), b.reg.to_string()));
+            return Ok(a);
+        }
+
+        match op {
+            // Unlike the simple ones, imul can't write to memory
+            AssignOp::Multiply =>
+                self.add_instr(Instr::Imul(a.reg.to_string(), b.reg.to_string())),
+
+            AssignOp::Divide | AssignOp::Modulo => {
+                let rax = SizedRegister { reg: Register::Rax, size: a.reg.size };
+                let rdx = SizedRegister { reg: Register::Rdx, size: a.reg.size };
+                let result = if op == AssignOp::Divide { rax.clone() } else { rdx.clone() };
+
+                // The dividend is RDX:RAX, so the top half is either its sign
+                // or zeros
+                let (extend, divide) = match (signed, a.reg.size) {
+                    (true, RegisterSize::QWord) => (Instr::Cqo, Instr::Idiv(b.reg.to_string())),
+                    (true, _) => (Instr::Cdq, Instr::Idiv(b.reg.to_string())),
+                    (false, _) => (
+                        Instr::Xor(rdx.to_string(), rdx.to_string()),
+                        Instr::Div(b.reg.to_string()),
+                    ),
+                };
+
+                let instrs = [
+                    Instr::Push("RDX".to_string()),
+                    Instr::Mov(rax.to_string(), a.reg.to_string()),
+                    extend,
+                    divide,
+                    Instr::Mov(a.reg.to_string(), result.to_string()),
+                    Instr::Pop("RDX".to_string()),
+                ];
+
+                for instr in instrs {
+                    self.add_instr(instr);
+                }
+            },
+
+            AssignOp::ShiftLeft | AssignOp::ShiftRight => {
+                // The amount has to be in CL, which might be holding an arg
+                let save_rcx = self.arg_regs.contains(&Register::Rcx);
+                let amount = SizedRegister { reg: b.reg.reg, size: RegisterSize::Byte };
+
+                if save_rcx {
+                    self.add_instr(Instr::Push("RCX".to_string()));
+                }
+
+                self.add_instr(Instr::Mov("CL".to_string(), amount.to_string()));
+
+                let value = a.reg.to_string();
+                let count = "CL".to_string();
+                self.add_instr(match (op, signed) {
+                    (AssignOp::ShiftLeft, _) => Instr::Shl(value, count),
+                    (_, true) => Instr::Sar(value, count),
+                    (_, false) => Instr::Shr(value, count),
+                });
+
+                if save_rcx {
+                    self.add_instr(Instr::Pop("RCX".to_string()));
+                }
+            },
+
+            _ => unreachable!("Simple operations are handled above"),
+        }
+
+        Ok(a)
+    }
+
+    fn gen_compound_assignment(&mut self, x: &CompoundAssignExpr) ->
+        Result<Scratch, CodegenError> {
+
+        let type_of = self.get_expr_type(&x.first);
+
+        if let Some(bits) = self.get_bit_field(&x.first) {
+            let addr = self.gen_address(&x.first)?;
+            let value = self.gen_expr(&x.second)?;
+            let current = self.gen_bit_field_load(&addr, &type_of, bits)?;
+
+            let result = self.gen_compound_operation(x, current, value)?;
+            return self.gen_bit_field_store(&addr, result, &type_of, bits);
+        }
+
+        // Only work out where the target is once, so e.g. `a[f()] += 1` only
+        // calls `f` once
+        let (target, _addr) = match &x.first.kind {
+            ExpressionKind::Identifier(id) =>
+                (self.get_symbol(id).expect("Undefined").asm_rep, None),
+            _ => {
+                let addr = self.gen_address(&x.first)?;
+                let location = format!("[{}]", addr.reg);
+                (get_memory_asm(&location, &type_of, self), Some(addr))
+            },
+        };
+
+        let mut value = match type_of.unqualified() {
+            // Moving a pointer along goes by whole elements
+            Type::Pointer(inner) => self.gen_scaled_index(&x.second, inner)?,
+            _ => self.gen_expr(&x.second)?,
+        };
+
+        // Where we can, just operate on the target in place. Only the result's
+        // low bits get kept, and those don't depend on how wide the operation
+        // is, so the value only has to be the target's size. A `_Bool` still
+        // needs the whole result though
+        if let Some(instr) = simple_operation(x.op)
+            && type_of.unqualified() != &Type::Bool {
+
+            let size = get_size(&type_of, self);
+            let value_t = self.get_expr_type(&x.second);
+            self.gen_extend(&mut value, size, value_t.is_signed(self.target()));
+            value.reg.size = size;
+
+            self.add_instr(instr(target.clone(), value.reg.to_string()));
+            self.add_instr(Instr::Mov(value.reg.to_string(), target));
+            return Ok(value);
+        }
+
+        let current = self.alloc_scratch(get_size(&type_of, self))?;
+        self.add_instr(Instr::Mov(current.reg.to_string(), target.clone()));
+
+        let result = self.gen_compound_operation(x, current, value)?;
+        self.add_instr(Instr::Mov(target, result.reg.to_string()));
+
+        Ok(result)
+    }
+
+    /// Applies a compound assignment's operator to the target's current value.
+    /// Both get converted to their common type first (or for a shift, just
+    /// the target gets promoted), and the result gets converted back to the
+    /// target's type
+    fn gen_compound_operation(&mut self,
+        x: &CompoundAssignExpr,
+        mut current: Scratch,
+        mut value: Scratch
+    ) -> Result<Scratch, CodegenError> {
+
+        let type_of = self.get_expr_type(&x.first);
+        let value_t = self.get_expr_type(&x.second);
+
+        let operation_t = match x.op {
+            AssignOp::ShiftLeft | AssignOp::ShiftRight => type_of.promoted(),
+            _ => type_of.common_type(&value_t, self.target()),
+        };
+
+        let size = get_size(&operation_t, self);
+        let target_size = current.reg.size;
+        self.gen_extend(&mut current, size, type_of.is_signed(self.target()));
+
+        // A shift amount keeps its own type, since only its low byte gets used
+        if !matches!(x.op, AssignOp::ShiftLeft | AssignOp::ShiftRight) {
+            self.gen_extend(&mut value, size, value_t.is_signed(self.target()));
+        }
+
+        let mut result = self.gen_operation(x.op, current, value, operation_t.is_signed(self.target()))?;
+        self.gen_convert(&mut result, &operation_t, &type_of);
+        result.reg.size = target_size;
+
+        Ok(result)
+    }
+
+    /// Loads the address of a variable, given its asm
+    pub fn gen_var_address(&mut self, asm_rep: &str) ->
+        Result<Scratch, CodegenError> {
+
+        let reg = self.alloc_scratch(RegisterSize::QWord)?;
+        let instr = Instr::Lea(
+            reg.reg.to_string(),
+            get_memory_operand(asm_rep).to_string());
+        self.add_instr(instr);
+
+        Ok(reg)
+    }
+
+    /// Gets the address of an lvalue. For a bit-field, that's the address of
+    /// the byte it starts in
+    pub fn gen_address(&mut self, expr: &Expression) ->
+        Result<Scratch, CodegenError> {
+
+        match &expr.kind {
+            ExpressionKind::Identifier(x) => {
+                let var = self.get_symbol(x).expect("Undefined");
+
+                // A function's asm is just its label
+                if let Type::Function(_) = var.type_of {
+                    let reg = self.alloc_scratch(RegisterSize::QWord)?;
+                    self.add_instr(Instr::Lea(
+                        reg.reg.to_string(),
+                        format!("[{}]", var.asm_rep)));
+
+                    return Ok(reg);
+                }
+
+                self.gen_var_address(&var.asm_rep)
+            },
+
+            // The pointer is the address
+            ExpressionKind::Dereference(x) => self.gen_expr(&x.expr),
+
+            ExpressionKind::Member(x) | ExpressionKind::PointerMember(x) =>
+                self.gen_member_address(x),
+
+            ExpressionKind::ArrayIndex(x) => {
+                let element_t = self.get_object_type(expr);
+                let base = self.gen_expr(&x.first)?;
+                let index = self.gen_scaled_index(&x.second, &element_t)?;
+
+                self.add_instr(Instr::Add(base.reg.to_string(), index.reg.to_string()));
+
+                Ok(base)
+            },
+
+            ExpressionKind::CompoundLiteral(x) => self.gen_compound_literal(x),
+
+            ExpressionKind::StringLiteral(x) => {
+                let label = format!("str.{}", self.new_label());
+                self.add_string(label.clone(), x);
+
+                let reg = self.alloc_scratch(RegisterSize::QWord)?;
+                self.add_instr(Instr::Lea(reg.reg.to_string(), format!("[{}]", label)));
+                Ok(reg)
+            },
+
+            ExpressionKind::Generic(_) => self.gen_address(expr.selected(self).expect("Unresolved _Generic")),
+
+            _ => panic!("Address arg must be lvalue"),
+        }
+    }
+
+    /// Evaluates an array index, widened to a QWord so it can be added to an
+    /// address
+    fn gen_index(&mut self, expr: &Expression) -> Result<Scratch, CodegenError> {
+        let mut index = self.gen_expr(expr)?;
+        let signed = self.get_expr_type(expr).is_signed(self.target());
+
+        self.gen_extend(&mut index, RegisterSize::QWord, signed);
+        Ok(index)
+    }
+
+    /// Evaluates an index into an array of `element_t`s, as a number of bytes
+    /// ready to be added to an address
+    fn gen_scaled_index(&mut self, expr: &Expression, element_t: &Type) ->
+        Result<Scratch, CodegenError> {
+
+        let index = self.gen_index(expr)?;
+
+        let size = get_bytes(element_t, self);
+        if size != 1 {
+            self.add_instr(Instr::Imul(index.reg.to_string(), size.to_string()));
+        }
+
+        Ok(index)
+    }
+
+    /// Evaluates the `i`th arg to a call. Any that go in a variadic function's
+    /// `...`, or to a function without a prototype, get promoted to at least
+    /// an int first
+    fn gen_promoted_arg(&mut self, arg: &Expression, i: usize, f: &FunctionType)
+        -> Result<Scratch, CodegenError> {
+
+        let mut value = self.gen_expr(arg)?;
+        let arg_t = self.get_expr_type(arg);
+
+        match f.args.get(i).filter(|_| f.prototyped) {
+            Some((_, param_t)) => self.gen_convert(&mut value, &arg_t, param_t),
+            None => self.gen_extend(&mut value, RegisterSize::DWord, arg_t.is_signed(self.target())),
+        }
+
+        Ok(value)
+    }
+
+    /// Converts a value that's about to be stored in an object of type `to`.
+    /// Type checking means it's already the right type, unless it's going in
+    /// a `_Bool`, in which case anything nonzero becomes 1, or it's an integer
+    /// constant initializing a different integer (or a pointer, if it's 0)
+    pub fn gen_convert(&mut self, value: &mut Scratch, from: &Type, to: &Type) {
+        if to.unqualified() == &Type::Bool && from.unqualified() != &Type::Bool {
+            self.add_instr(Instr::Cmp(value.reg.to_string(), "0".to_string()));
+            value.reg.size = RegisterSize::Byte;
+            self.add_instr(Instr::Setne(value.reg.to_string()));
+            return;
+        }
+
+        if from.is_integer() && to.is_scalar() {
+            let size = get_size(to, self);
+            self.gen_extend(value, size, from.is_signed(self.target()));
+            value.reg.size = size;
+        }
+    }
+
+    /// Sign or zero extends a value in place, if it's smaller than `size`
+    pub fn gen_extend(&mut self, value: &mut Scratch, size: RegisterSize, signed: bool) {
+        let old = value.reg.to_string();
+        let old_size = value.reg.size;
+
+        if old_size as u8 >= size as u8 {
+            return;
+        }
+
+        value.reg.size = size;
+        let new = value.reg.to_string();
+
+        match (old_size, signed) {
+            (RegisterSize::DWord, true) => self.add_instr(Instr::Movsxd(new, old)),
+            // Writing the low DWord clears the rest
+            (RegisterSize::DWord, false) => self.add_instr(Instr::Mov(old.clone(), old)),
+            (_, true) => self.add_instr(Instr::Movsx(new, old)),
+            (_, false) => self.add_instr(Instr::Movzx(new, old)),
+        }
+    }
+
+    /// Gets the address of a struct member. Works for both `.` and `->`, since
+    /// struct values are kept as their address anyway
+    fn gen_member_address(&mut self, expr: &MemberExpr) ->
+        Result<Scratch, CodegenError> {
+
+        let offset = self.get_member(expr).offset;
+        let base = self.gen_expr(&expr.expr)?;
+
+        if offset != 0 {
+            self.add_instr(Instr::Add(base.reg.to_string(), offset.to_string()));
+        }
+
+        Ok(base)
+    }
+
+    fn get_member(&self, expr: &MemberExpr) -> MemberLayout {
+        let struct_t = match self.get_expr_type(&expr.expr) {
+            Type::Pointer(inner) => *inner,
+            t => t,
+        };
+
+        let layout = match struct_t.unqualified() {
+            Type::Struct(s) => self.get_layout(&s.tag).expect("Incomplete struct"),
+            _ => panic!("Member access on non-struct"),
+        };
+
+        layout.get_member(&expr.member).expect("No such member").clone()
+    }
+
+    /// Where the lvalue sits from the byte it starts in, if it's a bit-field
+    pub fn get_bit_field(&self, expr: &Expression) -> Option<BitField> {
+        match &expr.kind {
+            ExpressionKind::Member(x) | ExpressionKind::PointerMember(x) =>
+                self.get_member(x).bits,
+            ExpressionKind::Generic(_) =>
+                self.get_bit_field(expr.selected(self).expect("Unresolved _Generic")),
+            _ => None,
+        }
+    }
+
+    /// Reads a bit-field out of the bytes at `addr`. Shifting it up to the top
+    /// of the register and back down again sign or zero extends it on the way
+    fn gen_bit_field_load(&mut self, addr: &Scratch, type_of: &Type, bits: BitField)
+        -> Result<Scratch, CodegenError> {
+
+        let size = get_size(type_of, self);
+        let value = self.gen_bit_field_read(addr, size, bits)?;
+
+        let unit_bits = size as usize * 8;
+        self.gen_shift(Instr::Shl, &value, unit_bits - bits.shift - bits.width);
+        self.gen_extract(&value, type_of, unit_bits - bits.width);
+
+        Ok(value)
+    }
+
+    /// Writes `value` into a bit-field at `addr`, leaving the rest of the bytes
+    /// it shares alone. Gives back the value as it reads back out of the
+    /// field, i.e. cut down to its width
+    pub fn gen_bit_field_store(&mut self,
+        addr: &Scratch,
+        value: Scratch,
+        type_of: &Type,
+        bits: BitField
+    ) -> Result<Scratch, CodegenError> {
+
+        let size = value.reg.size;
+        let unit_bits = size as usize * 8;
+        let bytes_mask = match bits.bytes() {
+            8 => u64::MAX,
+            b => (1 << (b * 8)) - 1,
+        };
+
+        // Line the new bits up with the field, with zeros either side
+        let field = self.alloc_scratch(size)?;
+        self.add_instr(Instr::Mov(field.reg.to_string(), value.reg.to_string()));
+        self.gen_shift(Instr::Shl, &field, unit_bits - bits.width);
+        self.gen_shift(Instr::Shr, &field, unit_bits - bits.width - bits.shift);
+
+        let mut unit = self.gen_bit_field_read(addr, size, bits)?;
+
+        // `and` can only take a sign-extended 32-bit immediate
+        let keep = !bits.mask() & bytes_mask;
+        if matches!(size, RegisterSize::QWord) && i32::try_from(keep as i64).is_err() {
+            let mask = self.alloc_scratch(size)?;
+            self.add_instr(Instr::Mov(mask.reg.to_string(), keep.to_string()));
+            self.add_instr(Instr::And(unit.reg.to_string(), mask.reg.to_string()));
+        } else {
+            self.add_instr(Instr::And(unit.reg.to_string(), keep.to_string()));
+        }
+
+        self.add_instr(Instr::Or(unit.reg.to_string(), field.reg.to_string()));
+
+        // Only the bytes the field spans get written back, since the rest of
+        // the unit could be other members
+        let mut done = 0;
+        for (offset, chunk) in bit_field_chunks(bits.bytes()) {
+            unit.reg.size = size;
+            self.gen_shift(Instr::Shr, &unit, (offset - done) * 8);
+            done = offset;
+
+            unit.reg.size = chunk;
+            self.add_instr(Instr::Mov(
+                format!("{} [{} + {}]", chunk, addr.reg, offset),
+                unit.reg.to_string()));
+        }
+
+        let shift = unit_bits - bits.width;
+        self.gen_shift(Instr::Shl, &value, shift);
+        self.gen_extract(&value, type_of, shift);
+
+        Ok(value)
+    }
+
+    /// Reads the bytes a bit-field spans into the bottom of a register of
+    /// `size`. Whatever ends up above them is garbage
+    fn gen_bit_field_read(&mut self, addr: &Scratch, size: RegisterSize, bits: BitField)
+        -> Result<Scratch, CodegenError> {
+
+        let chunks = bit_field_chunks(bits.bytes());
+        let mut value = self.alloc_scratch(size)?;
+
+        // The rest of the chunks get loaded separately and or-ed in
+        let mut tmp = match chunks.len() {
+            1 => None,
+            _ => Some(self.alloc_scratch(size)?),
+        };
+
+        for (offset, chunk) in chunks {
+            let target = match &mut tmp {
+                Some(tmp) if offset > 0 => tmp,
+                _ => &mut value,
+            };
+            let location = format!("{} [{} + {}]", chunk, addr.reg, offset);
+
+            // Zero extend the smaller chunks, so they don't leave garbage in
+            // the bytes the next ones go in
+            match chunk {
+                RegisterSize::Byte | RegisterSize::Word => {
+                    target.reg.size = RegisterSize::DWord;
+                    self.add_instr(Instr::Movzx(target.reg.to_string(), location));
+                },
+                _ => {
+                    target.reg.size = chunk;
+                    self.add_instr(Instr::Mov(target.reg.to_string(), location));
+                },
+            }
+
+            target.reg.size = size;
+            if let Some(tmp) = tmp.as_ref().filter(|_| offset > 0) {
+                self.gen_shift(Instr::Shl, tmp, offset * 8);
+                self.add_instr(Instr::Or(value.reg.to_string(), tmp.reg.to_string()));
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Shifts a value down by `amount`, sign extending it if it's signed
+    fn gen_extract(&mut self, value: &Scratch, type_of: &Type, amount: usize) {
+        match type_of.is_signed(self.target()) {
+            true => self.gen_shift(Instr::Sar, value, amount),
+            false => self.gen_shift(Instr::Shr, value, amount),
+        }
+    }
+
+    fn gen_shift(&mut self, shift: fn(String, String) -> Instr, value: &Scratch,
+        amount: usize) {
+
+        if amount > 0 {
+            self.add_instr(shift(value.reg.to_string(), amount.to_string()));
+        }
+    }
+
+    /// Copies `size` bytes from the address in `src` to the one in `dest`
+    pub fn gen_copy(&mut self,
+        dest: &SizedRegister,
+        src: &SizedRegister,
+        size: usize
+    ) -> Result<(), CodegenError> {
+
+        let mut tmp = self.alloc_scratch(RegisterSize::QWord)?;
+        let mut offset = 0;
+
+        // Go in the biggest chunks we can
+        for chunk in [RegisterSize::QWord, RegisterSize::DWord,
+            RegisterSize::Word, RegisterSize::Byte] {
+
+            tmp.reg.size = chunk;
+            let chunk_bytes = chunk as usize;
+
+            while size - offset >= chunk_bytes {
+                self.add_instr(Instr::Mov(
+                    tmp.reg.to_string(),
+                    format!("{} [{} + {}]", chunk, src, offset)));
+                self.add_instr(Instr::Mov(
+                    format!("{} [{} + {}]", chunk, dest, offset),
+                    tmp.reg.to_string()));
+
+                offset += chunk_bytes;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Zeroes `size` bytes starting at the address in `dest`
+    pub fn gen_zero(&mut self, dest: &SizedRegister, size: usize) ->
+        Result<(), CodegenError> {
+
+        let mut zero = self.alloc_scratch(RegisterSize::QWord)?;
+        self.add_instr(Instr::Xor(zero.reg.to_string(), zero.reg.to_string()));
+
+        let mut offset = 0;
+
+        for chunk in [RegisterSize::QWord, RegisterSize::DWord,
+            RegisterSize::Word, RegisterSize::Byte] {
+
+            zero.reg.size = chunk;
+            let chunk_bytes = chunk as usize;
+
+            while size - offset >= chunk_bytes {
+                self.add_instr(Instr::Mov(
+                    format!("{} [{} + {}]", chunk, dest, offset),
+                    zero.reg.to_string()));
+
+                offset += chunk_bytes;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads a value of the given type from the address in `addr`. Structs are
+    /// kept as their address, so they're left alone
+    fn gen_load(&mut self, addr: Scratch, type_of: &Type) ->
+        Result<Scratch, CodegenError> {
+
+        if let Type::Struct(_) | Type::Array(_) | Type::Function(_) = type_of.unqualified() {
+            return Ok(addr);
+        }
+
+        let size = get_size(type_of, self);
+        let value = self.alloc_scratch(size)?;
+        self.add_instr(Instr::Mov(
+            value.reg.to_string(),
+            format!("{} [{}]", size, addr.reg)));
+
+        Ok(value)
+    }
+
+    /// Works out the type of an (already verified) expression, following the
+    /// same rules as `Expression::verify`
+    pub fn get_expr_type(&self, expr: &Expression) -> Type {
+        expr.expr_type(self).expect("Verified expressions can be typed")
+    }
+
+    /// Same as `get_expr_type`, but without arrays or functions decaying
+    fn get_object_type(&self, expr: &Expression) -> Type {
+        expr.object_type(self).expect("Verified expressions can be typed")
+    }
+
     pub fn gen_expr(&mut self, expr: &Expression) -> 
         Result<Scratch, CodegenError> {
+
+        self.gen_expr_kind(expr).map_err(|e| e.at(expr.span))
+    }
+
+    fn gen_expr_kind(&mut self, expr: &Expression) ->
+        Result<Scratch, CodegenError> {
         
-        match expr {
-            Expression::Assignment(x) => {
-                if let Expression::Identifier(id) = &x.first {
-                    let symbol = self.get_symbol(&id)
+        match &expr.kind {
+            ExpressionKind::Comma(x) => {
+                self.gen_expr(&x.first)?;
+                self.gen_expr(&x.second)
+            },
+
+            ExpressionKind::CompoundAssignment(x) => self.gen_compound_assignment(x),
+
+            ExpressionKind::Assignment(x) => {
+                let type_of = self.get_expr_type(&x.first);
+
+                if let Type::Struct(_) = type_of {
+                    let dest = self.gen_address(&x.first)?;
+                    let src = self.gen_expr(&x.second)?;
+                    let size = get_bytes(&type_of, self);
+
+                    self.gen_copy(&dest.reg, &src.reg, size)?;
+
+                    Ok(dest)
+                } else if let ExpressionKind::Identifier(id) = &x.first.kind {
+                    let symbol = self.get_symbol(id)
                         .expect("Undefined").asm_rep.to_owned();
 
-                    let scratch = self.gen_expr(&x.second)?;
+                    let mut scratch = self.gen_expr(&x.second)?;
+                    let from = self.get_expr_type(&x.second);
+                    self.gen_convert(&mut scratch, &from, &type_of);
 
                     let instr = Instr::Mov(
                         symbol,
@@ -69,19 +713,35 @@ impl GeneratorInstance {
                     self.add_instr(instr);
 
                     Ok(scratch)
+                } else if let Some(bits) = self.get_bit_field(&x.first) {
+                    let addr = self.gen_address(&x.first)?;
+                    let mut scratch = self.gen_expr(&x.second)?;
+                    let from = self.get_expr_type(&x.second);
+                    self.gen_convert(&mut scratch, &from, &type_of);
+
+                    self.gen_bit_field_store(&addr, scratch, &type_of, bits)
                 } else {
-                    // TODO: Support other lvalues:
-                    todo!("Can't assign to non identifier right now");
+                    let addr = self.gen_address(&x.first)?;
+                    let mut scratch = self.gen_expr(&x.second)?;
+                    let from = self.get_expr_type(&x.second);
+                    self.gen_convert(&mut scratch, &from, &type_of);
+
+                    let instr = Instr::Mov(
+                        format!("{} [{}]", scratch.reg.size, addr.reg),
+                        scratch.reg.to_string());
+                    self.add_instr(instr);
+
+                    Ok(scratch)
                 }
             }
 
-            Expression::Ternary(expr) => {
+            ExpressionKind::Ternary(expr) => {
                 let condition = self.gen_expr(&expr.condition)?;
 
                 let to_branch = self.new_label();
                 let to_end = self.new_label();
-                // TODO: Get size
-                let result = self.alloc_scratch(RegisterSize::DWord)?;
+                let size = get_size(&self.get_expr_type(&expr.true_expr), self);
+                let result = self.alloc_scratch(size)?;
 
                 let cmp = Instr::Cmp(condition.reg.to_string(), "0".to_string());
                 let je = Instr::Je(to_branch);
@@ -104,7 +764,7 @@ impl GeneratorInstance {
                 Ok(result)
             },
 
-            Expression::LogicalOr(expr) => {
+            ExpressionKind::LogicalOr(expr) => {
                 let (a, b) = self.get_binary_scratches(expr)?;
                 let result = self.alloc_scratch(RegisterSize::Byte)?;
 
@@ -137,7 +797,7 @@ impl GeneratorInstance {
                 Ok(result)
             },
 
-            Expression::LogicalAnd(expr) => {
+            ExpressionKind::LogicalAnd(expr) => {
                 let (a, b) = self.get_binary_scratches(expr)?;
                 let result = self.alloc_scratch(RegisterSize::Byte)?;
 
@@ -166,180 +826,69 @@ impl GeneratorInstance {
                 Ok(result)
             }
 
-            Expression::BitwiseOr(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
+            ExpressionKind::BitwiseOr(args) => self.gen_binary(args, AssignOp::BitwiseOr),
+            ExpressionKind::BitwiseXor(args) => self.gen_binary(args, AssignOp::BitwiseXor),
+            ExpressionKind::BitwiseAnd(args) => self.gen_binary(args, AssignOp::BitwiseAnd),
 
-                let instr = Instr::Or(a.reg.to_string(), b.reg.to_string());
-                self.add_instr(instr);
-
-                Ok(a)
-            },
-
-            Expression::BitwiseXor(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
-
-                let instr = Instr::Xor(a.reg.to_string(), b.reg.to_string());
-                self.add_instr(instr);
-
-                Ok(a)
-            },
-
-            Expression::BitwiseAnd(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
-
-                let instr = Instr::And(a.reg.to_string(), b.reg.to_string());
-                self.add_instr(instr);
-
-                Ok(a)
-            },
-
-            Expression::Equality(expr) =>
+            ExpressionKind::Equality(expr) =>
                 self.gen_comparison(expr, ComparisonType::Eq),
 
-            Expression::Inequality(expr) => 
+            ExpressionKind::Inequality(expr) => 
                 self.gen_comparison(expr, ComparisonType::Ne),
 
-            Expression::LTCompare(expr) =>
+            ExpressionKind::LTCompare(expr) =>
                 self.gen_comparison(expr, ComparisonType::Lt),
 
-            Expression::GTCompare(expr) => 
+            ExpressionKind::GTCompare(expr) => 
                 self.gen_comparison(expr, ComparisonType::Gt),
 
-            Expression::LECompare(expr) =>
+            ExpressionKind::LECompare(expr) =>
                 self.gen_comparison(expr, ComparisonType::Le),
 
-            Expression::GECompare(expr) => 
+            ExpressionKind::GECompare(expr) => 
                 self.gen_comparison(expr, ComparisonType::Ge),
 
-            // TODO bitshifts
-            Expression::ShiftLeft(_) | Expression::ShiftRight(_) =>
-                todo!("Bitshifting"),
-
-            Expression::Add(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
-
-                let instr = Instr::Add(a.reg.to_string(), b.reg.to_string());
-                self.add_instr(instr);
-
-                Ok(a)
-            },
-
-            Expression::Subtract(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
+            ExpressionKind::ShiftLeft(args) => self.gen_binary(args, AssignOp::ShiftLeft),
+            ExpressionKind::ShiftRight(args) => self.gen_binary(args, AssignOp::ShiftRight),
 
-                let instr = Instr::Sub(a.reg.to_string(), b.reg.to_string());
-                self.add_instr(instr);
+            ExpressionKind::Add(args) => self.gen_binary(args, AssignOp::Add),
+            ExpressionKind::Subtract(args) => self.gen_binary(args, AssignOp::Subtract),
+            ExpressionKind::Multiply(args) => self.gen_binary(args, AssignOp::Multiply),
+            ExpressionKind::Divide(args) => self.gen_binary(args, AssignOp::Divide),
+            ExpressionKind::Modulo(args) => self.gen_binary(args, AssignOp::Modulo),
 
-                Ok(a)
-            },
-
-            Expression::Multiply(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
-
-                let instr = Instr::Imul(a.reg.to_string(), b.reg.to_string());
-                self.add_instr(instr);
-
-                Ok(a)
-            },
-
-            Expression::Divide(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
-
-                let rax = SizedRegister { reg: Register::Rax, size: a.reg.size }
-                    .to_string();
-
-                let instrs = [
-                    Instr::Push("RDX".to_string()),
-                    Instr::Mov(rax.clone(), a.reg.to_string()),
-                    Instr::Cqo,
-                    Instr::Idiv(b.reg.to_string()),
-                    Instr::Mov(a.reg.to_string(), rax),
-                    Instr::Pop("RDX".to_string()),
-                ];
-
-                for instr in instrs {
-                    self.add_instr(instr);
-                }
-
-                Ok(a)
-            },
-            
-            Expression::Modulo(args) => {
-                let (a, b) = self.get_binary_scratches(args)?;
-
-                let rax = SizedRegister { reg: Register::Rax, size: a.reg.size }
-                    .to_string();
-                
-                let rdx = SizedRegister { reg: Register::Rdx, size: a.reg.size }
-                    .to_string();
-
-                let instrs = [
-                    Instr::Push("RDX".to_string()),
-                    Instr::Mov(rax.clone(), a.reg.to_string()),
-                    Instr::Cqo,
-                    Instr::Idiv(b.reg.to_string()),
-                    Instr::Mov(a.reg.to_string(), rdx),
-                    Instr::Pop("RDX".to_string()),
-                ];
-
-                for instr in instrs {
-                    self.add_instr(instr);
-                }
-
-                Ok(a)
-            },
-
-            Expression::Cast(cast) => {
+            ExpressionKind::Cast(cast) => {
                 let mut reg = self.gen_expr(&cast.expr)?;
+                let from = self.get_expr_type(&cast.expr);
 
-                let old = reg.reg.to_string();
-                let old_sz = reg.reg.size as u8;
-                reg.reg.size = get_size(&cast.cast_to);
-                let new = reg.reg.to_string();
-                let new_sz = reg.reg.size as u8;
-
-                if new_sz > old_sz {
-                    let instr = Instr::Movsx(new, old);
-                    self.add_instr(instr);
+                if cast.cast_to.unqualified() == &Type::Bool {
+                    self.gen_convert(&mut reg, &from, &cast.cast_to);
+                    return Ok(reg);
                 }
 
+                // Widening depends on the signedness of what we started with,
+                // while narrowing just uses the low part of the register
+                let size = get_size(&cast.cast_to, self);
+                self.gen_extend(&mut reg, size, from.is_signed(self.target()));
+                reg.reg.size = size;
+
                 Ok(reg)
             }
 
             // TODO Incrementors
-            Expression::PreInc(_) | Expression::PreDec(_) |
-                Expression::PostInc(_) | Expression::PostDec(_) => todo!(),
+            ExpressionKind::PreInc(_) | ExpressionKind::PreDec(_) |
+                ExpressionKind::PostInc(_) | ExpressionKind::PostDec(_) => todo!(),
             
-            Expression::AddressOf(expr) => {
-                match &expr.expr {
-                    Expression::Identifier(x) => {
-                        let reg = self.alloc_scratch(RegisterSize::QWord)?;
-                        let x_asm = &self.get_symbol(&x).expect("Undefined").asm_rep;
-                        let instr = Instr::Lea(
-                            reg.reg.to_string(), 
-                            x_asm.to_string());
-                        self.add_instr(instr);
-                        Ok(reg)
-                    },
-
-                    // TODO Other pointery types
-                    Expression::Dereference(_) | Expression::ArrayIndex(_) => 
-                        todo!(),
-
-                    _ => panic!("Address arg must be lvalue"),
-                }
-            }
-
-            Expression::Dereference(expr) => {
-                let a = self.gen_expr(&expr.expr)?;
+            ExpressionKind::AddressOf(expr) => self.gen_address(&expr.expr),
 
-                let instr = Instr::Mov(a.reg.to_string(), format!("[{}]", a.reg));
-                self.add_instr(instr);
+            ExpressionKind::Dereference(x) => {
+                let type_of = self.get_object_type(expr);
+                let a = self.gen_expr(&x.expr)?;
 
-                Ok(a)
+                self.gen_load(a, &type_of)
             }
 
-            Expression::Negate(expr) => {
+            ExpressionKind::Negate(expr) => {
                 let a = self.gen_expr(&expr.expr)?;
 
                 let instr = Instr::Neg(a.reg.to_string());
@@ -348,7 +897,7 @@ impl GeneratorInstance {
                 Ok(a)
             }
 
-            Expression::BitwiseNot(expr) => {
+            ExpressionKind::BitwiseNot(expr) => {
                 let a = self.gen_expr(&expr.expr)?;
 
                 let instr = Instr::Not(a.reg.to_string());
@@ -357,7 +906,7 @@ impl GeneratorInstance {
                 Ok(a)
             }
 
-            Expression::LogicalNot(expr) => {
+            ExpressionKind::LogicalNot(expr) => {
                 let a = self.gen_expr(&expr.expr)?;
 
                 let to_branch = self.new_label();
@@ -383,42 +932,76 @@ impl GeneratorInstance {
                 Ok(a)
             }
 
-            // TODO sizeof
-            Expression::SizeOf(_) => todo!("Sizeof"),
+            // Only the operand's type matters, it never actually gets run
+            ExpressionKind::SizeOf(x) => {
+                let size = get_bytes(&self.get_object_type(&x.expr), self);
 
-            Expression::ArrayIndex(args) => {
-                let (arr, ind) = self.get_binary_scratches(args)?;
+                let scratch = self.alloc_scratch(RegisterSize::QWord)?;
+                let instr = Instr::Mov(scratch.reg.to_string(), size.to_string());
+                self.add_instr(instr);
 
-                /* TODO: Figure out how to get the size (answer: store type in
-                 * exprs)
-                let size = ??
+                Ok(scratch)
+            }
 
-                let instr = Instr::Mov(
-                    arr.reg.to_string()
-                    format!("[{} + {}*{}]", arr, ind, */
+            // Already verified, so these are constant
+            ExpressionKind::SizeOfType(_) | ExpressionKind::AlignOf(_) => {
+                let value = expr.eval_constant(self).unwrap();
 
-                todo!()
+                let scratch = self.alloc_scratch(RegisterSize::QWord)?;
+                let instr = Instr::Mov(scratch.reg.to_string(), value.to_string());
+                self.add_instr(instr);
+
+                Ok(scratch)
             }
 
-            Expression::FuncCall(expr) => {
-                let fn_name = match &expr.func {
-                    Expression::Identifier(s) => s,
-                    _ => panic!("Function needs to be identifier"),
-                };
+            ExpressionKind::ArrayIndex(_) => {
+                let type_of = self.get_object_type(expr);
+                let addr = self.gen_address(expr)?;
 
-                let var = self.get_symbol(fn_name)
-                    .expect("Undefined").clone();
+                self.gen_load(addr, &type_of)
+            }
+
+            ExpressionKind::Member(x) | ExpressionKind::PointerMember(x) => {
+                let type_of = self.get_object_type(expr);
+                let addr = self.gen_member_address(x)?;
+
+                match self.get_member(x).bits {
+                    Some(bits) => self.gen_bit_field_load(&addr, &type_of, bits),
+                    None => self.gen_load(addr, &type_of),
+                }
+            }
 
-                let ret_type = match var.type_of {
-                    Type::Function(f) => f.return_type,
+            ExpressionKind::FuncCall(expr) => {
+                let f_type = match self.get_expr_type(&expr.func) {
+                    Type::Pointer(f) => match *f {
+                        Type::Function(f) => f,
+                        _ => panic!("Function type needs to be function!"),
+                    },
                     _ => panic!("Function type needs to be function!"),
                 };
 
-                let is_void = match ret_type {
-                    Type::Void => true,
-                    _ => false,
+                let ret_type = f_type.return_type.clone();
+
+                let direct = match &expr.func.kind {
+                    ExpressionKind::Identifier(id) => self.get_symbol(id)
+                        .filter(|var| matches!(var.type_of, Type::Function(_)))
+                        .map(|var| var.asm_rep),
+                    _ => None,
+                };
+
+                // Named functions get called directly, anything else is a
+                // pointer we need to work out first. Scratches are never arg
+                // registers, so setting the args won't clobber it
+                let (target, _pointer) = match direct {
+                    Some(label) => (label, None),
+                    None => {
+                        let pointer = self.gen_expr(&expr.func)?;
+                        (pointer.reg.to_string(), Some(pointer))
+                    },
                 };
 
+                let is_void = matches!(ret_type, Type::Void);
+
                 // Set reg args
                 let mut pop_instrs = VecDeque::new();
                 for (i, arg) in expr.args.iter().enumerate() {
@@ -426,7 +1009,7 @@ impl GeneratorInstance {
                         break;
                     }
 
-                    let arg_scratch = self.gen_expr(arg)?;
+                    let arg_scratch = self.gen_promoted_arg(arg, i, &f_type)?;
 
                     let arg_reg = SizedRegister {
                         reg: ARG_REGS[i],
@@ -440,6 +1023,7 @@ impl GeneratorInstance {
 
                         self.add_instr(Instr::Push(to_push.to_string()));
                         pop_instrs.push_front(Instr::Pop(to_push.to_string()));
+                        self.pushed += 8;
                     }
 
                     let mov = Instr::Mov(
@@ -449,36 +1033,56 @@ impl GeneratorInstance {
                     self.add_instr(mov);
                 }
 
-                // Push stack args on
+                // RSP has to be 16-byte aligned at the call, so pad below
+                // anything already pushed if the stack args won't line it up
                 let num_args = expr.args.len();
+                let stack_bytes = num_args.saturating_sub(6) * 8;
+                let padding = (self.pushed + stack_bytes) % 16;
+
+                if padding > 0 {
+                    self.add_instr(Instr::Sub("RSP".to_string(), padding.to_string()));
+                    self.pushed += padding;
+                }
+
+                // Push stack args on
                 if num_args > 6 {
                     for i in (6..num_args).rev() { // Go backwards per ABI
-                        let mut arg_scratch = self.gen_expr(&expr.args[i])?;
+                        let mut arg_scratch = self.gen_promoted_arg(&expr.args[i], i, &f_type)?;
 
                         // Stack pushes need to be QWords (idk why)
                         arg_scratch.reg.size = RegisterSize::QWord;
 
                         self.add_instr(Instr::Push(arg_scratch.reg.to_string()));
+                        self.pushed += 8;
                     }
                 }
 
-                self.add_instr(Instr::Call(var.asm_rep));
+                // Variadic functions need AL to say how many vector
+                // registers have args in them, which is always none for us.
+                // Without a prototype, the function might be variadic
+                if f_type.variadic || !f_type.prototyped {
+                    self.add_instr(Instr::Mov("EAX".to_string(), "0".to_string()));
+                }
+
+                self.add_instr(Instr::Call(target));
 
                 // Pop off stack args
-                if num_args > 6 {
-                    let bytes_to_pop = (num_args - 6) * 8;
+                let bytes_to_pop = stack_bytes + padding;
+                if bytes_to_pop > 0 {
                     let instr = Instr::Add(
                         "RSP".to_string(),
                         bytes_to_pop.to_string());
                     self.add_instr(instr);
+                    self.pushed -= bytes_to_pop;
                 }
 
                 // Pop off saved reg args
                 for instr in pop_instrs {
                     self.add_instr(instr);
+                    self.pushed -= 8;
                 }
 
-                let ret = self.alloc_scratch(get_size(&ret_type))?;
+                let ret = self.alloc_scratch(get_size(&ret_type, self))?;
                 if !is_void {
                     let rax = SizedRegister { reg: Register::Rax, size: ret.reg.size };
                     let mov_ret = Instr::Mov(ret.reg.to_string(), rax.to_string());
@@ -488,9 +1092,38 @@ impl GeneratorInstance {
                 Ok(ret)
             },
 
-            Expression::Identifier(id) => {
-                let var = self.get_symbol(&id).expect("Undefined");
-                let scratch = self.alloc_scratch(get_size(&var.type_of))?;
+            ExpressionKind::VaStart(x) => self.gen_va_start(x),
+            ExpressionKind::VaArg(x) => self.gen_va_arg(x),
+            ExpressionKind::VaCopy(x) => self.gen_va_copy(x),
+
+            // Nothing to clean up, since `va_start` doesn't allocate anything
+            ExpressionKind::VaEnd(x) => {
+                self.gen_expr(&x.expr)?;
+                self.alloc_scratch(RegisterSize::Void)
+            },
+
+            ExpressionKind::CompoundLiteral(x) => {
+                let type_of = self.get_object_type(expr);
+                let addr = self.gen_compound_literal(x)?;
+
+                self.gen_load(addr, &type_of)
+            },
+
+            // The controlling expression is only there for its type
+            ExpressionKind::Generic(_) => self.gen_expr(expr.selected(self).expect("Unresolved _Generic")),
+
+            ExpressionKind::StatementExpr(x) => self.gen_statement_expr(x),
+
+            ExpressionKind::Identifier(id) => {
+                let var = self.get_symbol(id).expect("Undefined");
+
+                // Struct and array values are kept as their address, and
+                // functions decay to theirs
+                if let Type::Struct(_) | Type::Array(_) | Type::Function(_) = var.type_of.unqualified() {
+                    return self.gen_address(expr);
+                }
+
+                let scratch = self.alloc_scratch(get_size(&var.type_of, self))?;
 
                 let instr = Instr::Mov(
                     scratch.reg.to_string(),
@@ -500,7 +1133,7 @@ impl GeneratorInstance {
                 Ok(scratch)
             }
 
-            Expression::IntLiteral(x) => {
+            ExpressionKind::IntLiteral(x) => {
                 // TODO: Use correct size
                 let scratch = self.alloc_scratch(RegisterSize::DWord)?;
                 let instr = Instr::Mov(scratch.reg.to_string(), x.to_string());
@@ -508,15 +1141,297 @@ impl GeneratorInstance {
                 Ok(scratch)
             }
 
-            Expression::CharLiteral(x) => {
-                let scratch = self.alloc_scratch(RegisterSize::Byte)?;
-                let instr = Instr::Mov(scratch.reg.to_string(), x.to_string());
+            ExpressionKind::CharLiteral(x) => {
+                let size = get_size(&x.type_of(self.target()), self);
+                let scratch = self.alloc_scratch(size)?;
+                let instr = Instr::Mov(scratch.reg.to_string(), x.eval(self.target()).to_string());
                 self.add_instr(instr);
                 Ok(scratch)
             }
 
-            // TODO String literals
-            Expression::StringLiteral(_) => todo!()
+            // Arrays are kept as their address
+            ExpressionKind::StringLiteral(_) => self.gen_address(expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{Context, Target}, codegen::{x86_64::{tests::find_lines, X86_64Generator}, Generator}, error::CompilerError, fs::read_file, parser::parse};
+
+    fn read(ending: &str) -> Result<String, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/expression/");
+        p.push(ending);
+        read_file(&p)
+    }
+
+    fn generate(ending: &str) -> Result<String, CompilerError> {
+        let parsed = parse(&read(ending)?)?;
+        parsed.verify(&mut Context::new())?;
+
+        Ok(X86_64Generator::new(Target::default()).generate(&parsed)?)
+    }
+
+    #[test]
+    fn compound_assignment() -> Result<(), CompilerError> {
+        let asm = generate("compound.c")?;
+
+        // Adds and ors work on memory directly, the rest go via a register
+        assert!(asm.contains("\tadd DWORD [RBP - 4], "));
+        assert!(asm.contains("\tsub DWORD [total], "));
+        assert!(asm.contains("\tor DWORD [total], "));
+        assert!(asm.contains("\tshl "));
+        assert!(asm.contains("\tsar "));
+        assert!(asm.contains("\tidiv "));
+        Ok(())
+    }
+
+    #[test]
+    fn compound_assignment_conversions() -> Result<(), CompilerError> {
+        let asm = generate("compound_conversions.c")?;
+
+        // The int gets widened to go in the long
+        assert!(asm.contains("\tmovsxd "));
+        assert!(asm.contains("\tadd QWORD [l], "));
+
+        // The char gets multiplied as an int, then cut back down
+        assert!(asm.contains("\tmovzx "));
+        assert!(asm.contains("\tmov BYTE [c], "));
+
+        // Unsigned division zeros the top half of the dividend
+        assert!(asm.contains("\txor EDX, EDX\n\tdiv "));
+
+        // Pointers move by whole ints
+        assert!(asm.contains(", 4\n\tadd QWORD [p], "));
+        assert!(asm.contains(", 4\n\tsub QWORD [p], "));
+
+        assert_eq!(verify_error("compound_pointer.c")?,
+            "Can only add or subtract an integer from a pointer");
+        Ok(())
+    }
+
+    #[test]
+    fn volatile_accesses() -> Result<(), CompilerError> {
+        let asm = generate("volatile.c")?;
+
+        // Every read and write of a volatile object happens, even when they
+        // look redundant
+        assert_eq!(asm.matches(", DWORD [flag]\n").count(), 2);
+        assert_eq!(asm.matches("\tmov DWORD [flag], ").count(), 2);
+        Ok(())
+    }
+
+    fn verify_error(ending: &str) -> Result<&'static str, CompilerError> {
+        match parse(&read(ending)?)?.verify(&mut Context::new()) {
+            Err(e) => match e.root() {
+                CompilerError::SemanticError(message) => Ok(message),
+                _ => Err(e),
+            },
+            Ok(_) => panic!("Verified when we shouldn't have"),
+        }
+    }
+
+    #[test]
+    fn generic() -> Result<(), CompilerError> {
+        let asm = generate("generic.c")?;
+
+        // Qualifiers are dropped and arrays decay before picking, and typedefs
+        // are just another name for the type
+        assert!(asm.contains(", [f_int]\n"));
+        assert!(asm.contains(", [f_long]\n"));
+        assert!(asm.contains(", [f_gen]\n"));
+
+        // The selection can be an lvalue, a case label, or an array for sizeof
+        assert!(asm.contains(", 4\n\tmov DWORD ["));
+        assert!(asm.contains(", 2\n\tje "));
+        assert!(asm.contains(", 16\n\tmov EAX, "));
+        Ok(())
+    }
+
+    #[test]
+    fn generic_promotion() -> Result<(), CompilerError> {
+        let asm = generate("generic_promotion.c")?;
+
+        // Character constants, and arithmetic on chars, are ints
+        for (value, offset) in [(1, 8), (3, 12), (5, 16)] {
+            let store = format!("\tmov DWORD [RBP - {}], ", offset);
+            let reg = asm[asm.find(&store).unwrap() + store.len()..].lines().next().unwrap();
+            assert!(asm.contains(&format!("\tmov {reg}, {value}\n{store}{reg}\n")));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn generic_constants() -> Result<(), CompilerError> {
+        let asm = generate("generic_constant.c")?;
+
+        // Array lengths and enumerators pick their association without
+        // anything having to verify it first, and char plus long is a long
+        assert!(asm.contains("lengths: resb 12\n"));
+        assert!(asm.contains("\tmov RBP, RSP\n\tsub RSP, 16\n"));
+        find_lines(&asm, &["mov %0, 5", "mov DWORD [%1], %0"]);
+        find_lines(&asm, &["mov %0, 7", "mov DWORD [%1], %0"]);
+        Ok(())
+    }
+
+    #[test]
+    fn generic_errors() -> Result<(), CompilerError> {
+        assert_eq!(verify_error("generic_no_match.c")?,
+            "No _Generic association matches the controlling expression's type");
+        assert_eq!(verify_error("generic_duplicate.c")?,
+            "_Generic has more than one association for the same type");
+        assert_eq!(verify_error("generic_defaults.c")?,
+            "_Generic can only have one default");
+        Ok(())
+    }
+
+    #[test]
+    fn compound_literals() -> Result<(), CompilerError> {
+        let asm = generate("compound_literal.c")?;
+
+        // Each gets zeroed before the listed fields are stored, so `.y = 5`
+        // leaves x as 0
+        assert!(asm.contains(" + 0], "));
+        assert!(asm.contains(", 5\n\tmov DWORD ["));
+        assert!(asm.contains(", 7\n"));
+
+        // An array's length comes from its list
+        assert!(asm.contains(", 16\n\tadd "));
+
+        // Its slot comes from the frame, so nothing moves RSP apart from
+        // lining it back up after saving k
+        let pushed = &asm[asm.find("push RDI").expect("k needs saving")..];
+        let call = pushed.find("call sum").unwrap();
+        assert_eq!(pushed[..call].matches("RSP").count(), 1);
+        assert!(pushed.contains("\tsub RSP, 8\n\tcall sum\n\tadd RSP, 8\n\tpop RDI\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn compound_literal_in_loop() -> Result<(), CompilerError> {
+        let asm = generate("compound_literal_loop.c")?;
+
+        // The literal's slot is part of the frame, so going round the loop
+        // doesn't grow the stack
+        assert!(asm.contains("\tmov RBP, RSP\n\tsub RSP, 32\n"));
+        assert_eq!(asm.matches("sub RSP").count(), 1);
+        find_lines(&asm, &["lea %0, [RBP - 28]"]);
+        Ok(())
+    }
+
+    #[test]
+    fn call_alignment() -> Result<(), CompilerError> {
+        let asm = generate("call_alignment.c")?;
+
+        // One stack arg needs padding to keep RSP 16-byte aligned at the
+        // call, but two line it up on their own
+        find_lines(&asm, &["sub RSP, 8", "mov %0, 7", "push %1", "call seven", "add RSP, 16"]);
+        find_lines(&asm, &["push %0", "call eight", "add RSP, 16"]);
+        assert_eq!(asm.matches("sub RSP").count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn statement_exprs() -> Result<(), CompilerError> {
+        let asm = generate("statement_expr.c")?;
+
+        // Names declared inside only live as long as the expression
+        assert_eq!(asm.matches("\tmov DWORD [RBP - 20], ").count(), 2);
+        assert_eq!(asm.matches("call twice").count(), 2);
+
+        assert_eq!(verify_error("statement_expr_global.c")?,
+            "Statement expressions are only allowed in functions");
+        assert_eq!(verify_error("statement_expr_void.c")?,
+            "Declaration type must match");
+
+        match parse(&read("statement_expr_label.c")?)?.verify(&mut Context::new()) {
+            Err(e) => assert!(matches!(e.root(), CompilerError::NotSupported(_))),
+            Ok(_) => panic!("Verified when we shouldn't have"),
         }
+
+        Ok(())
+    }
+
+    #[test]
+    fn bitfields() -> Result<(), CompilerError> {
+        let asm = generate("bitfield.c")?;
+
+        // Static fields get merged byte by byte
+        assert!(asm.contains("defaults: db 219, 1\n\ttimes 6 db 0\n\tdb 7, 0, 0, 0, 0\n\ttimes 3 db 0\n"));
+
+        // Reads shift the field up to the top and back down, extending it
+        find_lines(&asm, &["movzx %0, WORD [%1 + 0]", "shl %0, 23", "sar %0, 27", "mov EAX, %0"]);
+
+        // Writes clear the field's bits and or the value in, only touching
+        // the bytes it spans
+        find_lines(&asm, &["shl %0, 29", "shr %0, 28", "movzx %1, BYTE [%2 + 0]",
+            "and %1, 241", "or %1, %0", "mov BYTE [%2 + 0], %3"]);
+        find_lines(&asm, &["mov %0, DWORD [%1 + 0]", "movzx %2, BYTE [%1 + 4]",
+            "shl %3, 32", "or %4, %3", "and %4, 0", "or %4, %5",
+            "mov DWORD [%1 + 0], %0", "shr %4, 32", "mov BYTE [%1 + 4], %6"]);
+
+        assert_eq!(verify_error("bitfield_address.c")?,
+            "Can't take the address of a bit-field");
+        assert_eq!(verify_error("bitfield_sizeof.c")?,
+            "Can't take the size of a bit-field");
+        Ok(())
+    }
+
+    #[test]
+    fn bitfield_bytes() -> Result<(), CompilerError> {
+        let asm = generate("bitfield_bytes.c")?;
+
+        // Bit-fields of different sizes sharing a byte both end up in it
+        assert!(asm.contains("shared: db 33\n\ttimes 1 db 0\n"));
+        assert!(asm.contains("header: db 84, 188, 154\n"));
+
+        // The packed `flags` is read and written as the one byte it's in,
+        // rather than running off the end of the struct
+        find_lines(&asm, &["add %0, 2", "movzx %1, BYTE [%0 + 0]", "shl %2, 8", "shr %2, 12"]);
+        find_lines(&asm, &["movzx %0, BYTE [%1 + 0]", "and %2, 15", "or %2, %3",
+            "mov BYTE [%1 + 0], %4"]);
+
+        // `b` spans three bytes, so `c` after it never gets written
+        find_lines(&asm, &["movzx %0, WORD [%1 + 0]", "movzx %2, BYTE [%1 + 2]",
+            "shl %2, 16", "or %0, %2", "and %0, 15", "or %0, %3",
+            "mov WORD [%1 + 0], %4", "shr %0, 16", "mov BYTE [%1 + 2], %5"]);
+        assert!(!asm.contains(" + 3]") && !asm.contains("DWORD ["));
+
+        Ok(())
+    }
+
+    #[test]
+    fn literals() -> Result<(), CompilerError> {
+        let asm = generate("literals.c")?;
+
+        // Each prefix encodes into its own code units, with plain pieces
+        // taking on the prefix of the literal they're joined to
+        assert!(asm.contains("\nplain.0: db 104, 195, 169, 108, 108, 111, 10, 0\n"));
+        assert!(asm.contains("\nutf8.1: db 195, 169, 120, 0\n"));
+        assert!(asm.contains("\nutf16.2: dw 97, 55357, 56832, 0\n"));
+        assert!(asm.contains("\nutf32.3: dd 233, 0\n"));
+        assert!(asm.contains("\nwide.4: dd 119, 105, 100, 101, 0\n"));
+
+        // Arrays of wchar_t, which is 32 bits wide on Linux, are filled in
+        // straight from the literal
+        assert!(asm.contains("\nwarray: dd 104, 233, 0\n"));
+
+        // Converting to char wraps, and prefixed constants keep their value
+        assert!(asm.contains("\nc: db -1\n"));
+        assert!(asm.contains("\nwc: dd 233\n"));
+        find_lines(&asm, &["mov %0, 8364", "mov WORD [RBP - 10], %0"]);
+
+        // Plain character constants are ints, and prefixed ones their code
+        // unit's type
+        for (function, size) in [("size", 12), ("char_size", 4), ("utf16_char_size", 2)] {
+            let start = format!("{}:", function);
+            let mov = format!("mov %0, {}", size);
+            find_lines(&asm, &[&start, "push RBP", "mov RBP, RSP", &mov, "mov RAX, %0"]);
+        }
+        Ok(())
     }
 }
+
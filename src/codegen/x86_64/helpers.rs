@@ -1,26 +1,28 @@
-use crate::ast::Type;
+use crate::ast::{layout::Layouts, Type};
 
 use super::registers::RegisterSize;
 
-pub fn get_bytes(t: &Type) -> usize {
-    match t {
+pub fn get_bytes(t: &Type, layouts: &impl Layouts) -> usize {
+    match t.unqualified() {
         Type::Void => 0,
 
-        Type::Uint8  | Type::Int8  => 1,
-        Type::Uint16 | Type::Int16 => 2,
-        Type::Uint32 | Type::Int32 => 4,
-        Type::Uint64 | Type::Int64 => 8,
-
         Type::Float | Type::Double => todo!(),
 
-        Type::Pointer(_) => 8,
-
         Type::Function(_) => panic!("Shouldn't be sizing fn"),
+
+        _ => t.size_of(layouts).expect("Sizing incomplete type"),
     }
 }
 
-pub fn get_size(t: &Type) -> RegisterSize {
-    match get_bytes(t) {
+/// Gets the size of register needed to hold a value of the given type. Structs
+/// and arrays don't fit in registers, so those get passed around by address
+/// instead
+pub fn get_size(t: &Type, layouts: &impl Layouts) -> RegisterSize {
+    if matches!(t.unqualified(), Type::Struct(_) | Type::Array(_)) {
+        return RegisterSize::QWord;
+    }
+
+    match get_bytes(t, layouts) {
         0 => RegisterSize::Void,
         1 => RegisterSize::Byte,
         2 => RegisterSize::Word,
@@ -30,15 +32,34 @@ pub fn get_size(t: &Type) -> RegisterSize {
     }
 }
 
-pub fn get_global_asm(symbol: &str, type_of: &Type) -> String {
-    let is_fn = match type_of {
-        Type::Function(_) => true,
-        _ => false,
-    };
+/// Gets the asm for a variable at the given memory location, e.g. `[RBP - 4]`
+pub fn get_memory_asm(location: &str, type_of: &Type, layouts: &impl Layouts)
+    -> String {
+
+    match type_of.unqualified() {
+        // Structs and arrays can't be moved in one go, so they don't get a size
+        Type::Struct(_) | Type::Array(_) => location.to_string(),
+        _ => format!("{} {}", get_size(type_of, layouts), location),
+    }
+}
+
+pub fn get_global_asm(symbol: &str, type_of: &Type, layouts: &impl Layouts)
+    -> String {
+
+    let is_fn = matches!(type_of, Type::Function(_));
 
     if is_fn {
         symbol.to_string()
     } else {
-        format!("{} [{}]", get_size(type_of), symbol)
+        get_memory_asm(&format!("[{}]", symbol), type_of, layouts)
+    }
+}
+
+/// Gets just the memory location (e.g. `[RBP - 4]`) out of a variable's asm,
+/// so that we can take its address
+pub fn get_memory_operand(asm_rep: &str) -> &str {
+    match asm_rep.find('[') {
+        Some(i) => &asm_rep[i..],
+        None => panic!("Can't get the address of a register"),
     }
 }
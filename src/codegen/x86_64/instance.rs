@@ -1,10 +1,18 @@
-use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet}, rc::Rc};
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet, VecDeque}, rc::Rc};
 
 use log::trace;
 
-use crate::{ast::Type, codegen::{error::CodegenError, x86_64::helpers::get_global_asm}};
+use crate::{ast::{attributes::{Attributes, Visibility}, layout::{Constants, Layouts, TagEntry}, Target, Type}, codegen::{error::CodegenError, x86_64::helpers::get_global_asm}, error::CompilerError};
 
-use super::{helpers::{get_bytes, get_size}, instructions::Instr, registers::{Register, RegisterSize, SizedRegister, ARG_REGS, NUM_REGS}};
+use super::{helpers::{get_bytes, get_memory_asm}, variadic::SaveArea, instructions::Instr, registers::{Register, RegisterSize, SizedRegister, ARG_REGS, NUM_REGS}};
+
+/// Flags for sections of our own, from `__attribute__((section))`. They're
+/// aligned enough for anything, so they don't depend on what's in them
+const CODE_FLAGS: &str = "progbits alloc exec nowrite align=16";
+const DATA_FLAGS: &str = "progbits alloc noexec write align=16";
+
+/// Flags for the sections of functions to run before and after `main`
+const INIT_FLAGS: &str = "progbits alloc noexec write align=8";
 
 #[derive(Debug, Clone)]
 pub struct ScopeVariable {
@@ -12,16 +20,31 @@ pub struct ScopeVariable {
     pub type_of: Type,
 }
 
+#[derive(Debug, Default)]
+pub struct Scope {
+    /// Maps symbol name to asm code for it
+    symbols: HashMap<String, ScopeVariable>,
+
+    /// Values of the enum constants in `symbols`
+    constants: HashMap<String, i64>,
+
+    /// Maps struct/union tag to what it refers to
+    tags: HashMap<String, TagEntry>,
+}
+
 pub struct GeneratorInstance {
+    /// What we're generating code for
+    target: Target,
+
     /// Tracks which registers are in used as scratch
     scratches: Rc<RefCell<HashMap<Register, bool>>>,
 
     /// Used for coming up with .L# labels
     label_counter: u64,
 
-    /// Stack of scopes, maps symbol name to asm code for it. 0 is global, 1 is
-    /// function scope, 2 is some scope inside that, etc
-    scopes: Rc<RefCell<Vec<HashMap<String, ScopeVariable>>>>,
+    /// Stack of scopes. 0 is global, 1 is function scope, 2 is some scope
+    /// inside that, etc
+    scopes: Rc<RefCell<Vec<Scope>>>,
 
     /// Tracks which argument registers are in use by the current fn
     pub arg_regs: HashSet<Register>,
@@ -29,15 +52,63 @@ pub struct GeneratorInstance {
     /// The label to jump to to return, if we're in a fn
     pub return_label: Option<u64>,
 
+    /// What the current fn returns, so the value can be converted to it
+    pub return_type: Type,
+
+    /// Labels to jump to on a `break`, innermost loop/switch last
+    pub break_labels: Vec<u64>,
+
+    /// Labels for the switches we're currently inside, innermost last
+    pub switches: Vec<SwitchLabels>,
+
+    /// Maps the current fn's labels (for goto) to their .L# labels
+    pub user_labels: HashMap<String, u64>,
+
+    /// Where the current fn saved its register args, if it's variadic
+    pub save_area: Option<SaveArea>,
+
     /// How many bytes below RDP we've allocated to local variables
     rdp_offset: Rc<Cell<usize>>,
 
-    /// External symbols we'll link to later
+    /// The most bytes below RBP the current fn's locals take up at once, which
+    /// its prologue reserves
+    frame_size: usize,
+
+    /// Where in `instructions` the current fn's prologue reserves its frame
+    frame_start: usize,
+
+    /// Bytes pushed below the frame by calls we're partway through setting up
+    pub pushed: usize,
+
+    /// External symbols we'll link to later, unless they get defined here
     externs: Vec<String>,
 
     /// Global symbols that are linkable by others
     globals: Vec<String>,
 
+    /// Global symbols defined in this file, exported or not
+    defined: HashSet<String>,
+
+    /// Global symbols declared `static`, which aren't exported
+    internal: HashSet<String>,
+
+    /// Globals declared without an initializer. They're only defined (as
+    /// zeroes) at the end, if nothing else defines them first. Each has its
+    /// type and alignment
+    tentatives: Vec<(String, Type, usize)>,
+
+    /// What each global symbol's attributes ask for, from all its declarations
+    attributes: HashMap<String, Attributes>,
+
+    /// Sections of our own we've switched to already, and so given flags
+    sections: HashSet<String>,
+
+    /// Constructors to run before `main`, in the order they were defined
+    init_array: Vec<String>,
+
+    /// Destructors to run after `main` returns
+    fini_array: Vec<String>,
+
     /// Contents of the data section
     data: String,
 
@@ -49,7 +120,7 @@ pub struct GeneratorInstance {
 }
 
 impl GeneratorInstance {
-    pub fn new() -> GeneratorInstance {
+    pub fn new(target: Target) -> GeneratorInstance {
         let mut scratches = HashMap::new();
         
         for i in 0..NUM_REGS {
@@ -67,21 +138,37 @@ impl GeneratorInstance {
         }
 
         GeneratorInstance {
+            target,
             scratches: Rc::new(RefCell::new(scratches)),
             label_counter: 0,
-            scopes: Rc::new(RefCell::new(vec![HashMap::new()])),
+            scopes: Rc::new(RefCell::new(vec![Scope::default()])),
             arg_regs: HashSet::new(),
             return_label: None,
+            return_type: Type::Void,
+            break_labels: vec![],
+            switches: vec![],
+            user_labels: HashMap::new(),
+            save_area: None,
             rdp_offset: Rc::new(Cell::new(0)),
+            frame_size: 0,
+            frame_start: 0,
+            pushed: 0,
             externs: vec![],
             globals: vec![],
+            defined: HashSet::new(),
+            internal: HashSet::new(),
+            tentatives: vec![],
+            attributes: HashMap::new(),
+            sections: HashSet::new(),
+            init_array: vec![],
+            fini_array: vec![],
             data: String::new(),
             bss: String::new(),
             instructions: String::new(),
         }
     }
 
-    pub fn alloc_scratch<'a>(&'a self, size: RegisterSize) -> 
+    pub fn alloc_scratch(&self, size: RegisterSize) -> 
         Result<Scratch, CodegenError> {
 
         let mut scratches = self.scratches.borrow_mut();
@@ -99,6 +186,23 @@ impl GeneratorInstance {
         })
     }
 
+    /// Takes a particular register, if it's one we use as scratch and it's free
+    pub fn claim_scratch(&self, reg: Register, size: RegisterSize) -> Option<Scratch> {
+        let mut scratches = self.scratches.borrow_mut();
+
+        match scratches.get(&reg) {
+            Some(false) => {
+                scratches.insert(reg, true);
+
+                Some(Scratch {
+                    reg: SizedRegister { reg, size },
+                    scratches: self.scratches.clone(),
+                })
+            },
+            _ => None,
+        }
+    }
+
     pub fn new_label(&mut self) -> u64 {
         let id = self.label_counter;
         self.label_counter += 1;
@@ -109,13 +213,25 @@ impl GeneratorInstance {
         self.instructions.push_str(&format!(".L{}:", id));
     }
 
+    /// Adds a table of 32-bit offsets from the table's own label to each of the
+    /// given labels, for use as a jump table
+    pub fn add_jump_table(&mut self, id: u64, targets: &[u64]) {
+        self.add_label(id);
+        self.instructions.push('\n');
+
+        for target in targets {
+            self.instructions.push_str(
+                &format!("\tdd .L{} - .L{}\n", target, id));
+        }
+    }
+
     pub fn add_fn_label(&mut self, label: String) {
         self.instructions.push_str(&format!("{}:\n", label));
     }
 
     pub fn get_symbol(&self, symbol: &str) -> Option<ScopeVariable> {
         for scope in self.scopes.borrow().iter().rev() {
-            match scope.get(symbol) {
+            match scope.symbols.get(symbol) {
                 Some(s) => return Some(s.clone()),
                 None => continue,
             }
@@ -130,18 +246,16 @@ impl GeneratorInstance {
         ScopeOwner::new(self)
     }
 
-    pub fn add_local(&mut self, symbol: String, type_of: Type) -> String {
+    pub fn add_local(&mut self, symbol: String, type_of: Type, align: usize) -> String {
         if self.global_scope() {
             panic!("Can't add local if you're global!!");
         }
 
-        let size = get_bytes(&type_of);
-        let new_offset = self.rdp_offset.get() + size;
-
-        self.rdp_offset.set(new_offset);
-        self.add_instr(Instr::Sub("RSP".to_string(), size.to_string()));
+        let size = get_bytes(&type_of, self);
+        let new_offset = self.alloc_stack(size, align);
 
-        let asm_rep = format!("{} [RBP - {}]", get_size(&type_of), new_offset);
+        let location = format!("[RBP - {}]", new_offset);
+        let asm_rep = get_memory_asm(&location, &type_of, self);
 
         self.add_symbol_with_asm(symbol, ScopeVariable { 
             asm_rep: asm_rep.clone(), type_of, });
@@ -149,54 +263,215 @@ impl GeneratorInstance {
         asm_rep
     }
 
+    /// Makes room in the current fn's frame, returning how far below RBP it
+    /// starts. Nothing's emitted, since the prologue reserves the whole frame
+    /// at once
+    pub fn alloc_stack(&mut self, size: usize, align: usize) -> usize {
+        // Every call is made with RSP 16-byte aligned, so RBP is too, and
+        // aligning the offset aligns the variable (locals can't ask for more)
+        let old_offset = self.rdp_offset.get();
+        let new_offset = (old_offset + size).div_ceil(align) * align;
+
+        self.rdp_offset.set(new_offset);
+        self.frame_size = self.frame_size.max(new_offset);
+
+        new_offset
+    }
+
+    /// Marks where the current fn's frame gets reserved, once we know how big
+    /// it is. Goes straight after RBP's set up
+    pub fn begin_frame(&mut self) {
+        self.frame_start = self.instructions.len();
+        self.frame_size = 0;
+    }
+
+    /// Reserves the frame for the fn we've just generated, keeping RSP 16-byte
+    /// aligned
+    pub fn reserve_frame(&mut self) {
+        let size = self.frame_size.div_ceil(16) * 16;
+
+        if size > 0 {
+            let instr = Instr::Sub("RSP".to_string(), size.to_string());
+            self.instructions.insert_str(self.frame_start, &format!("\t{}\n", instr));
+        }
+    }
+
     pub fn add_global(&mut self, symbol: String, type_of: Type) {
-        let asm_rep = get_global_asm(&symbol, &type_of);
+        let asm_rep = get_global_asm(&symbol, &type_of, self);
         self.add_symbol_with_asm(symbol, ScopeVariable { asm_rep, type_of });
     }
 
     pub fn add_symbol_with_asm(&mut self, symbol: String, var: ScopeVariable) {
-        if self.global_scope() {
-            self.globals.push(symbol.clone());
-        }
-
         trace!("Adding symbol {} as {:?}", symbol, var);
 
         // unwrap is allowed cause we should always have at least 1
-        self.scopes.borrow_mut().last_mut().unwrap().insert(symbol, var);
+        self.scopes.borrow_mut().last_mut().unwrap().symbols.insert(symbol, var);
     }
 
     pub fn add_extern(&mut self, symbol: String, type_of: Type) {
-        assert!(self.global_scope());
+        if !self.externs.contains(&symbol) {
+            self.externs.push(symbol.clone());
+        }
+
+        self.add_global(symbol, type_of);
+    }
 
-        self.externs.push(symbol.clone());
+    /// Marks a global symbol as having internal linkage
+    pub fn set_internal(&mut self, symbol: String) {
+        self.internal.insert(symbol);
+    }
+
+    /// Marks a global symbol as defined in this file, exporting it unless it
+    /// has internal linkage
+    pub fn define_global(&mut self, symbol: &str) {
+        self.defined.insert(symbol.to_string());
 
-        let asm_rep = get_global_asm(&symbol, &type_of);
-        self.scopes.borrow_mut().last_mut().unwrap().insert(symbol, 
-            ScopeVariable { asm_rep, type_of, });
+        if !self.internal.contains(symbol) {
+            self.globals.push(symbol.to_string());
+        }
+    }
+
+    /// Defines a function, which its attributes can ask to run before or
+    /// after `main`
+    pub fn define_function(&mut self, symbol: &str) {
+        self.define_global(symbol);
+
+        let attributes = self.attributes.get(symbol).cloned().unwrap_or_default();
+
+        if attributes.constructor {
+            self.init_array.push(symbol.to_string());
+        }
+
+        if attributes.destructor {
+            self.fini_array.push(symbol.to_string());
+        }
+    }
+
+    /// Adds what a declaration's attributes ask for to those of the symbol's
+    /// earlier declarations
+    pub fn add_attributes(&mut self, symbol: &str, attributes: &Attributes) {
+        self.attributes.entry(symbol.to_string())
+            .or_default()
+            .merge(attributes);
+    }
+
+    fn get_section(&self, symbol: &str) -> Option<String> {
+        self.attributes.get(symbol).and_then(|a| a.section.clone())
+    }
+
+    /// Switches to one of our own sections, giving it its flags the first time
+    fn section_directive(&mut self, section: &str, flags: &str) -> String {
+        if self.sections.insert(section.to_string()) {
+            format!("SECTION {} {}\n", section, flags)
+        } else {
+            format!("SECTION {}\n", section)
+        }
+    }
+
+    /// Puts the function's code in its own section, if it asked to be
+    pub fn enter_code_section(&mut self, symbol: &str) {
+        if let Some(section) = self.get_section(symbol) {
+            let directive = self.section_directive(&section, CODE_FLAGS);
+            self.instructions.push_str(&directive);
+        }
+    }
+
+    /// Goes back to the text section after `enter_code_section`
+    pub fn leave_code_section(&mut self, symbol: &str) {
+        if self.get_section(symbol).is_some() {
+            self.instructions.push_str("SECTION .text\n");
+        }
+    }
+
+    /// Adds lines to the data section, or to the symbol's own section if it
+    /// asked for one
+    fn add_sectioned(&mut self, symbol: &str, lines: &str) {
+        match self.get_section(symbol) {
+            Some(section) => {
+                let directive = self.section_directive(&section, DATA_FLAGS);
+                self.data.push_str(&directive);
+                self.data.push_str(lines);
+                self.data.push_str("SECTION .data\n");
+            },
+            None => self.data.push_str(lines),
+        }
+    }
+
+    /// The ELF extras for a symbol's GLOBAL or EXTERN directive, e.g.
+    /// `:weak hidden`
+    fn get_specials(&self, symbol: &str) -> String {
+        let attributes = match self.attributes.get(symbol) {
+            Some(a) => a,
+            None => return String::new(),
+        };
+
+        let mut specials = vec![];
+
+        if attributes.weak {
+            specials.push("weak");
+        }
+
+        if let Some(visibility) = attributes.visibility {
+            specials.push(match visibility {
+                Visibility::Default => "default",
+                Visibility::Hidden => "hidden",
+                Visibility::Protected => "protected",
+                Visibility::Internal => "internal",
+            });
+        }
+
+        match specials.is_empty() {
+            true => String::new(),
+            false => format!(":{}", specials.join(" ")),
+        }
+    }
+
+    pub fn add_tentative(&mut self, symbol: String, type_of: Type, align: usize) {
+        self.tentatives.push((symbol, type_of, align));
+    }
+
+    /// Defines whichever tentatively defined globals never got a real
+    /// definition
+    pub fn define_tentatives(&mut self) {
+        for (symbol, type_of, align) in std::mem::take(&mut self.tentatives) {
+            if !self.defined.contains(&symbol) {
+                self.define_global(&symbol);
+                self.add_bss(symbol, &type_of, align);
+            }
+        }
     }
     
     pub fn add_instr(&mut self, instr: Instr) {
         self.instructions.push_str(&format!("\t{}\n", instr));
     }
 
-    pub fn add_bss(&mut self, symbol: String, type_of: &Type) {
-        let size = get_bytes(type_of);
-        self.bss.push_str(&format!("{}: resb {}\n", symbol, size));
+    pub fn add_bss(&mut self, symbol: String, type_of: &Type, align: usize) {
+        let size = get_bytes(type_of, self);
+
+        // Sections of our own aren't BSS, so they need the zeroes spelled out
+        if self.get_section(&symbol).is_some() {
+            self.add_sectioned(&symbol,
+                &format!("align {}, db 0\n{}: times {} db 0\n", align, symbol, size));
+        } else {
+            self.bss.push_str(&format!("alignb {}\n", align));
+            self.bss.push_str(&format!("{}: resb {}\n", symbol, size));
+        }
     }
 
-    pub fn add_data(&mut self, symbol: String, asm: String) {
-        self.data.push_str(&format!("{}: {}\n", symbol, asm));
+    pub fn add_data_aligned(&mut self, symbol: String, asm: String, align: usize) {
+        self.add_sectioned(&symbol,
+            &format!("align {}, db 0\n{}: {}\n", align, symbol, asm));
     }
 
     pub fn get_instructions(&self) -> String {
         let mut asm = String::from("BITS 64\nDEFAULT REL\n\n");
 
-        for e in &self.externs {
-            asm.push_str(&format!("EXTERN {}\n", e));
+        for e in self.externs.iter().filter(|e| !self.defined.contains(*e)) {
+            asm.push_str(&format!("EXTERN {}{}\n", e, self.get_specials(e)));
         }
 
         for g in &self.globals {
-            asm.push_str(&format!("GLOBAL {}\n", g));
+            asm.push_str(&format!("GLOBAL {}{}\n", g, self.get_specials(g)));
         }
 
         asm.push_str("\nSECTION .data\n");
@@ -208,10 +483,79 @@ impl GeneratorInstance {
         asm.push_str("\nSECTION .text\n");
         asm.push_str(&self.instructions);
 
+        // The C runtime calls whatever these point to
+        for (section, functions) in [(".init_array", &self.init_array), (".fini_array", &self.fini_array)] {
+            if !functions.is_empty() {
+                asm.push_str(&format!("\nSECTION {} {}\n", section, INIT_FLAGS));
+            }
+
+            for f in functions {
+                asm.push_str(&format!("\tdq {}\n", f));
+            }
+        }
+
         asm
     }
 }
 
+impl Constants for GeneratorInstance {
+    fn lookup_constant(&self, name: &str) -> Option<i64> {
+        self.scopes.borrow().iter().rev()
+            .find(|scope| scope.symbols.contains_key(name))
+            .and_then(|scope| scope.constants.get(name).copied())
+    }
+
+    fn lookup_type(&self, name: &str) -> Option<Type> {
+        self.get_symbol(name).map(|var| var.type_of)
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+}
+
+impl Layouts for GeneratorInstance {
+    fn lookup_tag(&self, tag: &str) -> Option<TagEntry> {
+        self.scopes.borrow().iter().rev()
+            .find_map(|scope| scope.tags.get(tag).cloned())
+    }
+
+    fn lookup_tag_in_scope(&self, tag: &str) -> Option<TagEntry> {
+        self.scopes.borrow().last().unwrap().tags.get(tag).cloned()
+    }
+
+    fn insert_tag(&mut self, tag: String, entry: TagEntry) {
+        self.scopes.borrow_mut().last_mut().unwrap().tags.insert(tag, entry);
+    }
+
+    fn insert_constant(&mut self, name: String, value: i64) ->
+        Result<(), CompilerError> {
+
+        // The value itself works as the asm, since it's an immediate
+        let var = ScopeVariable {
+            asm_rep: value.to_string(),
+            type_of: Type::Int32,
+        };
+
+        let mut scopes = self.scopes.borrow_mut();
+        let scope = scopes.last_mut().unwrap();
+        scope.symbols.insert(name.clone(), var);
+        scope.constants.insert(name, value);
+
+        Ok(())
+    }
+}
+
+/// The labels generated for each case of a switch
+#[derive(Debug)]
+pub struct SwitchLabels {
+    /// Labels for each `case`, in the order they appear in the switch body
+    pub cases: VecDeque<u64>,
+
+    /// Label for `default`, if there is one
+    pub default: Option<u64>,
+}
+
 /// "Owns" a scratch register
 pub struct Scratch {
     pub reg: SizedRegister,
@@ -227,7 +571,7 @@ impl Drop for Scratch {
 
 /// "Owns" a scope that get cleaned up after
 pub struct ScopeOwner {
-    scopes: Rc<RefCell<Vec<HashMap<String, ScopeVariable>>>>,
+    scopes: Rc<RefCell<Vec<Scope>>>,
     rdp_offset: Rc<Cell<usize>>,
     old_offset: usize,
 }
@@ -239,7 +583,7 @@ impl ScopeOwner {
             rdp_offset: instance.rdp_offset.clone(),
             old_offset: instance.rdp_offset.get(),
         };
-        so.scopes.borrow_mut().push(HashMap::new());
+        so.scopes.borrow_mut().push(Scope::default());
         so
     }
 }
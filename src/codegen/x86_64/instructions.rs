@@ -4,24 +4,31 @@ use std::fmt::Display;
 pub enum Instr {
     Mov(String, String),
     Movsx(String, String),
+    Movsxd(String, String),
+    Movzx(String, String),
     Lea(String, String),
 
     Add(String, String),
     Sub(String, String),
     Imul(String, String),
     Idiv(String),
+    Div(String),
     Neg(String),
 
     Not(String),
     Or(String, String),
     Xor(String, String),
     And(String, String),
+    Shl(String, String),
+    Sar(String, String),
+    Shr(String, String),
 
     Push(String),
     Pop(String),
 
     Call(String),
     Jmp(u64),
+    JmpIndirect(String),
 
     Cmp(String, String),
     Je(u64),
@@ -30,10 +37,18 @@ pub enum Instr {
     Jg(u64),
     Jle(u64),
     Jge(u64),
+    Ja(u64),
+    Jb(u64),
 
+    Setne(String),
+
+    Cdq,
     Cqo,
 
     Ret,
+
+    /// Written as-is, e.g. a line of inline asm
+    Raw(String),
 }
 
 impl Display for Instr {
@@ -41,24 +56,31 @@ impl Display for Instr {
         match self {
             Instr::Mov(a, b) => write!(f, "mov {}, {}", a, b),
             Instr::Movsx(a, b) => write!(f, "movsx {}, {}", a, b),
+            Instr::Movsxd(a, b) => write!(f, "movsxd {}, {}", a, b),
+            Instr::Movzx(a, b) => write!(f, "movzx {}, {}", a, b),
             Instr::Lea(a, b) => write!(f, "lea {}, {}", a, b),
 
             Instr::Add(a, b) => write!(f, "add {}, {}", a, b),
             Instr::Sub(a, b) => write!(f, "sub {}, {}", a, b),
             Instr::Imul(a, b) => write!(f, "imul {}, {}", a, b),
             Instr::Idiv(a) => write!(f, "idiv {}", a),
+            Instr::Div(a) => write!(f, "div {}", a),
             Instr::Neg(a) => write!(f, "neg {}", a),
 
             Instr::Not(a) => write!(f, "not {}", a),
             Instr::Or(a, b) => write!(f, "or {}, {}", a, b),
             Instr::Xor(a, b) => write!(f, "xor {}, {}", a, b),
             Instr::And(a, b) => write!(f, "and {}, {}", a, b),
+            Instr::Shl(a, b) => write!(f, "shl {}, {}", a, b),
+            Instr::Sar(a, b) => write!(f, "sar {}, {}", a, b),
+            Instr::Shr(a, b) => write!(f, "shr {}, {}", a, b),
 
             Instr::Push(a) => write!(f, "push {}", a),
             Instr::Pop(a) => write!(f, "pop {}", a),
 
             Instr::Call(a) => write!(f, "call {}", a),
             Instr::Jmp(a) => write!(f, "jmp .L{}", a),
+            Instr::JmpIndirect(a) => write!(f, "jmp {}", a),
 
             Instr::Cmp(a, b) => write!(f, "cmp {}, {}", a, b),
             Instr::Je(a) => write!(f, "je .L{}", a),
@@ -67,10 +89,17 @@ impl Display for Instr {
             Instr::Jg(a) => write!(f, "jg .L{}", a),
             Instr::Jle(a) => write!(f, "jle .L{}", a),
             Instr::Jge(a) => write!(f, "jge .L{}", a),
+            Instr::Ja(a) => write!(f, "ja .L{}", a),
+            Instr::Jb(a) => write!(f, "jb .L{}", a),
 
+            Instr::Setne(a) => write!(f, "setne {}", a),
+
+            Instr::Cdq => write!(f, "cdq"),
             Instr::Cqo => write!(f, "cqo"),
 
             Instr::Ret => write!(f, "ret"),
+
+            Instr::Raw(a) => write!(f, "{}", a),
         }
     }
 }
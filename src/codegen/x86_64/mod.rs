@@ -4,6 +4,9 @@ mod instructions;
 mod instance;
 mod declaration;
 mod statement;
+mod switch;
+mod asm;
+mod variadic;
 mod expression;
 
 use std::{fs::{rename, File}, io::Write, path::PathBuf, process::Command};
@@ -11,14 +14,16 @@ use std::{fs::{rename, File}, io::Write, path::PathBuf, process::Command};
 use instance::GeneratorInstance;
 use log::{info, trace};
 use tempfile::tempdir;
-use crate::{ast::TranslationUnit, error::CompilerError, fs::replace_extension};
+use crate::{ast::{Target, TranslationUnit}, error::CompilerError, fs::replace_extension};
 use super::{error::CodegenError, AssemblerOptions, Generator};
 
-pub struct X86_64Generator;
+pub struct X86_64Generator {
+    target: Target,
+}
 
 impl X86_64Generator {
-    pub fn new() -> X86_64Generator {
-        X86_64Generator {}
+    pub fn new(target: Target) -> X86_64Generator {
+        X86_64Generator { target }
     }
 }
 
@@ -26,12 +31,14 @@ impl Generator for X86_64Generator {
     fn generate(&self, trans_unit: &TranslationUnit) -> 
         Result<String, CodegenError> {
 
-        let mut instance = GeneratorInstance::new();
+        let mut instance = GeneratorInstance::new(self.target);
 
         for decl in &trans_unit.declarations {
-            instance.gen_declaration(decl)?;
+            instance.gen_declaration(decl).map_err(|e| e.at(decl.span))?;
         }
 
+        instance.define_tentatives();
+
         Ok(instance.get_instructions())
     }
 
@@ -105,3 +112,52 @@ impl Generator for X86_64Generator {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    /// Finds `expected` as consecutive lines of the output, where each `%N` in
+    /// it stands for a scratch register. We don't know which ones get picked,
+    /// just that each `%N` is the same one throughout, which this gives back
+    pub fn find_lines<'a>(asm: &'a str, expected: &[&str]) -> Vec<&'a str> {
+        let lines: Vec<&str> = asm.lines().map(str::trim).collect();
+
+        lines.windows(expected.len())
+            .find_map(|window| {
+                let mut regs: Vec<Option<&str>> = vec![None; 10];
+                let all = window.iter().zip(expected)
+                    .all(|(line, pattern)| match_line(line, pattern, &mut regs));
+
+                all.then(|| regs.into_iter().map_while(|reg| reg).collect())
+            })
+            .unwrap_or_else(|| panic!("Expected\n{}\nin\n{}", expected.join("\n"), asm))
+    }
+
+    fn match_line<'a>(line: &'a str, pattern: &str, regs: &mut [Option<&'a str>]) -> bool {
+        let mut line = line;
+        let mut pieces = pattern.split('%');
+
+        // Up to the first `%`
+        let first = pieces.next().unwrap();
+        match line.strip_prefix(first) {
+            Some(rest) => line = rest,
+            None => return false,
+        }
+
+        for piece in pieces {
+            let n = (piece.as_bytes()[0] - b'0') as usize;
+            let len = line.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(line.len());
+            let reg = &line[..len];
+
+            if *regs[n].get_or_insert(reg) != reg {
+                return false;
+            }
+
+            match line[len..].strip_prefix(&piece[1..]) {
+                Some(rest) => line = rest,
+                None => return false,
+            }
+        }
+
+        line.is_empty()
+    }
+}
@@ -36,14 +36,45 @@ impl TryFrom<u8> for Register {
     type Error = &'static str;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        if value >= NUM_REGS {
-            return Err("Register number must be 0-13");
+        use Register::*;
+
+        // In the same order as their numbers
+        const ALL: [Register; NUM_REGS as usize] = [Rax, Rbx, Rcx, Rdx, Rsi, Rdi,
+            R8, R9, R10, R11, R12, R13, R14, R15];
+
+        ALL.get(value as usize).copied().ok_or("Register number must be 0-13")
+    }
+}
+
+impl Register {
+    /// The register a GCC-style asm constraint letter asks for, e.g. `a` for
+    /// RAX
+    pub fn from_constraint(letter: char) -> Option<Register> {
+        match letter {
+            'a' => Some(Register::Rax),
+            'b' => Some(Register::Rbx),
+            'c' => Some(Register::Rcx),
+            'd' => Some(Register::Rdx),
+            'S' => Some(Register::Rsi),
+            'D' => Some(Register::Rdi),
+            _ => None,
         }
+    }
+
+    /// Looks a register up by any of its names, e.g. `rax`, `EAX` or `r8b`
+    pub fn from_name(name: &str) -> Option<Register> {
+        let sizes = [
+            RegisterSize::QWord,
+            RegisterSize::DWord,
+            RegisterSize::Word,
+            RegisterSize::Byte,
+        ];
 
-        // This is safe cause we checked above that its legit
-        Ok(unsafe {
-            std::mem::transmute(value)
-        })
+        (0..NUM_REGS)
+            .map(|i| Register::try_from(i).unwrap())
+            .find(|&reg| sizes.iter().any(|&size| {
+                SizedRegister { reg, size }.to_string().eq_ignore_ascii_case(name)
+            }))
     }
 }
 
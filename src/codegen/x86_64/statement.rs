@@ -1,31 +1,121 @@
-use crate::{ast::Statement, codegen::error::CodegenError};
+use crate::{ast::{expressions::StatementExpr, statements::StatementKind, Statement}, codegen::error::CodegenError};
 
-use super::{instance::GeneratorInstance, instructions::Instr, registers::{Register, SizedRegister}};
+use super::{instance::{GeneratorInstance, Scratch}, instructions::Instr, registers::{Register, RegisterSize, SizedRegister}};
 
 impl GeneratorInstance {
     pub fn gen_statement(&mut self, stmt: &Statement) ->
         Result<(), CodegenError> {
+
+        self.gen_statement_kind(stmt).map_err(|e| e.at(stmt.span))
+    }
+
+    fn gen_statement_kind(&mut self, stmt: &Statement) ->
+        Result<(), CodegenError> {
     
-        match stmt {
-            Statement::Compound(stmts) => {
-                self.enter_scope();
+        match &stmt.kind {
+            StatementKind::Compound(stmts) => {
+                let _s = self.enter_scope();
                     
                 for s in stmts {
                     self.gen_statement(s)?;
                 }
             },
 
-            Statement::Declaration(decl) => self.gen_declaration(decl)?,
+            StatementKind::Declaration(decl) => self.gen_declaration(decl)?,
 
-            Statement::Expression(expr) => { self.gen_expr(&expr)?; },
+            StatementKind::Expression(expr) => { self.gen_expr(expr)?; },
 
-            Statement::If(if_stmt) => todo!(),
+            StatementKind::If(if_stmt) => {
+                let to_else = self.new_label();
 
-            Statement::WhileLoop(while_stmt) => todo!(),
+                {
+                    let cond = self.gen_expr(&if_stmt.condition)?;
+                    self.add_instr(Instr::Cmp(cond.reg.to_string(), "0".to_string()));
+                    self.add_instr(Instr::Je(to_else));
+                }
+
+                self.gen_statement(&if_stmt.if_block)?;
+
+                match &if_stmt.else_block {
+                    Some(else_block) => {
+                        let to_end = self.new_label();
+                        self.add_instr(Instr::Jmp(to_end));
+
+                        self.add_label(to_else);
+                        self.gen_statement(else_block)?;
+                        self.add_label(to_end);
+                    },
+                    None => self.add_label(to_else),
+                }
+            },
+
+            StatementKind::WhileLoop(while_stmt) => {
+                let to_start = self.new_label();
+                let to_end = self.new_label();
+
+                self.add_label(to_start);
+
+                {
+                    let cond = self.gen_expr(&while_stmt.condition)?;
+                    self.add_instr(Instr::Cmp(cond.reg.to_string(), "0".to_string()));
+                    self.add_instr(Instr::Je(to_end));
+                }
+
+                self.break_labels.push(to_end);
+                self.gen_statement(&while_stmt.body)?;
+                self.break_labels.pop();
+
+                self.add_instr(Instr::Jmp(to_start));
+                self.add_label(to_end);
+            },
+
+            StatementKind::Switch(switch) => self.gen_switch(switch)?,
+
+            StatementKind::Case(case) => {
+                // Cases are labelled in the order they appear in the switch
+                let label = self.switches.last_mut()
+                    .and_then(|s| s.cases.pop_front())
+                    .expect("Case outside of switch");
+
+                self.add_label(label);
+                self.gen_statement(&case.body)?;
+            },
+
+            StatementKind::Default(body) => {
+                let label = self.switches.last()
+                    .and_then(|s| s.default)
+                    .expect("Default outside of switch");
+
+                self.add_label(label);
+                self.gen_statement(body)?;
+            },
+
+            StatementKind::Label(label) => {
+                let id = self.user_labels[&label.name];
+
+                self.add_label(id);
+                self.gen_statement(&label.body)?;
+            },
+
+            StatementKind::Goto(name) => {
+                let id = self.user_labels[name];
+
+                self.add_instr(Instr::Jmp(id));
+            },
+
+            StatementKind::Break => {
+                let label = *self.break_labels.last()
+                    .expect("Break outside of loop or switch");
+
+                self.add_instr(Instr::Jmp(label));
+            },
 
-            Statement::Return(ret) => {
+            StatementKind::Return(ret) => {
                 if let Some(expr) = &**ret {
-                    let ret_val = self.gen_expr(&expr)?;
+                    let mut ret_val = self.gen_expr(expr)?;
+                    let from = self.get_expr_type(expr);
+                    let to = self.return_type.clone();
+                    self.gen_convert(&mut ret_val, &from, &to);
                     let rax_sized = SizedRegister {
                         reg: Register::Rax,
                         size: ret_val.reg.size
@@ -38,8 +128,95 @@ impl GeneratorInstance {
 
                 self.add_instr(Instr::Jmp(self.return_label.unwrap()));
             },
+
+            StatementKind::Asm(asm) => self.gen_asm(asm)?,
+        };
+
+        Ok(())
+    }
+
+    /// Runs the body of a statement expression in a scope of its own, giving
+    /// back the value of the last statement
+    pub fn gen_statement_expr(&mut self, expr: &StatementExpr) ->
+        Result<Scratch, CodegenError> {
+
+        let _s = self.enter_scope();
+
+        let Some((last, rest)) = expr.body.split_last() else {
+            return self.alloc_scratch(RegisterSize::Void);
         };
 
+        for stmt in rest {
+            self.gen_statement(stmt)?;
+        }
+
+        match expr.value() {
+            Some(value) => self.gen_expr(value).map_err(|e| e.at(last.span)),
+            None => {
+                self.gen_statement(last)?;
+                self.alloc_scratch(RegisterSize::Void)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{Context, Target}, codegen::{x86_64::X86_64Generator, Generator}, error::CompilerError, fs::read_file, parser::parse};
+
+    fn generate(ending: &str) -> Result<String, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/control/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(&mut Context::new())?;
+
+        Ok(X86_64Generator::new(Target::default()).generate(&parsed)?)
+    }
+
+    #[test]
+    fn if_else_branches() -> Result<(), CompilerError> {
+        let asm = generate("if_else.c")?;
+
+        // False skips to the else block, and the if block jumps past it
+        assert!(asm.contains(", 0\n\tje .L1\n"));
+        assert!(asm.contains("\tjmp .L2\n.L1:"));
+        assert!(asm.contains(".L2:\tmov "));
+        Ok(())
+    }
+
+    #[test]
+    fn while_loops_back() -> Result<(), CompilerError> {
+        let asm = generate("while.c")?;
+
+        // The condition is checked at the top, and the body jumps back to it
+        assert!(asm.contains(".L1:\tmov "));
+        assert!(asm.contains(", 0\n\tje .L2\n"));
+        assert!(asm.contains("\tjmp .L1\n.L2:"));
+        Ok(())
+    }
+
+    #[test]
+    fn loop_locals_reserved_once() -> Result<(), CompilerError> {
+        let asm = generate("loop_local.c")?;
+
+        // z's room is reserved with the rest of the frame, rather than every
+        // time round the loop
+        assert!(asm.contains("\tmov RBP, RSP\n\tsub RSP, 16\n.L1:"));
+        assert_eq!(asm.matches("sub RSP").count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn goto_back_over_local() -> Result<(), CompilerError> {
+        let asm = generate("goto_local.c")?;
+
+        // Jumping back before q's declaration doesn't make room for it again
+        assert!(asm.contains("\tsub RSP, 16\n.L1:\tmov "));
+        assert!(asm.contains("\tjmp .L1\n"));
+        assert_eq!(asm.matches("sub RSP").count(), 1);
         Ok(())
     }
 }
@@ -0,0 +1,272 @@
+use std::collections::VecDeque;
+
+use crate::{ast::{layout::Constants, statements::SwitchStatement}, codegen::error::CodegenError};
+
+use super::{helpers::get_size, instance::{GeneratorInstance, SwitchLabels}, instructions::Instr, registers::{RegisterSize, SizedRegister}};
+
+/// Fewest cases a switch needs before we'll consider a jump table
+const JUMP_TABLE_MIN_CASES: usize = 4;
+
+/// Most jump table entries we're willing to spend per case. Anything sparser
+/// than this gets searched instead
+const JUMP_TABLE_MAX_SPREAD: u64 = 3;
+
+/// Once the search gets down to this many cases, just compare against each
+const LINEAR_SEARCH_MAX: usize = 3;
+
+/// How many entries a jump table covering all the (sorted) cases would need
+fn table_span(cases: &[(i64, u64)]) -> u64 {
+    let lowest = cases[0].0;
+    let highest = cases[cases.len() - 1].0;
+
+    (highest.wrapping_sub(lowest) as u64).saturating_add(1)
+}
+
+fn is_dense(cases: &[(i64, u64)]) -> bool {
+    cases.len() >= JUMP_TABLE_MIN_CASES
+        && table_span(cases) <= cases.len() as u64 * JUMP_TABLE_MAX_SPREAD
+}
+
+impl GeneratorInstance {
+    /// Emits `op reg, value`, going via a scratch if the value is too big to
+    /// be an immediate (x86 only does sign-extended 32-bit ones)
+    fn gen_imm_op(&mut self,
+        op: fn(String, String) -> Instr,
+        reg: &SizedRegister,
+        value: i64
+    ) -> Result<(), CodegenError> {
+
+        let is_qword = matches!(reg.size, RegisterSize::QWord);
+
+        if !is_qword || i32::try_from(value).is_ok() {
+            self.add_instr(op(reg.to_string(), value.to_string()));
+        } else {
+            let tmp = self.alloc_scratch(RegisterSize::QWord)?;
+            self.add_instr(Instr::Mov(tmp.reg.to_string(), value.to_string()));
+            self.add_instr(op(reg.to_string(), tmp.reg.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Jumps to the right case by indexing into a table of label offsets
+    fn gen_jump_table(&mut self,
+        cond: &SizedRegister,
+        cases: &[(i64, u64)],
+        fallback: u64,
+        signed: bool
+    ) -> Result<(), CodegenError> {
+
+        let lowest = cases[0].0;
+        let span = table_span(cases);
+
+        // Widen the condition to 64 bits so we can index with it
+        let index = self.alloc_scratch(RegisterSize::QWord)?;
+        let widen = match (cond.size, signed) {
+            (RegisterSize::QWord, _) =>
+                Instr::Mov(index.reg.to_string(), cond.to_string()),
+            (RegisterSize::DWord, true) =>
+                Instr::Movsxd(index.reg.to_string(), cond.to_string()),
+            // Writing the lower 32 bits zeroes the upper ones
+            (RegisterSize::DWord, false) => {
+                let index_low = SizedRegister {
+                    reg: index.reg.reg,
+                    size: RegisterSize::DWord
+                };
+                Instr::Mov(index_low.to_string(), cond.to_string())
+            },
+            (_, true) => Instr::Movsx(index.reg.to_string(), cond.to_string()),
+            (_, false) => Instr::Movzx(index.reg.to_string(), cond.to_string()),
+        };
+        self.add_instr(widen);
+
+        // Anything below the lowest case wraps around to a huge unsigned
+        // number, so one unsigned comparison checks both ends of the range
+        self.gen_imm_op(Instr::Sub, &index.reg, lowest)?;
+        self.add_instr(Instr::Cmp(index.reg.to_string(), (span - 1).to_string()));
+        self.add_instr(Instr::Ja(fallback));
+
+        let table = self.new_label();
+        let base = self.alloc_scratch(RegisterSize::QWord)?;
+        let offset = self.alloc_scratch(RegisterSize::QWord)?;
+
+        self.add_instr(Instr::Lea(base.reg.to_string(), format!("[.L{}]", table)));
+        self.add_instr(Instr::Movsxd(
+            offset.reg.to_string(),
+            format!("DWORD [{} + {}*4]", base.reg, index.reg)));
+        self.add_instr(Instr::Add(base.reg.to_string(), offset.reg.to_string()));
+        self.add_instr(Instr::JmpIndirect(base.reg.to_string()));
+
+        let mut targets = vec![fallback; span as usize];
+        for (value, label) in cases {
+            targets[value.wrapping_sub(lowest) as u64 as usize] = *label;
+        }
+
+        self.add_jump_table(table, &targets);
+
+        Ok(())
+    }
+
+    /// Jumps to the right case via a binary search over the (sorted) cases
+    fn gen_case_search(&mut self,
+        cond: &SizedRegister,
+        cases: &[(i64, u64)],
+        fallback: u64,
+        signed: bool
+    ) -> Result<(), CodegenError> {
+
+        if cases.len() <= LINEAR_SEARCH_MAX {
+            for (value, label) in cases {
+                self.gen_imm_op(Instr::Cmp, cond, *value)?;
+                self.add_instr(Instr::Je(*label));
+            }
+
+            self.add_instr(Instr::Jmp(fallback));
+            return Ok(());
+        }
+
+        // Check the middle case, then search whichever half we'd be in
+        let mid = cases.len() / 2;
+        let (value, label) = cases[mid];
+        let lower_half = self.new_label();
+
+        self.gen_imm_op(Instr::Cmp, cond, value)?;
+        self.add_instr(Instr::Je(label));
+        self.add_instr(if signed {
+            Instr::Jl(lower_half)
+        } else {
+            Instr::Jb(lower_half)
+        });
+
+        self.gen_case_search(cond, &cases[mid + 1..], fallback, signed)?;
+
+        self.add_label(lower_half);
+        self.gen_case_search(cond, &cases[..mid], fallback, signed)
+    }
+
+    pub fn gen_switch(&mut self, switch: &SwitchStatement) ->
+        Result<(), CodegenError> {
+
+        // The condition gets promoted before it's compared against anything
+        let cond_t = self.get_expr_type(&switch.condition);
+        let promoted = cond_t.promoted();
+        let signed = promoted.is_signed(self.target());
+
+        let (case_stmts, defaults) = switch.get_cases();
+
+        let end = self.new_label();
+        let default = if defaults > 0 {
+            Some(self.new_label())
+        } else {
+            None
+        };
+
+        let mut labels = VecDeque::new();
+        let mut cases = vec![];
+        for case in case_stmts {
+            // Verification already made sure this is constant
+            let value = case.value.eval_constant(self)
+                .expect("Case value must be constant");
+
+            let label = self.new_label();
+            labels.push_back(label);
+            cases.push((promoted.convert_constant(value, self.target()), label));
+        }
+
+        if signed {
+            cases.sort_by_key(|(value, _)| *value);
+        } else {
+            cases.sort_by_key(|(value, _)| *value as u64);
+        }
+
+        // Scope the condition's scratch so it's freed before the body
+        {
+            let mut cond = self.gen_expr(&switch.condition)?;
+            self.gen_extend(&mut cond, get_size(&promoted, self), cond_t.is_signed(self.target()));
+            let fallback = default.unwrap_or(end);
+
+            if cases.is_empty() {
+                self.add_instr(Instr::Jmp(fallback));
+            } else if is_dense(&cases) {
+                self.gen_jump_table(&cond.reg, &cases, fallback, signed)?;
+            } else {
+                self.gen_case_search(&cond.reg, &cases, fallback, signed)?;
+            }
+        }
+
+        self.switches.push(SwitchLabels { cases: labels, default });
+        self.break_labels.push(end);
+
+        self.gen_statement(&switch.body)?;
+
+        self.break_labels.pop();
+        self.switches.pop();
+
+        self.add_label(end);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{Context, Target}, codegen::{x86_64::X86_64Generator, Generator}, error::CompilerError, fs::read_file, parser::parse};
+
+    fn generate(ending: &str) -> Result<String, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/switch/");
+        p.push(ending);
+
+        let parsed = parse(&read_file(&p)?)?;
+        parsed.verify(&mut Context::new())?;
+
+        Ok(X86_64Generator::new(Target::default()).generate(&parsed)?)
+    }
+
+    #[test]
+    fn dense_uses_table() -> Result<(), CompilerError> {
+        let asm = generate("dense.c")?;
+
+        // Anything outside 1 through 5 goes to default, as does the gap at 4
+        assert!(asm.contains(", 1\n\tcmp R"));
+        assert!(asm.contains(", 4\n\tja .L2\n"));
+        assert!(asm.contains(".L7:\n\tdd .L3 - .L7\n\tdd .L4 - .L7\n\tdd .L5 - .L7\n\
+            \tdd .L2 - .L7\n\tdd .L6 - .L7\n.L3:"));
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_uses_search() -> Result<(), CompilerError> {
+        let asm = generate("sparse.c")?;
+
+        assert!(!asm.contains("\tdd "));
+        assert!(asm.contains(", -1000\n"));
+        assert!(asm.contains("jl "));
+        Ok(())
+    }
+
+    #[test]
+    fn promoted_condition() -> Result<(), CompilerError> {
+        let asm = generate("promoted.c")?;
+
+        // A char condition becomes an int, so 256 is its own case rather
+        // than another 0
+        assert!(asm.contains("\tmovsx "));
+        assert!(asm.contains(", 0\n\tje .L2\n"));
+        assert!(asm.contains(", 256\n\tje .L3\n\tjmp .L1\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn case_locals_reserved_up_front() -> Result<(), CompilerError> {
+        let asm = generate("case_local.c")?;
+
+        // Jumping straight to case 2 skips y's declaration, but its room's
+        // already there, so the call can't clobber it
+        assert!(asm.contains("\tmov RBP, RSP\n\tsub RSP, 16\n"));
+        assert_eq!(asm.matches("sub RSP, 16").count(), 1);
+        assert!(!asm.contains("sub RSP, 4"));
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{ast::{expressions::{BinaryExpr, VaArgExpr}, Type}, codegen::error::CodegenError};
+
+use super::{helpers::{get_bytes, get_memory_asm, get_size}, instance::{GeneratorInstance, Scratch}, instructions::Instr, registers::{RegisterSize, SizedRegister, ARG_REGS}};
+
+/// Bytes in the register save area, one QWord per arg register. SysV has room
+/// for the XMM registers after these too, but we never save those
+const SAVE_AREA_SIZE: usize = 48;
+
+/// What `fp_offset` is once every XMM register's been used. We set it to this
+/// straight away, so that anything reading floats out of our `va_list`s goes
+/// to the stack instead of the XMM registers we didn't save
+const FP_OFFSET_EXHAUSTED: usize = 176;
+
+/// Where a variadic function keeps its register args, so that `va_arg` can
+/// get to the ones after its named params
+#[derive(Debug, Clone, Copy)]
+pub struct SaveArea {
+    /// How far below RBP it starts
+    rbp_offset: usize,
+
+    /// How many named params the function has
+    named: usize,
+}
+
+impl GeneratorInstance {
+    /// Saves every arg register at the start of a variadic function, before
+    /// anything can clobber the ones that aren't named params
+    pub fn gen_save_area(&mut self, named: usize) {
+        let rbp_offset = self.alloc_stack(SAVE_AREA_SIZE, 8);
+
+        for (i, reg) in ARG_REGS.iter().enumerate() {
+            let reg = SizedRegister { reg: *reg, size: RegisterSize::QWord };
+
+            self.add_instr(Instr::Mov(
+                format!("QWORD [RBP - {}]", rbp_offset - i * 8),
+                reg.to_string()));
+        }
+
+        self.save_area = Some(SaveArea { rbp_offset, named });
+    }
+
+    /// Fills in a `va_list` so that it starts at the first unnamed arg. Its
+    /// fields are `gp_offset`, `fp_offset`, `overflow_arg_area` and
+    /// `reg_save_area`, in that order
+    pub fn gen_va_start(&mut self, expr: &BinaryExpr) ->
+        Result<Scratch, CodegenError> {
+
+        let area = self.save_area.expect("va_start outside variadic fn");
+        let list = self.gen_expr(&expr.first)?;
+
+        let named_regs = area.named.min(ARG_REGS.len());
+        let named_stack = area.named - named_regs;
+
+        self.add_instr(Instr::Mov(
+            format!("DWORD [{}]", list.reg),
+            (named_regs * 8).to_string()));
+        self.add_instr(Instr::Mov(
+            format!("DWORD [{} + 4]", list.reg),
+            FP_OFFSET_EXHAUSTED.to_string()));
+
+        // Stack args start just past the return address and saved RBP
+        let temp = self.alloc_scratch(RegisterSize::QWord)?;
+        self.add_instr(Instr::Lea(
+            temp.reg.to_string(),
+            format!("[RBP + {}]", 16 + named_stack * 8)));
+        self.add_instr(Instr::Mov(
+            format!("QWORD [{} + 8]", list.reg),
+            temp.reg.to_string()));
+
+        self.add_instr(Instr::Lea(
+            temp.reg.to_string(),
+            format!("[RBP - {}]", area.rbp_offset)));
+        self.add_instr(Instr::Mov(
+            format!("QWORD [{} + 16]", list.reg),
+            temp.reg.to_string()));
+
+        self.alloc_scratch(RegisterSize::Void)
+    }
+
+    /// Gets the next arg out of a `va_list`, from the save area if there are
+    /// any arg registers left or from the stack if not
+    pub fn gen_va_arg(&mut self, expr: &VaArgExpr) ->
+        Result<Scratch, CodegenError> {
+
+        let list = self.gen_expr(&expr.list)?;
+        let offset = self.alloc_scratch(RegisterSize::DWord)?;
+        let addr = self.alloc_scratch(RegisterSize::QWord)?;
+
+        let offset_qword = SizedRegister {
+            reg: offset.reg.reg,
+            size: RegisterSize::QWord,
+        };
+
+        let in_regs = self.new_label();
+        let done = self.new_label();
+
+        self.add_instr(Instr::Mov(
+            offset.reg.to_string(),
+            format!("DWORD [{}]", list.reg)));
+        self.add_instr(Instr::Cmp(
+            offset.reg.to_string(),
+            SAVE_AREA_SIZE.to_string()));
+        self.add_instr(Instr::Jb(in_regs));
+
+        // Every arg gets a whole QWord on the stack
+        self.add_instr(Instr::Mov(
+            addr.reg.to_string(),
+            format!("QWORD [{} + 8]", list.reg)));
+        self.add_instr(Instr::Lea(
+            offset_qword.to_string(),
+            format!("[{} + 8]", addr.reg)));
+        self.add_instr(Instr::Mov(
+            format!("QWORD [{} + 8]", list.reg),
+            offset_qword.to_string()));
+        self.add_instr(Instr::Jmp(done));
+
+        // Writing the DWord offset cleared the rest of its register, so it
+        // can be added to the address as is
+        self.add_label(in_regs);
+        self.add_instr(Instr::Mov(
+            addr.reg.to_string(),
+            format!("QWORD [{} + 16]", list.reg)));
+        self.add_instr(Instr::Add(
+            addr.reg.to_string(),
+            offset_qword.to_string()));
+        self.add_instr(Instr::Add(offset.reg.to_string(), "8".to_string()));
+        self.add_instr(Instr::Mov(
+            format!("DWORD [{}]", list.reg),
+            offset.reg.to_string()));
+
+        self.add_label(done);
+
+        let result = self.alloc_scratch(get_size(&expr.type_of, self))?;
+        let location = format!("[{}]", addr.reg);
+        self.add_instr(Instr::Mov(
+            result.reg.to_string(),
+            get_memory_asm(&location, &expr.type_of, self)));
+
+        Ok(result)
+    }
+
+    /// Copies one `va_list` into another, so both carry on from the same arg
+    pub fn gen_va_copy(&mut self, expr: &BinaryExpr) ->
+        Result<Scratch, CodegenError> {
+
+        let dest = self.gen_expr(&expr.first)?;
+        let src = self.gen_expr(&expr.second)?;
+
+        let size = get_bytes(&Type::va_list(), self);
+        self.gen_copy(&dest.reg, &src.reg, size)?;
+
+        self.alloc_scratch(RegisterSize::Void)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{types::FunctionType, Context, Target, Type}, codegen::{x86_64::X86_64Generator, Generator}, error::CompilerError, fs::read_file, parser::parse};
+
+    fn read(ending: &str) -> Result<String, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/variadic/");
+        p.push(ending);
+        read_file(&p)
+    }
+
+    fn generate(ending: &str) -> Result<String, CompilerError> {
+        let parsed = parse(&read(ending)?)?;
+        parsed.verify(&mut Context::new())?;
+
+        Ok(X86_64Generator::new(Target::default()).generate(&parsed)?)
+    }
+
+    fn expect_semantic_error(ending: &str) -> Result<(), CompilerError> {
+        match parse(&read(ending)?)?.verify(&mut Context::new()) {
+            Ok(_) => panic!("Verified when we shouldn't have"),
+            Err(e) => match e.root() {
+                CompilerError::SemanticError(_) => Ok(()),
+                _ => Err(e)
+            }
+        }
+    }
+
+    #[test]
+    fn sum() -> Result<(), CompilerError> {
+        let unit = parse(&read("sum.c")?)?;
+
+        match &unit.declarations[0].type_of {
+            Type::Function(f) => assert!(matches!(**f,
+                FunctionType { variadic: true, ref args, .. } if args.len() == 1)),
+            _ => panic!("printf should be a function"),
+        }
+
+        let asm = generate("sum.c")?;
+
+        // Every arg register gets saved, and va_start skips the named one
+        assert!(asm.contains("mov QWORD [RBP - 8], R9\n"));
+        assert!(asm.contains("], 8\n\tmov DWORD ["));
+        assert!(asm.contains("+ 4], 176\n"));
+
+        // The short passed to printf gets promoted, and AL is set for both
+        // variadic calls
+        assert!(asm.contains("movsx "));
+        assert_eq!(asm.matches("mov EAX, 0\n\tcall").count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn outside() -> Result<(), CompilerError> {
+        expect_semantic_error("outside.c")
+    }
+
+    #[test]
+    fn too_few() -> Result<(), CompilerError> {
+        expect_semantic_error("too_few.c")
+    }
+
+    #[test]
+    fn not_a_list() -> Result<(), CompilerError> {
+        expect_semantic_error("not_a_list.c")
+    }
+
+    #[test]
+    fn lookalike() -> Result<(), CompilerError> {
+        expect_semantic_error("lookalike.c")
+    }
+
+    #[test]
+    fn struct_arg() -> Result<(), CompilerError> {
+        expect_semantic_error("struct_arg.c")
+    }
+}
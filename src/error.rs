@@ -1,8 +1,8 @@
-use std::io;
-use lalrpop_util::{lexer::Token, ParseError};
+use std::{fmt::Display, io};
+use lalrpop_util::ParseError;
 use thiserror::Error;
 
-use crate::codegen::error::CodegenError;
+use crate::{ast::Span, codegen::error::CodegenError, preprocessor::source_map::{SourceLocation, SourceMap}};
 
 #[derive(Error, Debug)]
 pub enum CompilerError {
@@ -12,9 +12,13 @@ pub enum CompilerError {
     #[error("File Error: {0}")]
     FileError(#[from] io::Error),
 
-    #[error("Parsing Error: {0}")]
-    ParseError(
-        lalrpop_util::ParseError<usize, String, &'static str>),
+    /// Every syntax error found, since the parser carries on past them. Each
+    /// is a `SyntaxError`, pointed at where it was found
+    #[error("{}", display_parse_errors(.0))]
+    ParseError(Vec<CompilerError>),
+
+    #[error("Parsing Error: {}", display_syntax_error(.0))]
+    SyntaxError(ParseError<usize, String, &'static str>),
 
     #[error("Feature Not Supported: {0}")]
     NotSupported(&'static str),
@@ -22,11 +26,86 @@ pub enum CompilerError {
     #[error("Semantic Error: {0}")]
     SemanticError(&'static str),
 
+    /// A `_Static_assert` that failed, with its message if it had one
+    #[error("Static Assertion Failed{}", .0.as_ref().map(|m| format!(": {}", m)).unwrap_or_default())]
+    StaticAssertion(Option<String>),
+
     #[error("Compiliation Error: {0}")]
-    CodegenError(#[from] CodegenError),
+    CodegenError(CodegenError),
 
     #[error("{0}")]
     Custom(&'static str),
+
+    /// An error from part of the source, before we know which file and line
+    /// that is
+    #[error("{error} (at {span})")]
+    Spanned { span: Span, error: Box<CompilerError> },
+
+    #[error("{location}: {error}")]
+    Located { location: SourceLocation, error: Box<CompilerError> },
+}
+
+impl CompilerError {
+    /// Points the error at `span`, unless something more specific (i.e. a node
+    /// nested inside it) already has
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            Self::Spanned { .. } | Self::Located { .. } => self,
+            _ if span.is_empty() => self,
+            error => Self::Spanned { span, error: Box::new(error) },
+        }
+    }
+
+    /// Turns the span into a file, line and column
+    pub fn locate(self, source_map: &SourceMap) -> Self {
+        match self {
+            Self::Spanned { span, error } => Self::Located {
+                location: source_map.locate(span.start),
+                error,
+            },
+            Self::ParseError(errors) => Self::ParseError(errors.into_iter()
+                .map(|e| e.locate(source_map))
+                .collect()),
+            _ => self,
+        }
+    }
+
+    /// The error itself, without where it happened
+    pub fn root(&self) -> &CompilerError {
+        match self {
+            Self::Spanned { error, .. } | Self::Located { error, .. } => error.root(),
+            _ => self,
+        }
+    }
+}
+
+/// Something that's probably a mistake, though it doesn't stop us compiling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Warning {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Warning { message: message.into(), span }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Warning: {}", self.message)
+    }
+}
+
+impl From<CodegenError> for CompilerError {
+    fn from(value: CodegenError) -> Self {
+        match value {
+            CodegenError::Spanned { span, error } =>
+                Self::CodegenError(*error).at(span),
+            error => Self::CodegenError(error),
+        }
+    }
 }
 
 impl From<&'static str> for CompilerError {
@@ -35,33 +114,82 @@ impl From<&'static str> for CompilerError {
     }
 }
 
+fn display_parse_errors(errors: &[CompilerError]) -> String {
+    errors.iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Like LALRPOP's own messages, but leaving out the offsets, since the error
+/// gets a proper location instead
+fn display_syntax_error(error: &ParseError<usize, String, &'static str>) -> String {
+    let expecting = |expected: &[String]| match expected {
+        [] => String::new(),
+        [only] => format!("\nExpected {}", only),
+        [rest @ .., last] => format!("\nExpected one of {} or {}", rest.join(", "), last),
+    };
+
+    match error {
+        ParseError::InvalidToken { .. } => "Invalid token".to_string(),
+        ParseError::UnrecognizedEof { expected, .. } =>
+            format!("Unexpected end of file{}", expecting(expected)),
+        ParseError::UnrecognizedToken { token: (_, token, _), expected } =>
+            format!("Unrecognized token `{}`{}", token, expecting(expected)),
+        ParseError::ExtraToken { token: (_, token, _) } =>
+            format!("Extra token `{}`", token),
+        ParseError::User { error } => error.to_string(),
+    }
+}
+
+impl<T: Display> From<ParseError<usize, T, &'static str>> for CompilerError {
+    fn from(value: ParseError<usize, T, &'static str>) -> Self {
+        CompilerError::ParseError(vec![syntax_error(value)])
+    }
+}
+
+/// Turns one of LALRPOP's errors into ours, pointed at the token it's about.
+/// Errors from the grammar's own code don't say where they are, so it's up to
+/// the caller to point those
+pub fn syntax_error<T: Display>(value: ParseError<usize, T, &'static str>) -> CompilerError {
+    let span = match &value {
+        ParseError::InvalidToken { location } |
+            ParseError::UnrecognizedEof { location, .. } =>
+            Span::new(*location, *location + 1),
+        ParseError::UnrecognizedToken { token: (start, _, end), .. } |
+            ParseError::ExtraToken { token: (start, _, end) } =>
+            Span::new(*start, *end),
+        ParseError::User { .. } => Span::default(),
+    };
+
+    CompilerError::SyntaxError(owned_parse_error(value)).at(span)
+}
+
 /// All this is doing is taking ownership of the tokens passed back by LALRPOP's
-/// errors. They're all `&'a str`s, but we want `String`s!
-impl<'a> From<ParseError<usize, Token<'a>, &'static str>> for CompilerError {
-    fn from(value: ParseError<usize, Token<'a>, &'static str>) -> Self {
-        let new = match value {
-            ParseError::InvalidToken { location } =>
-                ParseError::InvalidToken { location },
-            ParseError::UnrecognizedEof { location, expected } =>
-                ParseError::UnrecognizedEof { location, expected },
-            ParseError::UnrecognizedToken { token, expected } => {
-                let (start, tok, end) = token;
-                let new_tok = String::from(tok.1);
-                ParseError::UnrecognizedToken {
-                    token: (start, new_tok, end),
-                    expected
-                }
-            },
-            ParseError::ExtraToken { token } => {
-                let (start, tok, end) = token;
-                let new_tok = String::from(tok.1);
-                ParseError::ExtraToken {
-                    token: (start, new_tok, end)
-                }
-            },
-            ParseError::User { error } => ParseError::User { error }
-        };
+/// errors. They borrow from the input, but we want `String`s!
+pub fn owned_parse_error<T: Display>(value: ParseError<usize, T, &'static str>) ->
+    ParseError<usize, String, &'static str> {
 
-        CompilerError::ParseError(new)
+    match value {
+        ParseError::InvalidToken { location } =>
+            ParseError::InvalidToken { location },
+        ParseError::UnrecognizedEof { location, expected } =>
+            ParseError::UnrecognizedEof { location, expected },
+        ParseError::UnrecognizedToken { token, expected } => {
+            let (start, tok, end) = token;
+            let new_tok = tok.to_string();
+            ParseError::UnrecognizedToken {
+                token: (start, new_tok, end),
+                expected
+            }
+        },
+        ParseError::ExtraToken { token } => {
+            let (start, tok, end) = token;
+            let new_tok = tok.to_string();
+            ParseError::ExtraToken {
+                token: (start, new_tok, end)
+            }
+        },
+        ParseError::User { error } => ParseError::User { error }
     }
 }
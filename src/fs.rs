@@ -9,7 +9,12 @@ pub fn read_file(path: &Path) -> Result<String, CompilerError> {
     let mut f = File::open(path)?;
     let mut contents = String::new();
     f.read_to_string(&mut contents)?;
-    Ok(contents)
+
+    // Editors on some platforms start UTF-8 files with a byte order mark
+    match contents.strip_prefix('\u{feff}') {
+        Some(rest) => Ok(rest.to_string()),
+        None => Ok(contents),
+    }
 }
 
 pub fn replace_extension(mut path: PathBuf, extension: &str) -> PathBuf {
@@ -36,6 +41,13 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn byte_order_mark() -> Result<(), CompilerError> {
+        let content = read_file(Path::new("tests/files/unit/fs/bom.txt"))?;
+        assert_eq!("Hello World\n", content);
+        Ok(())
+    }
+
     #[test]
     fn not_found() -> Result<(), CompilerError> {
         let out = read_file(Path::new("tests/files/unit/fs/fake.txt"));
@@ -1,12 +1,12 @@
 use std::path::PathBuf;
 use ast::Context;
 use clap::{Parser, ValueEnum};
-use codegen::{get_generator, AssemblerOptions};
+use codegen::{get_generator, triple::{get_target, get_triple}, AssemblerOptions};
 use colog::basic_builder;
 use error::CompilerError;
-use log::{debug, info, error, LevelFilter};
-use parser::parse;
-use preprocessor::preprocess;
+use log::{debug, info, error, warn, LevelFilter};
+use parser::{parse_for_target, LanguageMode};
+use preprocessor::preprocess_mapped;
 
 pub mod error;
 pub mod fs;
@@ -75,6 +75,14 @@ struct CLIArgs {
     #[arg(long)]
     ast: bool,
 
+    /// Accept old-style (K&R) function definitions
+    #[arg(long)]
+    legacy: bool,
+
+    /// Accept C23's new keywords, e.g. `bool`
+    #[arg(long, conflicts_with = "legacy")]
+    c23: bool,
+
     /// How detailed should logs be?
     #[arg(short, long, default_value_t = LogLevel::Standard, value_enum)]
     log_level: LogLevel,
@@ -82,6 +90,12 @@ struct CLIArgs {
 
 fn entry() -> Result<(), CompilerError> {
     let args = CLIArgs::parse();
+    let mode = match (args.legacy, args.c23) {
+        (true, _) => LanguageMode::Legacy,
+        (_, true) => LanguageMode::C23,
+        _ => LanguageMode::Standard,
+    };
+    let target = get_target(&get_triple());
 
     let mut log_builder = basic_builder();
     log_builder.filter_level(LevelFilter::from(&args.log_level));
@@ -93,44 +107,53 @@ fn entry() -> Result<(), CompilerError> {
         return Err(CompilerError::InvalidOption("Cannot specify -o when generating multiple output files (e.g. with -c)"));
     }
 
-    let files_preproccessed = args.files.iter().enumerate().map(|(i, ref s)| {
+    let files_preproccessed = args.files.iter().enumerate().map(|(i, s)| {
         info!("Preprocessing {}", args.files[i].display());
-        preprocess(s)
+        preprocess_mapped(s)
     });
 
     if args.preprocess_only {
         for file in files_preproccessed {
-            println!("{}", file?);
+            println!("{}", file?.0);
         }
 
         return Ok(());
     }
 
     let files_parsed = files_preproccessed.enumerate().map(|(i, s)| match s {
-        Ok(x) => {
+        Ok((x, source_map)) => {
             info!("Parsing {}", args.files[i].display());
-            let parsed = parse(&x)?;
+            let parsed = parse_for_target(&x, mode, target)
+                .map_err(|e| e.locate(&source_map))?;
             info!("Semantically checking {}", args.files[i].display());
-            parsed.verify(&mut Context::new())?;
-            Ok(parsed)
+            let mut context = Context::for_target(target);
+            let verified = parsed.verify(&mut context);
+
+            for warning in context.take_warnings() {
+                warn!("{}: {}", source_map.locate(warning.span.start), warning);
+            }
+
+            verified.map_err(|e| e.locate(&source_map))?;
+            Ok((parsed, source_map))
         },
         Err(e) => Err(e),
     });
 
     if args.ast {
         for file in files_parsed {
-            println!("{:#?}", file?);
+            println!("{:#?}", file?.0);
         }
 
         return Ok(());
     }
 
-    let generator = get_generator(&target_lexicon::Architecture::X86_64)?;
+    let generator = get_generator(&target_lexicon::Architecture::X86_64, target)?;
 
     let assembly = files_parsed.enumerate().map(|(i, x)| match x {
-        Ok(trans_unit) => {
+        Ok((trans_unit, source_map)) => {
             info!("Generating assembly for {}", args.files[i].display());
-            let asm = generator.generate(&trans_unit)?;
+            let asm = generator.generate(&trans_unit)
+                .map_err(|e| CompilerError::from(e).locate(&source_map))?;
             Ok(asm)
         },
         Err(e) => Err(e),
@@ -1,17 +1,262 @@
 // Helpers for LALRPOP grammar
 
-use crate::ast::{expressions::{BinaryExpr, UnaryExpr}, statements::{IfStatement, WhileLoopStatement}, Expression, Statement};
+use std::{cell::RefCell, collections::HashSet, iter::Peekable, str::Chars};
+
+use lalrpop_util::ParseError;
+
+use crate::{ast::{asm::{AsmOperand, AsmStatement}, attributes::{Attributes, Visibility}, declaration::{Alignment, Declaration, DeclarationValue, StaticAssert, StorageClass}, expressions::{AssignOp, BinaryExpr, CharLiteralExpr, CompoundAssignExpr, Encoding, ExpressionKind, MemberExpr, StringLiteralExpr, TernaryExpr, UnaryExpr}, initializer::{InitItem, Initializer}, types::{ArrayType, EnumType, Enumerator, FunctionType, Qualifiers, StructMember, StructType, TagKind}, Type, statements::{CaseStatement, IfStatement, LabelStatement, StatementKind, SwitchStatement, WhileLoopStatement}, Expression, Span, Statement, Target}, error::Warning};
+
+use super::{lexer::Tok, typedefs::Typedefs, LanguageMode, UserError, Warnings};
+
+/// Points an error from one of the helpers here at the input it's about
+pub fn user_error<'input>(lo: usize, hi: usize) ->
+    impl Fn(&'static str) -> ParseError<usize, Tok<'input>, UserError> {
+
+    move |error| ParseError::User { error: (Span::new(lo, hi), error) }
+}
 
 pub fn parse_if(
     condition: Expression,
     if_block: Statement,
     else_block: Option<Statement>
-) -> Statement {
-    Statement::If(Box::new(IfStatement { condition, if_block, else_block }))
+) -> StatementKind {
+    StatementKind::If(Box::new(IfStatement { condition, if_block, else_block }))
+}
+
+pub fn parse_while(condition: Expression, body: Statement) -> StatementKind {
+    StatementKind::WhileLoop(Box::new(WhileLoopStatement { condition, body } ))
+}
+
+pub fn parse_switch(condition: Expression, body: Statement) -> StatementKind {
+    StatementKind::Switch(Box::new(SwitchStatement { condition, body }))
+}
+
+pub fn parse_case(value: Expression, body: Statement) -> StatementKind {
+    StatementKind::Case(Box::new(CaseStatement { value, body }))
+}
+
+pub fn parse_label(name: &str, body: Statement) -> StatementKind {
+    StatementKind::Label(Box::new(LabelStatement { name: String::from(name), body }))
+}
+
+/// The operands of an extended asm statement: outputs, inputs and clobbers
+pub type AsmOperands<'a> = (Vec<AsmOperand>, Vec<AsmOperand>, Vec<&'a str>);
+
+pub fn parse_asm(volatile: bool, template: Vec<&str>, operands: Option<AsmOperands>)
+    -> Result<StatementKind, &'static str> {
+
+    let template = parse_string(&template)?;
+    let extended = operands.is_some();
+    let (outputs, inputs, clobbers) = operands.unwrap_or_default();
+
+    let clobbers = clobbers.into_iter()
+        .map(|c| parse_string(&[c]))
+        .collect::<Result<_, _>>()?;
+
+    Ok(StatementKind::Asm(Box::new(AsmStatement {
+        template, volatile, extended, outputs, inputs, clobbers,
+    })))
+}
+
+pub fn parse_asm_operand(constraint: &str, expr: Expression) ->
+    Result<AsmOperand, &'static str> {
+
+    let constraint = parse_string(&[constraint])?;
+    if constraint.is_empty() {
+        return Err("Asm operands need a constraint");
+    }
+
+    Ok(AsmOperand { constraint, expr })
+}
+
+/// What an escape sequence in a char or string literal stands for
+enum Escape {
+    /// A character, which gets encoded however the literal is
+    Char(char),
+
+    /// A numeric escape like `\x41` or `\101`, which is a code unit as is
+    Unit(u32),
+}
+
+/// Reads an escape sequence, just after its backslash
+fn parse_escape(chars: &mut Peekable<Chars>) -> Result<Escape, &'static str> {
+    // The lexer already checked there's something after it
+    let escaped = match chars.next().unwrap() {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'a' => '\x07',
+        'b' => '\x08',
+        'f' => '\x0c',
+        'v' => '\x0b',
+        c @ ('\\' | '\'' | '"' | '?') => c,
+
+        'x' => {
+            let mut code = 0u32;
+            while let Some(d) = chars.peek().and_then(|d| d.to_digit(16)) {
+                code = code.saturating_mul(16).saturating_add(d);
+                chars.next();
+            }
+
+            return Ok(Escape::Unit(code));
+        },
+
+        c @ '0'..='7' => {
+            let mut code = c.to_digit(8).unwrap();
+            for _ in 0..2 {
+                match chars.peek().and_then(|d| d.to_digit(8)) {
+                    Some(d) => code = code * 8 + d,
+                    None => break,
+                }
+                chars.next();
+            }
+
+            return Ok(Escape::Unit(code));
+        },
+
+        // Universal character names, e.g. `\u00e9`, which name a code point
+        c @ ('u' | 'U') => {
+            let digits = if c == 'u' { 4 } else { 8 };
+            let mut code = 0u32;
+
+            for _ in 0..digits {
+                let d = chars.next().and_then(|d| d.to_digit(16))
+                    .ok_or("Universal character names need 4 or 8 hex digits")?;
+                code = code * 16 + d;
+            }
+
+            char::from_u32(code).ok_or("Universal character name isn't a valid character")?
+        },
+
+        _ => return Err("Invalid escape in string literal"),
+    };
+
+    Ok(Escape::Char(escaped))
+}
+
+/// Splits a char or string literal into its encoding and what's between its
+/// quotes
+fn split_literal(literal: &str) -> (Encoding, &str) {
+    let quote = literal.find(['\'', '"']).unwrap();
+    (Encoding::from_prefix(&literal[..quote]), &literal[quote + 1..literal.len() - 1])
 }
 
-pub fn parse_while(condition: Expression, body: Statement) -> Statement {
-    Statement::WhileLoop(Box::new(WhileLoopStatement { condition, body } ))
+/// Gets the code units of what's between a literal's quotes, encoded the way
+/// `encoding` says. Numeric escapes are code units already, so they just need
+/// to fit in one
+fn parse_units(body: &str, encoding: Encoding, target: Target) ->
+    Result<Vec<u32>, &'static str> {
+
+    let mut units = vec![];
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let escape = match c {
+            '\\' => parse_escape(&mut chars)?,
+            c => Escape::Char(c),
+        };
+
+        match escape {
+            Escape::Char(c) => encoding.encode(c, &target, &mut units),
+            Escape::Unit(u) if u <= encoding.max_unit(&target) => units.push(u),
+            Escape::Unit(_) => return Err("Escape sequence out of range for its literal"),
+        }
+    }
+
+    Ok(units)
+}
+
+/// Gets the contents of (adjacent) string literals that the compiler reads
+/// itself, e.g. asm templates, with their escapes replaced
+pub fn parse_string(literals: &[&str]) -> Result<String, &'static str> {
+    let mut value = String::new();
+
+    for literal in literals {
+        if !literal.starts_with('"') {
+            return Err("String literal can't have a prefix here");
+        }
+
+        let mut chars = literal[1..literal.len() - 1].chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+
+            let escaped = match parse_escape(&mut chars)? {
+                Escape::Char(c) => c,
+                Escape::Unit(u) => char::from_u32(u).ok_or("Invalid escape in string literal")?,
+            };
+
+            value.push(escaped);
+        }
+    }
+
+    Ok(value)
+}
+
+/// Joins up adjacent string literals into one, like C does. They can mix
+/// unprefixed literals with prefixed ones, which take on the prefix
+pub fn parse_string_literal(target: Target, literals: &[&str]) ->
+    Result<ExpressionKind, &'static str> {
+
+    let mut encoding = Encoding::Plain;
+
+    for literal in literals {
+        match (encoding, split_literal(literal).0) {
+            (_, Encoding::Plain) => (),
+            (Encoding::Plain, e) => encoding = e,
+            (a, b) if a == b => (),
+            _ => return Err("Can't join string literals with different prefixes"),
+        }
+    }
+
+    // Escapes end with their own literal, so e.g. `"\x12" "3"` is two units
+    let mut units = vec![];
+    for literal in literals {
+        units.extend(parse_units(split_literal(literal).1, encoding, target)?);
+    }
+
+    Ok(ExpressionKind::StringLiteral(Box::new(StringLiteralExpr {
+        units: units.into(),
+        encoding,
+    })))
+}
+
+pub fn parse_char(warnings: &Warnings, target: Target, literal: &str, span: Span) ->
+    Result<ExpressionKind, &'static str> {
+
+    let (encoding, body) = split_literal(literal);
+    let units = parse_units(body, encoding, target)?;
+
+    let value = match (&units[..], encoding) {
+        ([value], _) => *value,
+
+        // Like GCC, plain ones can hold more than one char (including a char
+        // that takes more than one byte, e.g. 'é'). They're packed into an
+        // int, first char highest, keeping only the last 4
+        (units, Encoding::Plain) => {
+            let message = match units.len() {
+                2..=4 => "Multi-character character constant",
+                _ => "Character constant too long for its type",
+            };
+            warnings.borrow_mut().push(Warning::new(message, span));
+
+            units.iter().fold(0, |value: u32, unit| value << 8 | unit)
+        },
+
+        _ => return Err("Character doesn't fit in one code unit of its literal"),
+    };
+
+    let multichar = units.len() > 1;
+    Ok(ExpressionKind::CharLiteral(CharLiteralExpr { value, encoding, multichar }))
+}
+
+/// Declarations in a block are statements too, found where they're declared
+pub fn parse_decl_statement(decl: Declaration) -> Statement {
+    let span = decl.span;
+    Statement::new(StatementKind::Declaration(Box::new(decl)), span)
 }
 
 pub fn parse_unary(expr: Expression) -> Box<UnaryExpr> {
@@ -21,3 +266,717 @@ pub fn parse_unary(expr: Expression) -> Box<UnaryExpr> {
 pub fn parse_binary(first: Expression, second: Expression) -> Box<BinaryExpr> {
     Box::new(BinaryExpr { first, second })
 }
+
+/// Builds a binary operator, which spans from the start of its first operand to
+/// the end of its second
+pub fn parse_operator(
+    kind: fn(Box<BinaryExpr>) -> ExpressionKind,
+    first: Expression,
+    second: Expression
+) -> Expression {
+
+    let span = first.span.to(second.span);
+    Expression::new(kind(parse_binary(first, second)), span)
+}
+
+pub fn parse_compound(op: AssignOp, first: Expression, second: Expression) ->
+    Expression {
+
+    let span = first.span.to(second.span);
+    let kind = ExpressionKind::CompoundAssignment(
+        Box::new(CompoundAssignExpr { op, first, second }));
+
+    Expression::new(kind, span)
+}
+
+pub fn parse_ternary(
+    condition: Expression,
+    true_expr: Expression,
+    false_expr: Expression
+) -> Expression {
+
+    let span = condition.span.to(false_expr.span);
+    let kind = ExpressionKind::Ternary(
+        Box::new(TernaryExpr { condition, true_expr, false_expr }));
+
+    Expression::new(kind, span)
+}
+
+pub fn parse_member(expr: Expression, member: &str) -> Box<MemberExpr> {
+    Box::new(MemberExpr::new(expr, String::from(member)))
+}
+
+/// Gets the id of the tag a struct, union or enum definition declares, or a
+/// new one of its own if it doesn't have a tag
+pub fn parse_tag(typedefs: &RefCell<Typedefs>, tag: Option<&str>) -> String {
+    let mut typedefs = typedefs.borrow_mut();
+
+    match tag {
+        Some(t) => typedefs.declare_tag(t),
+        None => typedefs.anonymous_tag(),
+    }
+}
+
+pub fn parse_struct(
+    warnings: &Warnings,
+    kind: TagKind,
+    attributes: Vec<Attribute>,
+    tag: String,
+    members: Option<Vec<StructMember>>
+) -> Result<Type, &'static str> {
+
+    let mut s = StructType {
+        kind,
+        tag,
+        members: members.map(|m| m.into()),
+        packed: false,
+        aligned: None,
+    };
+
+    // Only the definition decides how it's laid out
+    if s.members.is_some() {
+        apply_struct_attributes(warnings, &mut s, attributes)?;
+    } else {
+        ignore_attributes(warnings, &attributes);
+    }
+
+    Ok(Type::Struct(Box::new(s)))
+}
+
+pub fn parse_enum(
+    tag: String,
+    members: Option<Vec<Enumerator>>
+) -> Type {
+
+    Type::Enum(Box::new(EnumType {
+        tag,
+        members: members.map(|m| m.into()),
+    }))
+}
+
+/// Handles the type of a declaration like `struct foo;`, which declares the
+/// tag in the current scope even if there's one in an outer scope
+pub fn parse_tag_declaration(typedefs: &RefCell<Typedefs>, mut t: Type) -> Type {
+    match &mut t {
+        Type::Struct(s) if s.members.is_none() =>
+            s.tag = typedefs.borrow_mut().redeclare_tag(&s.tag),
+        Type::Enum(e) if e.members.is_none() =>
+            e.tag = typedefs.borrow_mut().redeclare_tag(&e.tag),
+        _ => (),
+    }
+
+    t
+}
+
+/// Turns declarations into typedefs, so that their names mean their types
+/// from here on out
+pub fn parse_typedefs(typedefs: &RefCell<Typedefs>, decls: Vec<Declaration>) ->
+    Result<Vec<Declaration>, &'static str> {
+
+    decls.into_iter()
+        .map(|decl| parse_typedef(typedefs, decl))
+        .collect()
+}
+
+fn parse_typedef(typedefs: &RefCell<Typedefs>, mut decl: Declaration) ->
+    Result<Declaration, &'static str> {
+
+    let name = match &decl.name {
+        Some(n) => n.clone(),
+        None => return Err("Typedef doesn't declare a name"),
+    };
+
+    if decl.value.is_some() {
+        return Err("Typedef can't be initialized");
+    }
+
+    if decl.storage.is_some() {
+        return Err("Typedef can't have a storage class");
+    }
+
+    if !decl.alignments.is_empty() {
+        return Err("Typedef can't have an alignment specifier");
+    }
+
+    typedefs.borrow_mut().insert(&name, decl.type_of.as_reference());
+    decl.typedef = true;
+
+    Ok(decl)
+}
+
+pub fn parse_qualifiers(qualifiers: Vec<Qualifiers>) -> Qualifiers {
+    qualifiers.into_iter().fold(Qualifiers::default(), Qualifiers::union)
+}
+
+/// One word in a declaration's type, e.g. the `unsigned` in `const unsigned x`
+pub enum Specifier {
+    Qualifier(Qualifiers),
+    Basic(BasicSpecifier),
+
+    /// Specifiers that are a whole type on their own, like typedef names or
+    /// `struct S`
+    Named(Type),
+
+    /// Attributes mixed in with the specifiers, e.g. `int __attribute__((unused)) x`
+    Attributes(Vec<Attribute>),
+
+    /// `_Alignas`, which can go anywhere among a declaration's specifiers
+    Alignment(Alignment),
+}
+
+/// Keywords that make up arithmetic types. They can come in any order, so
+/// they get sorted before being matched, with the sign ones first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BasicSpecifier {
+    Signed,
+    Unsigned,
+    Void,
+    Bool,
+    Char,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+}
+
+/// Works out the type named by a list of specifiers, e.g. `unsigned long int`
+/// or `long const long`. Any attributes are ignored, since there's nothing
+/// for them to apply to (e.g. in a cast)
+pub fn parse_specifiers(warnings: &Warnings, specifiers: Vec<Specifier>) ->
+    Result<Type, &'static str> {
+
+    let specifiers = parse_decl_specifiers(warnings, specifiers)?;
+
+    if !specifiers.alignments.is_empty() {
+        return Err("_Alignas can only be used in a declaration");
+    }
+
+    ignore_attributes(warnings, &specifiers.attributes);
+    Ok(specifiers.type_of)
+}
+
+/// What a declaration's specifiers add up to: its type, and anything else
+/// that applies to the whole declaration
+pub struct DeclSpecifiers {
+    pub type_of: Type,
+    pub attributes: Vec<Attribute>,
+    pub alignments: Vec<Alignment>,
+}
+
+/// Like `parse_specifiers`, but also gives the attributes and alignments that
+/// apply to the declaration. Attributes straight after a struct's definition
+/// apply to the struct instead, as in `struct { ... } __attribute__((packed)) s`
+pub fn parse_decl_specifiers(warnings: &Warnings, specifiers: Vec<Specifier>) ->
+    Result<DeclSpecifiers, &'static str> {
+
+    use BasicSpecifier::*;
+
+    let mut qualifiers = Qualifiers::default();
+    let mut named = None;
+    let mut basic = vec![];
+    let mut attributes = vec![];
+    let mut alignments = vec![];
+    let mut after_struct = false;
+
+    for specifier in specifiers {
+        let is_struct = matches!(&specifier,
+            Specifier::Named(Type::Struct(s)) if s.members.is_some());
+
+        match specifier {
+            Specifier::Qualifier(q) => qualifiers = qualifiers.union(q),
+            Specifier::Named(_) if named.is_some() =>
+                return Err("Two or more data types in declaration"),
+            Specifier::Named(t) => named = Some(t),
+            Specifier::Basic(b) => basic.push(b),
+
+            Specifier::Attributes(a) if after_struct => {
+                if let Some(Type::Struct(s)) = &mut named {
+                    apply_struct_attributes(warnings, s, a)?;
+                }
+            },
+            Specifier::Attributes(a) => attributes.extend(a),
+            Specifier::Alignment(a) => alignments.push(a),
+        }
+
+        after_struct = is_struct;
+    }
+
+    basic.sort();
+
+    let t = match (named, basic.as_slice()) {
+        (Some(t), []) => t,
+        (Some(_), _) => return Err("Two or more data types in declaration"),
+        (None, []) => return Err("Missing type specifier"),
+
+        (None, [Void]) => Type::Void,
+        (None, [Bool]) => Type::Bool,
+        (None, [Float]) => Type::Float,
+        (None, [Double]) => Type::Double,
+        (None, [Long, Double]) => return Err("long double isn't supported"),
+
+        // Plain `char` is its own type, separate from `signed char` and
+        // `unsigned char`
+        (None, [Char]) => Type::Char,
+        (None, [Signed, Char]) => Type::Int8,
+        (None, [Unsigned, Char]) => Type::Uint8,
+
+        (None, [Short] | [Short, Int] | [Signed, Short] | [Signed, Short, Int]) =>
+            Type::Int16,
+        (None, [Unsigned, Short] | [Unsigned, Short, Int]) => Type::Uint16,
+
+        (None, [Int] | [Signed] | [Signed, Int]) => Type::Int32,
+        (None, [Unsigned] | [Unsigned, Int]) => Type::Uint32,
+
+        // `long` and `long long` are both 64 bits
+        (None, [Long] | [Int, Long] | [Long, Long] | [Int, Long, Long] |
+            [Signed, Long] | [Signed, Int, Long] | [Signed, Long, Long] |
+            [Signed, Int, Long, Long]) => Type::Int64,
+        (None, [Unsigned, Long] | [Unsigned, Int, Long] | [Unsigned, Long, Long] |
+            [Unsigned, Int, Long, Long]) => Type::Uint64,
+
+        (None, _) => return Err("Invalid combination of type specifiers"),
+    };
+
+    Ok(DeclSpecifiers { type_of: t.qualified(qualifiers), attributes, alignments })
+}
+
+/// An attribute as it was written, e.g. `aligned(16)` or `gnu::unused`
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<AttributeArg>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum AttributeArg {
+    Expression(Expression),
+    String(String),
+}
+
+/// String arguments get parsed as expressions like any other argument, so
+/// pick them back out
+pub fn parse_attribute_args(args: Vec<Expression>) -> Vec<AttributeArg> {
+    args.into_iter()
+        .map(|arg| match &arg.kind {
+            ExpressionKind::StringLiteral(s) if s.encoding == Encoding::Plain => {
+                let bytes: Vec<u8> = s.units.iter().map(|u| *u as u8).collect();
+                AttributeArg::String(String::from_utf8_lossy(&bytes).into_owned())
+            },
+            _ => AttributeArg::Expression(arg),
+        })
+        .collect()
+}
+
+/// Turns attributes into what they ask for, warning about any we don't know.
+/// GCC lets any name be written with underscores around it (`__packed__`),
+/// and C23 lets them be prefixed with `gnu::`
+pub fn parse_attributes(warnings: &Warnings, attributes: Vec<Attribute>) ->
+    Result<Attributes, &'static str> {
+
+    let mut parsed = Attributes::default();
+
+    for Attribute { name, args, span } in attributes {
+        match (attribute_name(&name), args.as_slice()) {
+            ("noreturn", []) => parsed.noreturn = true,
+            ("nodiscard" | "warn_unused_result", []) => parsed.nodiscard = true,
+            ("unused" | "maybe_unused", []) => parsed.unused = true,
+            ("packed", []) => parsed.packed = true,
+            ("weak", []) => parsed.weak = true,
+            ("constructor", []) => parsed.constructor = true,
+            ("destructor", []) => parsed.destructor = true,
+
+            // Without an alignment, it's the most anything could need
+            ("aligned", []) => parsed.aligned =
+                Some(ExpressionKind::IntLiteral(16).into()),
+            ("aligned", [AttributeArg::Expression(e)]) => parsed.aligned = Some(e.clone()),
+
+            ("section", [AttributeArg::String(s)]) => parsed.section = Some(s.clone()),
+
+            ("visibility", [AttributeArg::String(v)]) => parsed.visibility = Some(match v.as_str() {
+                "default" => Visibility::Default,
+                "hidden" => Visibility::Hidden,
+                "protected" => Visibility::Protected,
+                "internal" => Visibility::Internal,
+                _ => return Err("Visibility must be default, hidden, protected or internal"),
+            }),
+
+            ("noreturn" | "nodiscard" | "warn_unused_result" | "unused" |
+                "maybe_unused" | "packed" | "weak" | "constructor" | "destructor" |
+                "aligned" | "section" | "visibility", _) =>
+                return Err("Wrong arguments for attribute"),
+
+            _ => warnings.borrow_mut().push(Warning::new(
+                format!("Ignoring unknown attribute '{}'", name), span)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// The name without any `gnu::` or underscores around it
+fn attribute_name(name: &str) -> &str {
+    let name = name.strip_prefix("gnu::").unwrap_or(name);
+
+    name.strip_prefix("__")
+        .and_then(|n| n.strip_suffix("__"))
+        .unwrap_or(name)
+}
+
+/// For attributes somewhere we don't do anything with them, like on a
+/// parameter or in a cast. The ones that only silence warnings are fine, since
+/// we wouldn't have given one there anyway
+pub fn ignore_attributes(warnings: &Warnings, attributes: &[Attribute]) {
+    for attribute in attributes {
+        if !matches!(attribute_name(&attribute.name), "unused" | "maybe_unused" | "fallthrough") {
+            warnings.borrow_mut().push(Warning::new(
+                format!("Ignoring attribute '{}', which does nothing here", attribute.name),
+                attribute.span));
+        }
+    }
+}
+
+/// The attributes that can go on a struct's definition, like the `packed` in
+/// `struct __attribute__((packed)) s { ... }`
+fn apply_struct_attributes(warnings: &Warnings, s: &mut StructType,
+    attributes: Vec<Attribute>) -> Result<(), &'static str> {
+
+    let attributes = parse_attributes(warnings, attributes)?;
+    s.packed |= attributes.packed;
+
+    if attributes.aligned.is_some() {
+        s.aligned = attributes.aligned;
+    }
+
+    Ok(())
+}
+
+/// A function's parameters, as they'll end up in its type
+pub struct Params {
+    pub args: Vec<(String, Type)>,
+    pub variadic: bool,
+    pub prototyped: bool,
+}
+
+impl Params {
+    /// `()`, which doesn't say what the parameters are
+    pub fn unspecified() -> Self {
+        Params { args: vec![], variadic: false, prototyped: false }
+    }
+
+    pub fn prototype(args: Vec<(String, Type)>, variadic: bool) -> Self {
+        Params { args, variadic, prototyped: true }
+    }
+}
+
+/// One layer of a declarator, e.g. the `*` or `[10]` in `int *a[10]`
+pub enum Derived {
+    Pointer(Qualifiers),
+    Array(Option<Expression>),
+    Function(Params),
+
+    /// Just the names of the parameters, like the `(a, b)` of an old-style
+    /// definition. Their types come from declarations before the body
+    ParamNames(Vec<String>),
+}
+
+/// The part of a declaration after the type specifier, e.g. `(*fp)(int)`.
+/// The name is None for abstract declarators, like those in casts
+#[derive(Default)]
+pub struct Declarator {
+    pub name: Option<String>,
+
+    /// In the order they were parsed, which is the reverse of the order they
+    /// apply to the base type. In `*a[10]` that's `[10]` then `*`, since `a`
+    /// is an array of pointers
+    pub derived: Vec<Derived>,
+
+    /// Where the name is. Empty for abstract declarators
+    pub span: Span,
+
+    /// Any written after it, e.g. `void f(void) __attribute__((noreturn))`
+    pub attributes: Vec<Attribute>,
+}
+
+impl Declarator {
+    pub fn named(name: &str, span: Span) -> Self {
+        Declarator { name: Some(String::from(name)), span, ..Declarator::default() }
+    }
+
+    pub fn with_attributes(mut self, attributes: Vec<Attribute>) -> Self {
+        self.attributes.extend(attributes);
+        self
+    }
+
+    pub fn with(mut self, derived: Derived) -> Self {
+        self.derived.push(derived);
+        self
+    }
+
+    /// Builds the declared type by wrapping the base type in each layer
+    pub fn apply(self, base: Type) -> Result<(Option<String>, Type), &'static str> {
+        let mut t = base;
+
+        for derived in self.derived.into_iter().rev() {
+            t = match derived {
+                Derived::Pointer(q) => Type::Pointer(Box::new(t)).qualified(q),
+
+                Derived::Array(length) => Type::Array(Box::new(ArrayType {
+                    element: t,
+                    length,
+                })),
+
+                Derived::Function(p) => Type::Function(Box::new(FunctionType {
+                    return_type: t,
+                    args: parse_params(p.args)?.into(),
+                    variadic: p.variadic,
+                    prototyped: p.prototyped,
+                })),
+
+                Derived::ParamNames(_) =>
+                    return Err("Parameters need types outside of a definition"),
+            };
+        }
+
+        Ok((self.name, t))
+    }
+}
+
+/// Parameters declared as arrays or functions are really pointers, and
+/// `(void)` means there aren't any
+fn parse_params(params: Vec<(String, Type)>) -> Result<Vec<(String, Type)>, &'static str> {
+    if let [(name, Type::Void)] = params.as_slice() && name.is_empty() {
+        return Ok(vec![]);
+    }
+
+    params.into_iter()
+        .map(|(name, t)| match t {
+            Type::Void => Err("Parameter can't be void"),
+            t => Ok((name, t.decay())),
+        })
+        .collect()
+}
+
+pub fn parse_param(warnings: &Warnings, attributes: Vec<Attribute>, t: Type,
+    declarator: Declarator) -> Result<(String, Type), &'static str> {
+
+    ignore_attributes(warnings, &attributes);
+    ignore_attributes(warnings, &declarator.attributes);
+
+    let (name, t) = declarator.apply(t)?;
+    Ok((name.unwrap_or_default(), t))
+}
+
+/// A struct member's declarator and bit-field width. Only bit-fields can go
+/// without a declarator
+pub type MemberDeclarator = (Option<Declarator>, Option<Expression>);
+
+/// Splits e.g. `unsigned a : 1, b : 3;` into a member for each declarator
+pub fn parse_member_decl(
+    warnings: &Warnings,
+    attributes: Vec<Attribute>,
+    t: Type,
+    alignments: Vec<Alignment>,
+    declarators: Vec<MemberDeclarator>
+) -> Result<Vec<StructMember>, &'static str> {
+
+    let shared = parse_member_attributes(warnings, attributes)?;
+
+    // Only the first one gets to define any structs, the rest just use them
+    let rest = t.as_reference();
+    let mut base = Some(t);
+
+    declarators.into_iter()
+        .map(|(declarator, width)| {
+            let t = base.take().unwrap_or_else(|| rest.clone());
+
+            let mut attributes = shared.clone();
+            let (name, type_of) = match declarator {
+                Some(mut d) => {
+                    let own = std::mem::take(&mut d.attributes);
+                    attributes.merge(&parse_member_attributes(warnings, own)?);
+                    d.apply(t)?
+                },
+                None => (None, t),
+            };
+
+            if name.is_none() && width.is_none() {
+                return Err("Struct member needs a name");
+            }
+
+            Ok(StructMember {
+                name,
+                type_of,
+                width,
+                alignments: alignments.clone(),
+                attributes,
+            })
+        })
+        .collect()
+}
+
+/// Only `packed` and `aligned` do anything for a struct member
+fn parse_member_attributes(warnings: &Warnings, attributes: Vec<Attribute>) ->
+    Result<Attributes, &'static str> {
+
+    let (layout, other): (Vec<_>, Vec<_>) = attributes.into_iter()
+        .partition(|a| matches!(attribute_name(&a.name), "packed" | "aligned"));
+
+    ignore_attributes(warnings, &other);
+    parse_attributes(warnings, layout)
+}
+
+/// Attributes before the declarator apply to everything it declares, and
+/// ones after it just to that name
+fn parse_declaration(
+    warnings: &Warnings,
+    storage: Option<StorageClass>,
+    alignments: Vec<Alignment>,
+    t: Type,
+    mut attributes: Attributes,
+    mut declarator: Declarator,
+    value: Option<DeclarationValue>
+) -> Result<Declaration, &'static str> {
+
+    attributes.merge(&parse_attributes(warnings, std::mem::take(&mut declarator.attributes))?);
+
+    let span = declarator.span;
+    let (name, type_of) = declarator.apply(t)?;
+
+    // A string literal initializing an array is the same as one in braces,
+    // e.g. `char s[] = "hi";` means `char s[] = { "hi" };`
+    let value = match value {
+        Some(DeclarationValue::Variable(e)) if matches!(type_of, Type::Array(_))
+            && matches!(e.kind, ExpressionKind::StringLiteral(_)) => {
+
+            let item = InitItem { designators: vec![], value: Initializer::Expression(e) };
+            Some(DeclarationValue::List(Box::new([item])))
+        },
+        value => value,
+    };
+
+    Ok(Declaration { name, type_of, storage, alignments, attributes, typedef: false, value, span })
+}
+
+/// Splits e.g. `int a, *b = c;` into a declaration for each name
+pub fn parse_declarations(
+    warnings: &Warnings,
+    storage: Option<StorageClass>,
+    alignments: Vec<Alignment>,
+    t: Type,
+    attributes: Vec<Attribute>,
+    declarators: Vec<(Declarator, Option<DeclarationValue>)>
+) -> Result<Vec<Declaration>, &'static str> {
+
+    // Only the first one gets to define any structs, the rest just use them
+    let rest = t.as_reference();
+    let mut base = Some(t);
+    let attributes = parse_attributes(warnings, attributes)?;
+
+    declarators.into_iter()
+        .map(|(declarator, value)| {
+            let t = base.take().unwrap_or_else(|| rest.clone());
+            parse_declaration(warnings, storage, alignments.clone(), t, attributes.clone(),
+                declarator, value)
+        })
+        .collect()
+}
+
+pub fn parse_function(
+    warnings: &Warnings,
+    mode: LanguageMode,
+    (storage, attributes): (Option<StorageClass>, Vec<Attribute>),
+    specifiers: DeclSpecifiers,
+    mut declarator: Declarator,
+    param_decls: Vec<Declaration>,
+    body: Box<[Statement]>
+) -> Result<Declaration, &'static str> {
+
+    // e.g. `int (*f)(void) { ... }` declares a pointer, not a function
+    let params = match declarator.derived.first_mut() {
+        Some(Derived::Function(_)) if param_decls.is_empty() => None,
+        Some(Derived::Function(_)) =>
+            return Err("Only old-style definitions can declare parameters before the body"),
+
+        Some(Derived::ParamNames(_)) if mode != LanguageMode::Legacy =>
+            return Err("Old-style definitions need legacy mode"),
+        Some(Derived::ParamNames(names)) =>
+            Some(parse_old_params(std::mem::take(names), param_decls)?),
+
+        _ => return Err("Only functions can have a body"),
+    };
+
+    if let Some(params) = params {
+        declarator.derived[0] = Derived::Function(params);
+    }
+
+    let attributes = parse_attributes(warnings, [attributes, specifiers.attributes].concat())?;
+    parse_declaration(warnings, storage, specifiers.alignments, specifiers.type_of, attributes, declarator,
+        Some(DeclarationValue::Function(body)))
+}
+
+/// Joins up the message's string literals, like C does with any adjacent
+/// ones, and decodes their escapes
+pub fn parse_static_assert(condition: Expression, message: Option<Vec<&str>>,
+    span: Span) -> Result<Declaration, &'static str> {
+
+    let message = message.map(|parts| parse_string(&parts)).transpose()?;
+
+    Ok(Declaration {
+        name: None,
+        type_of: Type::Void,
+        storage: None,
+        alignments: vec![],
+        attributes: Attributes::default(),
+        typedef: false,
+        value: Some(DeclarationValue::StaticAssert(StaticAssert { condition, message })),
+        span,
+    })
+}
+
+/// Gives the parameters of an old-style definition, like `int f(a, b) char *b;`,
+/// their types. Any that don't get declared are ints
+fn parse_old_params(names: Vec<String>, decls: Vec<Declaration>) ->
+    Result<Params, &'static str> {
+
+    let mut args: Vec<(String, Type)> = vec![];
+
+    for name in names {
+        if args.iter().any(|(n, _)| *n == name) {
+            return Err("Parameter named twice");
+        }
+
+        args.push((name, Type::Int32));
+    }
+
+    let mut declared = HashSet::new();
+
+    for decl in decls {
+        if decl.value.is_some() {
+            return Err("Parameters can't be initialized");
+        }
+
+        if !matches!(decl.storage, None | Some(StorageClass::Register)) {
+            return Err("Parameters can only have the register storage class");
+        }
+
+        if !decl.alignments.is_empty() {
+            return Err("Parameters can't be aligned");
+        }
+
+        let name = decl.name.ok_or("Parameter declaration needs a name")?;
+
+        let arg = args.iter_mut()
+            .find(|(n, _)| *n == name)
+            .ok_or("Declared a parameter that isn't in the list")?;
+
+        if !declared.insert(name) {
+            return Err("Parameter declared twice");
+        }
+
+        arg.1 = decl.type_of;
+    }
+
+    Ok(Params { args, variadic: false, prototyped: false })
+}
@@ -0,0 +1,815 @@
+// A hand-written lexer, since C can't be lexed without knowing which names
+// are typedefs (e.g. `foo * bar;` is a declaration if `foo` is a type, but a
+// multiplication otherwise). We keep track of that as we go, by following
+// declarations and scopes closely enough to spot where typedef names are
+// declared and shadowed, and record them in the parser's `Typedefs`
+
+use std::{cell::RefCell, fmt};
+
+use crate::ast::Span;
+
+use super::{typedefs::{Scope, Typedefs}, LanguageMode, UserError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tok<'input> {
+    Identifier(&'input str),
+    TypeName(&'input str), // An identifier that's currently a typedef name
+    IntLiteral(&'input str),
+    CharLiteral(&'input str),
+    StringLiteral(&'input str),
+    Keyword(&'static str),
+    Punct(&'static str),
+}
+
+impl fmt::Display for Tok<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tok::Identifier(s) | Tok::TypeName(s) | Tok::IntLiteral(s) |
+                Tok::CharLiteral(s) | Tok::StringLiteral(s) => f.write_str(s),
+            Tok::Keyword(s) | Tok::Punct(s) => f.write_str(s),
+        }
+    }
+}
+
+pub type Spanned<'input> = (usize, Tok<'input>, usize);
+
+const KEYWORDS: &[&str] = &[
+    "_Alignas", "_Alignof", "_Bool", "_Generic", "_Static_assert", "__asm",
+    "__asm__", "__attribute", "__attribute__", "__builtin_va_arg",
+    "__builtin_va_copy", "__builtin_va_end", "__builtin_va_list",
+    "__builtin_va_start", "__volatile__", "asm", "auto", "break", "case", "char", "const",
+    "default", "double", "else", "enum", "extern", "float", "goto", "if", "int",
+    "long", "register", "restrict", "return", "short", "signed", "sizeof",
+    "static", "struct", "switch", "typedef", "union",
+    "unsigned", "void", "volatile", "while",
+];
+
+/// Keywords that are only keywords in C23, and could be anything before
+const C23_KEYWORDS: &[&str] = &["bool", "static_assert"];
+
+/// Longest first, so that e.g. `<<` wins over `<`
+const PUNCTUATION: &[&str] = &[
+    "...", "[[", "<<=", ">>=", "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    ";", "(", ")", "{", "}", "[", "]", ",", "=", ".", "&", "*", "-", "~",
+    "!", "/", "%", "+", "<", ">", "^", "|", "?", ":",
+];
+
+/// Keywords that can start a declaration
+const SPECIFIERS: &[&str] = &[
+    "typedef", "extern", "static", "auto", "register", "_Alignas", "void",
+    "_Bool", "bool", "char", "short", "int", "long", "float", "double",
+    "signed", "unsigned", "struct", "union", "enum", "const", "volatile",
+    "restrict", "__builtin_va_list", "__attribute", "__attribute__",
+];
+
+/// Specifiers that say what the type is (as opposed to e.g. `extern`). Once
+/// we've seen one, a typedef name can only be a declarator being redeclared
+const TYPE_SPECIFIERS: &[&str] = &[
+    "void", "_Bool", "bool", "char", "short", "int", "long", "float",
+    "double", "signed", "unsigned", "struct", "union", "enum",
+    "__builtin_va_list",
+];
+
+/// Where we are within a statement or declaration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// At the start of a statement or declaration
+    Start,
+
+    /// In a declaration's specifiers, e.g. `typedef unsigned int`
+    Specifiers { typedef: bool, seen_type: bool },
+
+    /// In a declarator, e.g. `*(*name)`. `named` once we've seen its name, and
+    /// `depth` is how many of its parentheses we're inside
+    Declarator { typedef: bool, named: bool, depth: usize },
+
+    /// In an initializer or bit-field width, which ends with the declarator
+    Initializer { typedef: bool },
+
+    /// In an expression statement (or some other non-declaration)
+    Expression,
+
+    /// After `if`, `while` or `switch`, whose condition is followed by a new
+    /// statement rather than the rest of an expression
+    Condition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    /// The file or a block, both of which have their own scope
+    Block,
+
+    /// A struct or union's members, which aren't in any scope
+    Members,
+
+    /// An enum's constants, which go in the enclosing scope
+    Enumerators,
+
+    /// A function's parameters, which go in the scope of its body
+    Params,
+
+    /// Brackets with no declarations in them, e.g. an array size
+    Nested,
+}
+
+/// A set of brackets we're inside
+#[derive(Debug)]
+struct Frame<'input> {
+    kind: FrameKind,
+    closer: &'static str,
+    state: State,
+
+    /// The scope of the parameters of the function being declared here, if any
+    params: Option<Scope>,
+
+    /// What a `{` would open here, if it'd be a struct or enum body
+    tag_body: Option<FrameKind>,
+
+    /// For an attribute's brackets, what to go back to once they close
+    resume: Option<Resume<'input>>,
+}
+
+/// The token before an attribute, and what a `{` would have opened after it.
+/// Attributes can go almost anywhere, e.g. `struct __attribute__((packed)) S`,
+/// so we carry on afterwards as if they weren't there
+type Resume<'input> = (Option<Tok<'input>>, Option<FrameKind>);
+
+impl<'input> Frame<'input> {
+    fn new(kind: FrameKind, closer: &'static str) -> Self {
+        Self {
+            kind,
+            closer,
+            state: State::Start,
+            params: None,
+            tag_body: None,
+            resume: None,
+        }
+    }
+
+    /// The brackets of an attribute, which don't have any declarations in them
+    fn attribute(closer: &'static str, resume: Resume<'input>) -> Self {
+        Self { resume: Some(resume), ..Self::new(FrameKind::Nested, closer) }
+    }
+}
+
+pub struct Lexer<'input, 't> {
+    input: &'input str,
+    pos: usize,
+    prev: Option<Tok<'input>>,
+
+    /// Innermost last. The first is the file itself
+    frames: Vec<Frame<'input>>,
+
+    /// Where the names declared in each scope go. Every block and parameter
+    /// list frame has a scope there, which we enter and exit along with it
+    typedefs: &'t RefCell<Typedefs>,
+
+    /// Decides which words are keywords
+    mode: LanguageMode,
+
+    /// Set by `__attribute__`, for its brackets to resume from
+    attribute: Option<Resume<'input>>,
+
+    /// Set when an attribute's brackets close
+    resume: Option<Resume<'input>>,
+}
+
+impl<'input, 't> Lexer<'input, 't> {
+    pub fn new(input: &'input str, typedefs: &'t RefCell<Typedefs>,
+        mode: LanguageMode) -> Self {
+
+        Self {
+            input,
+            pos: 0,
+            prev: None,
+            frames: vec![Frame::new(FrameKind::Block, "")],
+            typedefs,
+            mode,
+            attribute: None,
+            resume: None,
+        }
+    }
+
+    fn top(&mut self) -> &mut Frame<'input> {
+        // Never empty, since we never pop the file's frame
+        self.frames.last_mut().unwrap()
+    }
+
+    /// Records that a name has been declared, in whatever scope it belongs to.
+    /// Enum constants go in the enclosing one, which is the current scope
+    /// since their frame doesn't have one
+    fn declare(&mut self, name: &str, typedef: bool) {
+        if matches!(self.top().kind, FrameKind::Members | FrameKind::Nested) {
+            return;
+        }
+
+        self.typedefs.borrow_mut().declare(name, typedef);
+    }
+
+    fn push(&mut self, frame: Frame<'input>) {
+        if matches!(frame.kind, FrameKind::Block | FrameKind::Params) {
+            self.typedefs.borrow_mut().enter_scope();
+        }
+
+        self.frames.push(frame);
+    }
+
+    fn track_identifier(&mut self, name: &'input str, tag_body: Option<FrameKind>)
+        -> Tok<'input> {
+
+        match self.prev {
+            // Members and labels are never types
+            Some(Tok::Punct("." | "->")) | Some(Tok::Keyword("goto")) =>
+                return Tok::Identifier(name),
+
+            // Neither are tags, though they can still have a body after them
+            Some(Tok::Keyword("struct" | "union" | "enum")) => {
+                self.top().tag_body = tag_body;
+                return Tok::Identifier(name);
+            },
+
+            _ => (),
+        }
+
+        let is_type = self.typedefs.borrow().is_typedef(name);
+        let lookup = if is_type {
+            Tok::TypeName(name)
+        } else {
+            Tok::Identifier(name)
+        };
+
+        let frame = self.top();
+
+        match (frame.kind, frame.state) {
+            (FrameKind::Nested, _) => lookup,
+
+            (FrameKind::Enumerators, State::Start) => {
+                frame.state = State::Initializer { typedef: false };
+                self.declare(name, false);
+                Tok::Identifier(name)
+            },
+
+            (FrameKind::Enumerators, _) => lookup,
+
+            (_, State::Start) => {
+                frame.state = if is_type {
+                    State::Specifiers { typedef: false, seen_type: true }
+                } else {
+                    State::Expression
+                };
+
+                lookup
+            },
+
+            (_, State::Specifiers { typedef, seen_type: false }) if is_type => {
+                frame.state = State::Specifiers { typedef, seen_type: true };
+                lookup
+            },
+
+            (_, State::Specifiers { typedef, .. }) => {
+                frame.state = State::Declarator { typedef, named: true, depth: 0 };
+                self.declare(name, typedef);
+                Tok::Identifier(name)
+            },
+
+            (_, State::Declarator { typedef, named: false, depth }) => {
+                frame.state = State::Declarator { typedef, named: true, depth };
+                self.declare(name, typedef);
+                Tok::Identifier(name)
+            },
+
+            _ => lookup,
+        }
+    }
+
+    fn track_keyword(&mut self, keyword: &'static str) {
+        let frame = self.top();
+
+        if matches!(frame.kind, FrameKind::Nested | FrameKind::Enumerators) {
+            return;
+        }
+
+        frame.tag_body = match keyword {
+            "struct" | "union" => Some(FrameKind::Members),
+            "enum" => Some(FrameKind::Enumerators),
+            _ => None,
+        };
+
+        let is_specifier = SPECIFIERS.contains(&keyword);
+        let is_type = TYPE_SPECIFIERS.contains(&keyword);
+        let is_typedef = keyword == "typedef";
+
+        frame.state = match frame.state {
+            State::Start if is_specifier => State::Specifiers {
+                typedef: is_typedef,
+                seen_type: is_type,
+            },
+
+            State::Specifiers { typedef, seen_type } if is_specifier =>
+                State::Specifiers {
+                    typedef: typedef || is_typedef,
+                    seen_type: seen_type || is_type,
+                },
+
+            State::Start if matches!(keyword, "if" | "while" | "switch") =>
+                State::Condition,
+
+            // e.g. `return`, `else`
+            State::Start => State::Expression,
+
+            state => state,
+        };
+    }
+
+    fn open_brace(&mut self, tag_body: Option<FrameKind>) {
+        let after_paren = self.prev == Some(Tok::Punct("("));
+        let after_group = self.prev == Some(Tok::Punct(")"));
+        let frame = self.top();
+
+        let kind = match (tag_body, frame.kind, frame.state) {
+            (Some(kind), _, _) => kind,
+
+            // A statement expression, e.g. `({ int x = f(); x * 2; })`
+            _ if after_paren => FrameKind::Block,
+
+            (_, FrameKind::Nested, _) | (_, _, State::Initializer { .. }) =>
+                FrameKind::Nested,
+
+            // A compound literal's list, e.g. `(int[]){ 1, 2 }`
+            (_, _, State::Expression) if after_group => FrameKind::Nested,
+
+            _ => FrameKind::Block,
+        };
+
+        if kind == FrameKind::Block {
+            frame.state = State::Start;
+
+            // A function's body gets its parameters, and ends its definition
+            if let Some(params) = frame.params.take() {
+                self.typedefs.borrow_mut().reenter_scope(params);
+                self.frames.push(Frame::new(kind, "}"));
+                return;
+            }
+        }
+
+        self.push(Frame::new(kind, "}"));
+    }
+
+    fn open_paren(&mut self, opener: &'static str) {
+        let closer = if opener == "(" { ")" } else { "]" };
+        let after_group = self.prev == Some(Tok::Punct(")"));
+        let after_alignas = self.prev == Some(Tok::Keyword("_Alignas"));
+
+        // Attributes don't change what we're in the middle of
+        if let Some(resume) = self.attribute.take() {
+            self.push(Frame::attribute(closer, resume));
+            return;
+        }
+
+        let frame = self.top();
+
+        match (frame.kind, frame.state, opener) {
+            (FrameKind::Nested, _, _) => (),
+
+            (_, State::Declarator { named: true, .. }, "(") => {
+                self.push(Frame::new(FrameKind::Params, closer));
+                return;
+            },
+
+            // Parameters of an abstract declarator, e.g. `int (*)(int x)`
+            (_, State::Declarator { depth: 0, .. }, "(") if after_group => {
+                self.push(Frame::new(FrameKind::Params, closer));
+                return;
+            },
+
+            // Just grouping, e.g. `int (*f)(void)`
+            (_, State::Declarator { typedef, named: false, depth }, "(") => {
+                frame.state = State::Declarator { typedef, named: false, depth: depth + 1 };
+                return;
+            },
+
+            // Part of the specifiers rather than a declarator, e.g. the
+            // `(long)` in `_Alignas(long) int x`
+            (_, State::Specifiers { .. }, "(") if after_alignas => (),
+
+            (_, State::Specifiers { typedef, .. }, "(") => {
+                frame.state = State::Declarator { typedef, named: false, depth: 1 };
+                return;
+            },
+
+            (_, State::Start, _) => frame.state = State::Expression,
+
+            _ => (),
+        }
+
+        self.push(Frame::new(FrameKind::Nested, closer));
+    }
+
+    fn close(&mut self, closer: &'static str) {
+        let frame = self.top();
+
+        if let State::Declarator { typedef, named, depth } = frame.state
+            && closer == ")" && depth > 0 {
+
+            frame.state = State::Declarator { typedef, named, depth: depth - 1 };
+            return;
+        }
+
+        // A `}` also closes anything left open inside its block, which is
+        // where the parser picks back up after an error. Other mismatched
+        // brackets are the parser's problem
+        if closer == "}" {
+            while self.top().closer != "}" && self.frames.iter().any(|f| f.closer == "}") {
+                self.abandon();
+            }
+        }
+
+        let frame = self.top();
+        if frame.closer != closer || self.frames.len() == 1 {
+            return;
+        }
+
+        let popped = self.frames.pop().unwrap();
+        self.resume = popped.resume;
+
+        let scope = match popped.kind {
+            FrameKind::Block | FrameKind::Params =>
+                Some(self.typedefs.borrow_mut().exit_scope()),
+            _ => None,
+        };
+
+        // The condition of an `if` or loop is followed by its body
+        if self.top().state == State::Condition {
+            self.top().state = State::Start;
+        }
+
+        // The first parameter list in a declarator is the function's
+        if popped.kind == FrameKind::Params && let Some(scope) = scope {
+            self.top().params.get_or_insert(scope);
+        }
+    }
+
+    /// Drops a frame that was never closed, along with its scope
+    fn abandon(&mut self) {
+        let popped = self.frames.pop().unwrap();
+
+        if matches!(popped.kind, FrameKind::Block | FrameKind::Params) {
+            self.typedefs.borrow_mut().exit_scope();
+        }
+    }
+
+    fn track_punct(&mut self, punct: &'static str, tag_body: Option<FrameKind>) {
+        // A `;` can only end a declaration or statement, which are always
+        // directly in a block or struct, so we must have missed whatever
+        // closes any brackets we're in. The parser recovers at the same `;`
+        if punct == ";" {
+            while self.frames.len() > 1
+                && !matches!(self.top().kind, FrameKind::Block | FrameKind::Members) {
+
+                self.abandon();
+            }
+        }
+
+        match punct {
+            "{" => return self.open_brace(tag_body),
+            "(" | "[" => return self.open_paren(punct),
+
+            // C23 attributes, which end with two separate `]`s
+            "[[" => {
+                self.push(Frame::attribute("]", (self.prev, tag_body)));
+                self.push(Frame::new(FrameKind::Nested, "]"));
+                return;
+            },
+
+            "}" | ")" | "]" => return self.close(punct),
+            _ => (),
+        }
+
+        let frame = self.top();
+
+        if frame.kind == FrameKind::Nested {
+            return;
+        }
+
+        frame.state = match (punct, frame.kind, frame.state) {
+            (";", _, _) => {
+                frame.params = None;
+                State::Start
+            },
+
+            (",", FrameKind::Enumerators | FrameKind::Params, _) => State::Start,
+
+            (",", _, State::Declarator { typedef, .. } | State::Initializer { typedef }) => {
+                frame.params = None;
+                State::Declarator { typedef, named: false, depth: 0 }
+            },
+
+            ("=", _, State::Declarator { typedef, named: true, .. }) =>
+                State::Initializer { typedef },
+
+            // Bit-field widths
+            (":", FrameKind::Members, State::Declarator { typedef, .. } |
+                State::Specifiers { typedef, .. }) => State::Initializer { typedef },
+
+            // After a label or case, we're back at the start of a statement
+            (":", _, State::Expression) => State::Start,
+
+            ("*", _, State::Specifiers { typedef, .. }) =>
+                State::Declarator { typedef, named: false, depth: 0 },
+
+            (_, _, State::Start) => State::Expression,
+
+            (_, _, state) => state,
+        };
+    }
+
+    /// Works out how a token affects what we're in the middle of, and turns
+    /// identifiers into type names where they should be
+    fn track(&mut self, tok: Tok<'input>) -> Tok<'input> {
+        let tag_body = self.top().tag_body.take();
+
+        let tok = match tok {
+            Tok::Identifier(name) => self.track_identifier(name, tag_body),
+            Tok::Keyword("__attribute__" | "__attribute") => {
+                self.attribute = Some((self.prev, tag_body));
+                tok
+            },
+            Tok::Keyword(keyword) => {
+                self.track_keyword(keyword);
+                tok
+            },
+            Tok::Punct(punct) => {
+                self.track_punct(punct, tag_body);
+                tok
+            },
+            _ => {
+                let frame = self.top();
+                if frame.kind != FrameKind::Nested && frame.state == State::Start {
+                    frame.state = State::Expression;
+                }
+                tok
+            },
+        };
+
+        match self.resume.take() {
+            Some((prev, tag_body)) => {
+                self.prev = prev;
+                self.top().tag_body = tag_body;
+            },
+            None => self.prev = Some(tok),
+        }
+
+        tok
+    }
+
+    /// Gets the next token, without caring what it means
+    fn next_raw(&mut self) -> Option<Result<Spanned<'input>, UserError>> {
+        let rest = &self.input[self.pos..];
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+
+        let start = self.pos;
+        let c = trimmed.chars().next()?;
+
+        let (tok, len) = if let Some(prefix) = literal_prefix_len(trimmed) {
+            let literal = &trimmed[prefix..];
+
+            let len = match literal.starts_with('\'') {
+                true => char_literal_len(literal).ok_or("Invalid char literal"),
+                false => string_literal_len(literal).ok_or("Unterminated string literal"),
+            };
+
+            match len {
+                Ok(len) if literal.starts_with('\'') =>
+                    (Tok::CharLiteral(&trimmed[..prefix + len]), prefix + len),
+                Ok(len) => (Tok::StringLiteral(&trimmed[..prefix + len]), prefix + len),
+                Err(e) => return Some(Err((Span::new(start, start + 1), e))),
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = trimmed
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(trimmed.len());
+            let word = &trimmed[..len];
+
+            let c23_keywords = match self.mode {
+                LanguageMode::C23 => C23_KEYWORDS,
+                _ => &[],
+            };
+
+            match KEYWORDS.iter().chain(c23_keywords).find(|k| **k == word) {
+                Some(keyword) => (Tok::Keyword(keyword), len),
+                None => (Tok::Identifier(word), len),
+            }
+        } else if c.is_ascii_digit() {
+            let len = trimmed
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(trimmed.len());
+
+            (Tok::IntLiteral(&trimmed[..len]), len)
+        } else {
+            match PUNCTUATION.iter().find(|p| trimmed.starts_with(**p)) {
+                Some(punct) => (Tok::Punct(punct), punct.len()),
+                None => return Some(Err((Span::new(start, start + 1), "Invalid token"))),
+            }
+        };
+
+        self.pos += len;
+        Some(Ok((start, tok, self.pos)))
+    }
+}
+
+impl<'input> Iterator for Lexer<'input, '_> {
+    type Item = Result<Spanned<'input>, UserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_raw()?.map(|(start, tok, end)| {
+            (start, self.track(tok), end)
+        }))
+    }
+}
+
+/// If `s` starts with a char or string literal, gets the length of its
+/// encoding prefix (`L`, `u8`, `u` or `U`, or none at all)
+fn literal_prefix_len(s: &str) -> Option<usize> {
+    ["", "L", "u8", "u", "U"].iter()
+        .find(|p| s.strip_prefix(**p).is_some_and(|rest| rest.starts_with(['\'', '"'])))
+        .map(|p| p.len())
+}
+
+/// Gets the length of the char literal at the start of `s` (e.g. `'a'`,
+/// `'\n'` or GCC's multi-character `'ab'`), without any prefix, or None if
+/// it's not a valid one
+fn char_literal_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+
+    // It can't be empty
+    if *bytes.get(i)? == b'\'' {
+        return None;
+    }
+
+    while *bytes.get(i)? != b'\'' {
+        match bytes[i] {
+            b'\\' => {
+                i += 1;
+
+                let escape_len = match *bytes.get(i)? {
+                    b'x' => 1 + bytes[i + 1..].iter()
+                        .take_while(|b| b.is_ascii_hexdigit())
+                        .count(),
+                    b'0'..=b'7' => bytes[i..].iter()
+                        .take(3)
+                        .take_while(|b| (b'0'..=b'7').contains(*b))
+                        .count(),
+                    // Universal character names have an exact number of digits
+                    b'u' | b'U' => {
+                        let digits = if bytes[i] == b'u' { 4 } else { 8 };
+                        let hex = bytes[i + 1..].iter()
+                            .take(digits)
+                            .take_while(|b| b.is_ascii_hexdigit())
+                            .count();
+
+                        if hex != digits {
+                            return None;
+                        }
+
+                        1 + digits
+                    },
+                    c if b"abfnrtv\\'\"".contains(&c) => 1,
+                    _ => return None,
+                };
+
+                // `\x` needs at least one digit
+                if escape_len == 1 && bytes[i] == b'x' {
+                    return None;
+                }
+
+                i += escape_len;
+            },
+
+            b'\n' => return None,
+
+            _ => i += s[i..].chars().next()?.len_utf8(),
+        }
+    }
+
+    Some(i + 1)
+}
+
+/// Gets the length of the string literal at the start of `s`, quotes and all,
+/// or None if it isn't closed before the end of the line
+fn string_literal_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+
+    loop {
+        match *bytes.get(i)? {
+            b'"' => return Some(i + 1),
+            b'\\' => i += 2,
+            b'\n' => return None,
+            _ => i += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::Context, error::CompilerError, fs::read_file, parser::parse};
+
+    use super::*;
+
+    fn read(ending: &str) -> Result<String, CompilerError> {
+        let mut p = PathBuf::from("tests/files/unit/lexer/");
+        p.push(ending);
+
+        read_file(&p)
+    }
+
+    /// Whether each use of `name` in the file was lexed as a type name
+    fn type_name_uses(input: &str, name: &str) -> Vec<bool> {
+        let typedefs = RefCell::new(Typedefs::new());
+
+        Lexer::new(input, &typedefs, LanguageMode::default())
+            .filter_map(|t| match t.unwrap().1 {
+                Tok::Identifier(n) if n == name => Some(false),
+                Tok::TypeName(n) if n == name => Some(true),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn typedefs() -> Result<(), CompilerError> {
+        let input = read("typedefs.c")?;
+        parse(&input)?.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn shadowing() -> Result<(), CompilerError> {
+        let input = read("shadowing.c")?;
+
+        assert_eq!(type_name_uses(&input, "T"),
+            [false, true, true, false, false, true, true, false, false, true]);
+
+        parse(&input)?.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn braces() -> Result<(), CompilerError> {
+        let input = read("braces.c")?;
+
+        // Blocks after a condition and statement expressions have their own
+        // scope, while a compound literal's list is just part of an expression
+        assert_eq!(type_name_uses(&input, "T"),
+            [false, true, true, true, false, false, true, false, false, true, true]);
+
+        parse(&input)?.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn scopes() -> Result<(), CompilerError> {
+        let input = read("scopes.c")?;
+
+        assert_eq!(type_name_uses(&input, "T"),
+            [false, false, true, true, true, false, false, true]);
+
+        // The parser has to agree on which `T` each one is, or the types
+        // won't match up
+        parse(&input)?.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(char_literal_len("'a'"), Some(3));
+        assert_eq!(char_literal_len("'\\n'"), Some(4));
+        assert_eq!(char_literal_len("'\\x41'"), Some(6));
+        assert_eq!(char_literal_len("'\\101'"), Some(6));
+        assert_eq!(char_literal_len("'\\x'"), None);
+        assert_eq!(char_literal_len("''"), None);
+        assert_eq!(char_literal_len("'\\u00e9'"), Some(8));
+        assert_eq!(char_literal_len("'\\U0001F600'"), Some(12));
+        assert_eq!(char_literal_len("'\\u00e'"), None);
+    }
+
+    #[test]
+    fn literal_prefixes() {
+        assert_eq!(literal_prefix_len("L'a'"), Some(1));
+        assert_eq!(literal_prefix_len("u8\"a\""), Some(2));
+        assert_eq!(literal_prefix_len("u\"a\""), Some(1));
+        assert_eq!(literal_prefix_len("U'a'"), Some(1));
+        assert_eq!(literal_prefix_len("\"a\""), Some(0));
+        assert_eq!(literal_prefix_len("u8"), None);
+        assert_eq!(literal_prefix_len("Lx'a'"), None);
+    }
+
+    #[test]
+    fn string_literals() {
+        assert_eq!(string_literal_len("\"hi\" x"), Some(4));
+        assert_eq!(string_literal_len("\"say \\\"hi\\\"\""), Some(12));
+        assert_eq!(string_literal_len("\"\""), Some(2));
+        assert_eq!(string_literal_len("\"oops\n\""), None);
+        assert_eq!(string_literal_len("\"oops"), None);
+    }
+}
@@ -1,12 +1,326 @@
-use lalrpop_util::lalrpop_mod;
+use std::cell::RefCell;
 
-use crate::{ast::TranslationUnit, error::CompilerError};
+use lalrpop_util::{lalrpop_mod, ErrorRecovery, ParseError};
+
+use crate::{ast::{Span, Target, TranslationUnit}, error::{syntax_error, CompilerError, Warning}};
+
+use self::{lexer::{Lexer, Tok}, typedefs::Typedefs};
 
 pub mod helpers;
+pub mod lexer;
+pub mod typedefs;
+
+// The generated parser takes the recovered errors as a `&mut Vec`, since it
+// pushes to them
+lalrpop_mod!(#[allow(clippy::ptr_arg)] grammar, "/parser/grammar.rs");
+
+/// Which flavour of C to accept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LanguageMode {
+    #[default]
+    Standard,
+
+    /// Also accepts old-style (K&R) function definitions
+    Legacy,
+
+    /// Also accepts C23's new keywords, e.g. `bool`
+    C23,
+}
+
+/// An error from the lexer or one of the grammar's own checks, along with the
+/// part of the input it's about
+pub type UserError = (Span, &'static str);
+
+/// Where the grammar's helpers put their warnings, e.g. about attributes they
+/// don't know. They end up in the `TranslationUnit`
+pub type Warnings = RefCell<Vec<Warning>>;
 
-lalrpop_mod!(grammar, "/parser/grammar.rs");
+/// How many tokens the parser has to get through after recovering from an
+/// error before we report any more, like Bison does. Until then, they're most
+/// likely knock-on effects of the first
+const RECOVERY_TOKENS: usize = 3;
 
 pub fn parse(input: &str) -> Result<TranslationUnit, CompilerError> {
-    let output = grammar::TransalationUnitParser::new().parse(input)?;
-    Ok(output)
+    parse_with_mode(input, LanguageMode::default())
+}
+
+pub fn parse_with_mode(input: &str, mode: LanguageMode) ->
+    Result<TranslationUnit, CompilerError> {
+
+    parse_for_target(input, mode, Target::default())
+}
+
+/// Parses for a target other than the default, which decides how wide
+/// literals get encoded
+pub fn parse_for_target(input: &str, mode: LanguageMode, target: Target) ->
+    Result<TranslationUnit, CompilerError> {
+
+    let typedefs = RefCell::new(Typedefs::new());
+    let warnings = Warnings::default();
+    let mut recovered = vec![];
+    let mut starts = vec![];
+
+    let tokens = Lexer::new(input, &typedefs, mode)
+        .inspect(|t| if let Ok((start, _, _)) = t {
+            starts.push(*start);
+        });
+
+    let output = grammar::TransalationUnitParser::new()
+        .parse(&typedefs, mode, target, &warnings, &mut recovered, tokens);
+
+    // Any syntax errors mean we stop here, rather than check a broken AST
+    let failed = match output {
+        Ok(unit) if recovered.is_empty() => return Ok(unit),
+        Ok(_) => None,
+        Err(error) => Some(ErrorRecovery { error, dropped_tokens: vec![] }),
+    };
+
+    // Where the parser carried on from after the last error
+    let mut resumed: Option<usize> = None;
+    let mut errors = vec![];
+
+    for ErrorRecovery { error, dropped_tokens } in recovered.into_iter().chain(failed) {
+        let at = error_start(&error);
+        let since = resumed.map(|r| starts.partition_point(|s| *s < at)
+            - starts.partition_point(|s| *s <= r));
+
+        if since.is_none_or(|n| n >= RECOVERY_TOKENS) {
+            errors.push(located_error(error));
+        }
+
+        resumed = Some(dropped_tokens.last().map_or(at, |t| t.0).max(at));
+    }
+
+    Err(CompilerError::ParseError(errors))
+}
+
+/// Where in the input the parser hit an error
+fn error_start(error: &ParseError<usize, Tok, UserError>) -> usize {
+    match error {
+        ParseError::InvalidToken { location } |
+            ParseError::UnrecognizedEof { location, .. } => *location,
+        ParseError::UnrecognizedToken { token, .. } |
+            ParseError::ExtraToken { token } => token.0,
+        ParseError::User { error: (span, _) } => span.start,
+    }
+}
+
+/// Points any error at the input it's about, including our own, which carry
+/// their span with them
+fn located_error(error: ParseError<usize, Tok, UserError>) -> CompilerError {
+    match error {
+        ParseError::User { error: (span, error) } =>
+            syntax_error(ParseError::<usize, Tok, _>::User { error }).at(span),
+        error => syntax_error(error.map_error(|(_, error)| error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{ast::{declaration::DeclarationValue, statements::StatementKind, types::FunctionType, Context, Type},
+        fs::read_file, preprocessor::preprocess_mapped};
+
+    fn parse_file(path: &str, mode: LanguageMode) -> Result<TranslationUnit, CompilerError> {
+        let input = read_file(&PathBuf::from(path))?;
+        parse_with_mode(&input, mode)
+    }
+
+    fn function_type(unit: &TranslationUnit, i: usize) -> &FunctionType {
+        match &unit.declarations[i].type_of {
+            Type::Function(f) => f,
+            _ => panic!("Declaration {} should be a function", i),
+        }
+    }
+
+    use super::*;
+
+    #[test]
+    fn multiple_declarators() -> Result<(), CompilerError> {
+        let input = read_file(&PathBuf::from("tests/files/unit/parser/declarators.c"))?;
+        let unit = parse(&input)?;
+
+        let names: Vec<Option<&str>> = unit.declarations.iter()
+            .map(|d| d.name.as_deref())
+            .collect();
+        assert_eq!(names, [Some("a"), Some("b"), Some("c"), Some("origin"),
+            Some("cursor"), Some("T"), Some("PT"), Some("d"), Some("e"),
+            Some("main")]);
+
+        assert!(unit.declarations[1].value.is_some());
+        assert_eq!(unit.declarations[2].type_of, Type::Pointer(Box::new(Type::Int32)));
+
+        let locals = match &unit.declarations[9].value {
+            Some(DeclarationValue::Function(stmts)) => stmts.iter()
+                .filter(|s| matches!(s.kind, StatementKind::Declaration(_)))
+                .count(),
+            _ => panic!("main should have a body"),
+        };
+        assert_eq!(locals, 7);
+
+        unit.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn anonymous_tags() -> Result<(), CompilerError> {
+        let input = read_file(&PathBuf::from("tests/files/unit/parser/anonymous.c"))?;
+
+        // Every parse numbers its anonymous structs from the start again
+        for _ in 0..2 {
+            let unit = parse(&input)?;
+            let tags: Vec<&str> = unit.declarations.iter()
+                .map(|d| match &d.type_of {
+                    Type::Struct(s) => s.tag.as_str(),
+                    _ => panic!("Declarations should be structs"),
+                })
+                .collect();
+
+            assert_eq!(tags, ["<anonymous>", "<anonymous>.1"]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn prototypes() -> Result<(), CompilerError> {
+        let unit = parse_file("tests/files/unit/function/prototypes.c",
+            LanguageMode::Standard)?;
+
+        let add = function_type(&unit, 0);
+        assert!(add.prototyped);
+        assert_eq!(add.args.len(), 2);
+
+        assert!(!function_type(&unit, 1).prototyped);
+        assert!(function_type(&unit, 2).prototyped);
+
+        let widen = function_type(&unit, 3);
+        assert_eq!(widen.args[1].1, Type::Pointer(Box::new(Type::Char)));
+
+        let main = function_type(&unit, 6);
+        assert!(main.prototyped);
+        assert!(main.args.is_empty());
+
+        unit.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn void_args() -> Result<(), CompilerError> {
+        let unit = parse_file("tests/files/unit/function/void_args.c",
+            LanguageMode::Standard)?;
+
+        match unit.verify(&mut Context::new()) {
+            Err(e) if matches!(e.root(), CompilerError::SemanticError(_)) => Ok(()),
+            x => panic!("Expected a semantic error, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn unprototyped() -> Result<(), CompilerError> {
+        let unit = parse_file("tests/files/unit/function/unprototyped.c",
+            LanguageMode::Standard)?;
+        unit.verify(&mut Context::new())
+    }
+
+    #[test]
+    fn old_style() -> Result<(), CompilerError> {
+        let path = "tests/files/unit/function/old_style.c";
+        assert!(parse_file(path, LanguageMode::Standard).is_err());
+
+        let unit = parse_file(path, LanguageMode::Legacy)?;
+        let scale = function_type(&unit, 0);
+        assert!(!scale.prototyped);
+        assert_eq!(scale.args.iter().map(|(_, t)| t.clone()).collect::<Vec<_>>(),
+            [Type::Int64, Type::Int32, Type::Pointer(Box::new(Type::Int32))]);
+
+        unit.verify(&mut Context::new())
+    }
+
+    /// Every syntax error in the file, along with where it starts
+    fn parse_errors(path: &str) -> Vec<(usize, ParseError<usize, String, &'static str>)> {
+        let errors = match parse_file(path, LanguageMode::Standard) {
+            Err(CompilerError::ParseError(errors)) => errors,
+            x => panic!("Expected parse errors, got {:?}", x),
+        };
+
+        errors.into_iter()
+            .map(|e| match e {
+                CompilerError::Spanned { span, error } => match *error {
+                    CompilerError::SyntaxError(error) => (span.start, error),
+                    error => panic!("Expected a syntax error, got {:?}", error),
+                },
+                _ => panic!("Expected a span, got {:?}", e),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn error_recovery() {
+        let errors = parse_errors("tests/files/unit/parser/errors.c");
+
+        let locations: Vec<usize> = errors.iter()
+            .map(|(start, e)| match e {
+                ParseError::UnrecognizedToken { expected, .. } => {
+                    assert!(!expected.is_empty());
+                    *start
+                },
+                _ => panic!("Expected an unrecognized token, got {:?}", e),
+            })
+            .collect();
+        assert_eq!(locations, [8, 50, 76, 93]);
+    }
+
+    #[test]
+    fn recovery_scopes() {
+        // The block's typedef ends with it, even though its first statement
+        // was broken, and later typedefs at file scope still work
+        let errors = parse_errors("tests/files/unit/parser/recovery_scopes.c");
+        let locations: Vec<usize> = errors.iter().map(|(start, _)| *start).collect();
+        assert_eq!(locations, [28, 80]);
+    }
+
+    #[test]
+    fn follow_on_errors() {
+        // Recovering at the `;` in the if's body ends the function early, but
+        // the errors that causes don't get reported
+        let errors = parse_errors("tests/files/unit/parser/follow_on.c");
+        let locations: Vec<usize> = errors.iter().map(|(start, _)| *start).collect();
+        assert_eq!(locations, [25, 71]);
+    }
+
+    #[test]
+    fn error_locations() -> Result<(), CompilerError> {
+        let path = PathBuf::from("tests/files/unit/parser/errors.c");
+        let (input, source_map) = preprocess_mapped(&path)?;
+
+        let error = parse(&input).expect_err("Should have errors").locate(&source_map);
+        let lines: Vec<String> = error.to_string().lines()
+            .filter(|l| !l.starts_with("Expected"))
+            .map(String::from)
+            .collect();
+
+        assert_eq!(lines, [
+            "tests/files/unit/parser/errors.c:1:9: Parsing Error: Unrecognized token `;`",
+            "tests/files/unit/parser/errors.c:5:16: Parsing Error: Unrecognized token `;`",
+            "tests/files/unit/parser/errors.c:7:14: Parsing Error: Unrecognized token `0`",
+            "tests/files/unit/parser/errors.c:10:12: Parsing Error: Unrecognized token `3`",
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn literal_errors() {
+        let message = |path| match parse_errors(path).as_slice() {
+            [(start, ParseError::User { error })] => (*start, *error),
+            errors => panic!("Expected one literal error, got {:?}", errors),
+        };
+
+        // They point at the literal itself
+        assert_eq!(message("tests/files/unit/parser/literal_prefixes.c"),
+            (9, "Can't join string literals with different prefixes"));
+        assert_eq!(message("tests/files/unit/parser/literal_escape.c").1,
+            "Escape sequence out of range for its literal");
+        assert_eq!(message("tests/files/unit/parser/literal_char.c").1,
+            "Character doesn't fit in one code unit of its literal");
+    }
 }
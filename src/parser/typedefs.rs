@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use crate::ast::Type;
+
+/// Which names are typedef names and what they stand for, along with tags, by
+/// scope. The lexer declares names and opens and closes scopes as it goes, so
+/// that it knows whether a name is a typedef name. The parser fills in what
+/// each one means once it's parsed the typedef, and finds it here again
+#[derive(Debug)]
+pub struct Typedefs {
+    /// Innermost last. The first is the file scope
+    scopes: Vec<Scope>,
+
+    /// How many different tags have been declared with each name, so that
+    /// each can be given its own id
+    tag_counts: HashMap<String, usize>,
+}
+
+/// The names and tags declared in a block, or in a function's parameters
+#[derive(Debug, Default)]
+pub struct Scope {
+    names: HashMap<String, Name>,
+
+    /// Maps each tag declared here to the id it goes by from here on
+    tags: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+enum Name {
+    /// Anything other than a typedef name, e.g. a variable, which hides any
+    /// typedef name with the same name from outer scopes
+    Ordinary,
+
+    /// A typedef name, and its type once the parser has got that far
+    Typedef(Option<Type>),
+}
+
+impl Default for Typedefs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Typedefs {
+    pub fn new() -> Self {
+        Self { scopes: vec![Scope::default()], tag_counts: HashMap::new() }
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    /// Carries on with a scope that was exited, which is how a function's body
+    /// ends up with its parameters in scope
+    pub fn reenter_scope(&mut self, scope: Scope) {
+        self.scopes.push(scope);
+    }
+
+    pub fn exit_scope(&mut self) -> Scope {
+        match self.scopes.len() {
+            // Never exit the file scope
+            1 => Scope::default(),
+            _ => self.scopes.pop().unwrap(),
+        }
+    }
+
+    fn current(&mut self) -> &mut Scope {
+        // Never empty, since we never exit the file scope
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// Records that a name has been declared in the current scope
+    pub fn declare(&mut self, name: &str, typedef: bool) {
+        let name_of = match typedef {
+            true => Name::Typedef(None),
+            false => Name::Ordinary,
+        };
+
+        self.current().names.insert(name.to_string(), name_of);
+    }
+
+    /// Whether the name currently refers to a typedef
+    pub fn is_typedef(&self, name: &str) -> bool {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.names.get(name))
+            .is_some_and(|n| matches!(n, Name::Typedef(_)))
+    }
+
+    /// Gives a typedef name declared by the lexer its type. The lexer can be a
+    /// token ahead of the parser, so this is the innermost one still waiting
+    /// for a type, rather than whatever's in the current scope. If there isn't
+    /// one, its scope has already ended and nothing can use it
+    pub fn insert(&mut self, name: &str, type_of: Type) {
+        let pending = self.scopes.iter_mut().rev()
+            .find_map(|scope| match scope.names.get_mut(name) {
+                Some(Name::Typedef(t @ None)) => Some(t),
+                _ => None,
+            });
+
+        if let Some(t) = pending {
+            *t = Some(type_of);
+        }
+    }
+
+    /// Gets the type a typedef name stands for. Again the lexer might already
+    /// have declared something shadowing it, e.g. the `T` in `T T;`, so this
+    /// skips anything that isn't a typedef with a type yet
+    pub fn get(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev()
+            .find_map(|scope| match scope.names.get(name) {
+                Some(Name::Typedef(Some(t))) => Some(t.clone()),
+                _ => None,
+            })
+    }
+
+    /// Gets the id of the tag a mention like `struct foo` refers to, which is
+    /// whichever one is visible. If there isn't one, this declares it
+    pub fn mention_tag(&mut self, tag: &str) -> String {
+        let visible = self.scopes.iter().rev()
+            .find_map(|scope| scope.tags.get(tag))
+            .cloned();
+
+        match visible {
+            Some(id) => id,
+            None => self.declare_tag(tag),
+        }
+    }
+
+    /// Gets the id of the tag a definition (or `struct foo;`) declares. That's
+    /// the one already in this scope if there is one, so that a definition
+    /// completes an earlier declaration, and a new one shadowing any outer
+    /// tags otherwise
+    pub fn declare_tag(&mut self, tag: &str) -> String {
+        if let Some(id) = self.current().tags.get(tag) {
+            return id.clone();
+        }
+
+        let id = self.new_tag_id(tag);
+        self.current().tags.insert(tag.to_string(), id.clone());
+        id
+    }
+
+    /// Gets an id for a struct, union or enum without a tag. Nothing can
+    /// refer to it by name, so it isn't added to any scope
+    pub fn anonymous_tag(&mut self) -> String {
+        // Nobody could write this as a tag, so it can't clash
+        self.new_tag_id("<anonymous>")
+    }
+
+    fn new_tag_id(&mut self, tag: &str) -> String {
+        // The first tag with a name keeps it as its id, and any that shadow it
+        // get numbered. Names can't have a `.`, so these never clash
+        let count = self.tag_counts.entry(tag.to_string()).or_default();
+        let id = match *count {
+            0 => tag.to_string(),
+            n => format!("{}.{}", tag, n),
+        };
+        *count += 1;
+
+        id
+    }
+
+    /// Handles `struct foo;`, which always declares a tag in this scope. By
+    /// the time we know that's what it is, `struct foo` has already been
+    /// read as a mention, so this takes the id it got and gives back the one
+    /// it should have
+    pub fn redeclare_tag(&mut self, id: &str) -> String {
+        let name = self.scopes.iter().rev()
+            .find_map(|scope| scope.tags.iter().find(|(_, i)| *i == id))
+            .map(|(name, _)| name.clone());
+
+        match name {
+            Some(name) => self.declare_tag(&name),
+            None => id.to_string(),
+        }
+    }
+}
@@ -7,6 +7,9 @@ pub enum Directive {
     IncludeLocal(PathBuf),
     Define(Definition),
     Raw(String),
+
+    /// Where the next raw block came from (its file and zero based line)
+    Origin(PathBuf, usize),
 }
 
 #[derive(Debug)]
@@ -7,55 +7,51 @@ use log::trace;
 use crate::{error::CompilerError, fs::read_file};
 
 mod directive;
+pub mod source_map;
 use directive::Directive;
+use source_map::SourceMap;
 
 lalrpop_mod!(grammar, "/preprocessor/grammar.rs");
 
-fn parse(file_contents: &str) -> Result<Vec<Directive>, CompilerError> {
+/// Parses the file into directives, each alongside the (zero based) line it
+/// starts on
+fn parse(file_contents: &str) -> Result<Vec<(usize, Directive)>, CompilerError> {
     // First parse each line into either a raw string or the directive
-    file_contents.lines().map(|line| {
+    file_contents.lines().enumerate().map(|(i, line)| {
         if line.starts_with('#') {
-            Ok(grammar::DirectiveParser::new().parse(line)?)
+            Ok((i, grammar::DirectiveParser::new().parse(line)?))
         } else {
-            Ok(Directive::Raw(String::from(line)))
+            Ok((i, Directive::Raw(String::from(line))))
         }
     // Then, combine any adjacent raw strings
-    }).fold(Ok(Vec::new()), |directives_res, curr_res| {
-        let curr = match curr_res {
-            Ok(x) => x,
-            Err(e) => return Err(e),
-        };
-
-        let mut directives = match directives_res {
-            Ok(x) => x,
-            Err(e) => return Err(e),
-        };
+    }).try_fold(Vec::new(), |mut directives, curr_res: Result<_, CompilerError>| {
+        let (i, curr) = curr_res?;
 
         match directives.pop() {
             // We're the first directive, just push
-            None => directives.push(curr),
+            None => directives.push((i, curr)),
             // Last directive was a raw, so combine us and them if we're
             // also a raw
-            Some(Directive::Raw(mut line)) => {
+            Some((start, Directive::Raw(mut line))) => {
                 match curr {
                     // We're a raw! Combine and push
                     Directive::Raw(curr_line) => {
                         let combined = [line, curr_line].join("\n");
-                        directives.push(Directive::Raw(combined));
+                        directives.push((start, Directive::Raw(combined)));
                     }
                     // We're not :(
                     _ => {
                         // Add \n to the end of each raw block
                         line.push('\n');
-                        directives.push(Directive::Raw(line));
-                        directives.push(curr);
+                        directives.push((start, Directive::Raw(line)));
+                        directives.push((i, curr));
                     }
                 }
             },
             // Last directive wasn't, so push it back alongside us
             Some(x) => {
                 directives.push(x);
-                directives.push(curr);
+                directives.push((i, curr));
             }
         };
 
@@ -79,23 +75,43 @@ fn apply_definitions(
 
 fn get_directives(path: &Path) -> Result<VecDeque<Directive>, CompilerError> {
     let file_contents = read_file(path)?;
-    let directives = parse(&file_contents)?;
+    let mut directives = VecDeque::new();
 
     // Use a VecDeque so we can add stuff to the front in the processing loop
-    Ok(VecDeque::from(directives))
+    for (line, directive) in parse(&file_contents)? {
+        // Note where each block of code came from, so errors can point there
+        if let Directive::Raw(_) = directive {
+            directives.push_back(Directive::Origin(path.to_path_buf(), line));
+        }
+
+        directives.push_back(directive);
+    }
+
+    Ok(directives)
 }
 
 pub fn preprocess(path: &Path) -> Result<String, CompilerError> {
+    Ok(preprocess_mapped(path)?.0)
+}
+
+/// Same as `preprocess`, but also gives a map from the output back to the
+/// files and lines it came from
+pub fn preprocess_mapped(path: &Path) -> Result<(String, SourceMap), CompilerError> {
     let mut directives = get_directives(path)?;
     trace!("Produced directives: {:?}", directives);
 
     let mut definitions: IndexMap<String, String> = IndexMap::new();
 
     let mut output = String::new();
+    let mut source_map = SourceMap::default();
 
     while !directives.is_empty() {
         match directives.pop_front().unwrap() {
 
+            Directive::Origin(file, line) => {
+                source_map.mark(output.len(), file, line);
+            },
+
             Directive::Raw(raw) => {
                 output.push_str(&apply_definitions(&definitions, &raw));
             },
@@ -124,7 +140,7 @@ pub fn preprocess(path: &Path) -> Result<String, CompilerError> {
                 // Stick a \n at the end of the included directives so that the
                 // #include replacement ends in a newline in the final file
                 directives.push_front(Directive::Raw(String::from('\n')));
-                for d in include_directives {
+                for d in include_directives.into_iter().rev() {
                     directives.push_front(d);
                 };
             }
@@ -136,7 +152,8 @@ pub fn preprocess(path: &Path) -> Result<String, CompilerError> {
         }
     }
 
-    Ok(output)
+    source_map.finish(&output);
+    Ok((output, source_map))
 }
 
 #[cfg(test)]
@@ -173,6 +190,20 @@ I like to say 1 + 2 + 3 >> 2";
         Ok(())
     }
 
+    #[test]
+    fn source_map() -> Result<(), CompilerError> {
+        let (output, source_map) = preprocess_mapped(&path("include_local.txt"))?;
+
+        let yay = output.find("Yay!").unwrap();
+        let header = output.rfind("Hello from the header!").unwrap();
+
+        assert_eq!(source_map.locate(yay).to_string(),
+            "tests/files/unit/preproc/include_local.txt:4:1");
+        assert_eq!(source_map.locate(header + 6).to_string(),
+            "tests/files/unit/preproc/include_local.h:1:7");
+        Ok(())
+    }
+
     #[test]
     fn include_local() -> Result<(), CompilerError> {
         let output = preprocess(&path("include_local.txt"))?;
@@ -0,0 +1,68 @@
+use std::{fmt::Display, path::PathBuf};
+
+/// A file, line and column, counting from one like editors do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+/// Maps byte offsets in the preprocessor's output back to the files they came
+/// from. Columns are for the line after macros have been expanded, so they
+/// can be a little off on lines that use them
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    /// Each block of output, as the offset it starts at, the file it came
+    /// from, and the (zero based) line in that file it starts on
+    origins: Vec<(usize, PathBuf, usize)>,
+
+    /// The offset each line of output starts at
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn mark(&mut self, offset: usize, file: PathBuf, line: usize) {
+        self.origins.push((offset, file, line));
+    }
+
+    /// Called once the output's done, so we know where its lines are
+    pub fn finish(&mut self, output: &str) {
+        self.line_starts = std::iter::once(0)
+            .chain(output.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+    }
+
+    pub fn locate(&self, offset: usize) -> SourceLocation {
+        let line_of = |offset: usize| self.line_starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1);
+
+        let output_line = line_of(offset);
+        let column = offset - self.line_starts.get(output_line).unwrap_or(&0) + 1;
+
+        let origin = self.origins.iter()
+            .rev()
+            .find(|(start, _, _)| *start <= offset);
+
+        match origin {
+            Some((start, file, line)) => SourceLocation {
+                file: file.clone(),
+                line: line + output_line - line_of(*start) + 1,
+                column,
+            },
+
+            None => SourceLocation {
+                file: PathBuf::new(),
+                line: output_line + 1,
+                column,
+            },
+        }
+    }
+}
@@ -0,0 +1,27 @@
+unsigned long rdtsc(void) {
+    unsigned long lo;
+    unsigned long hi;
+
+    asm volatile("rdtsc" : "=a"(lo), "=d"(hi));
+
+    return (hi << (unsigned long) 32) | lo;
+}
+
+int add(int a, int b) {
+    __asm__("add %0, %1" : "+r"(a) : "r"(b) : "cc");
+    return a;
+}
+
+void cpuid(int leaf, int *a, int *b, int *c, int *d) {
+    asm("cpuid" : "=a"(*a), "=b"(*b), "=c"(*c), "=d"(*d) : "a"(leaf));
+}
+
+int shifted(int x) {
+    asm("shl %0, %1" : "=r"(x) : "i"(3), "0"(x));
+    return x;
+}
+
+void fence(void) {
+    asm("mfence\n\t" "lfence");
+    __asm volatile("" : : : "memory");
+}
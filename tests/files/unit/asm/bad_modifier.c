@@ -0,0 +1,5 @@
+int main(void) {
+    int x = 0;
+    asm("inc %0" : "&=r"(x));
+    return x;
+}
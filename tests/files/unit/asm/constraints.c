@@ -0,0 +1,29 @@
+long counter;
+
+int low_bits(int x) {
+    int out;
+    asm("mov %0, %1\n\tand %0, 15" : "=&r"(out) : "r"(x));
+    return out;
+}
+
+void bump(void) {
+    asm("inc %0" : "+rm"(counter));
+}
+
+int sum(int x) {
+    int total = 0;
+    asm("add %0, %1" : "+g"(total) : "q"(x));
+    return total;
+}
+
+unsigned char low_byte(unsigned int x) {
+    unsigned char out;
+    asm("mov %0, %1" : "=qm"(out) : "0"(x));
+    return out;
+}
+
+void touch(void) {
+    long local = 1;
+    asm("not %0" : "+m"(local));
+    asm("add %0, %1" : "+m"(counter) : "ri"((long) 2));
+}
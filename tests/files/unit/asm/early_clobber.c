@@ -0,0 +1,5 @@
+int f(int x) {
+    int y;
+    asm("mov %0, 1\n\tadd %0, %1" : "=&a"(y) : "a"(x));
+    return y;
+}
@@ -0,0 +1,5 @@
+int main(void) {
+    int x;
+    asm("mov %0, 1" : "=r"(x + 1));
+    return x;
+}
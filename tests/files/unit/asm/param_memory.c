@@ -0,0 +1,4 @@
+int twice(int x) {
+    asm("shl %0, 1" : "+m"(x));
+    return x;
+}
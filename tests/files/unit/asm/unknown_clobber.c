@@ -0,0 +1,4 @@
+int main(void) {
+    asm("nop" : : : "rzx");
+    return 0;
+}
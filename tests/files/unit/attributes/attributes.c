@@ -0,0 +1,66 @@
+struct __attribute__((packed)) header {
+    char kind;
+    int length;
+    long offset;
+};
+
+struct padded {
+    char c;
+    int i;
+} __attribute__((aligned(32)));
+
+typedef struct {
+    short s;
+    char c;
+} __attribute__((__packed__)) tiny_t;
+
+struct [[gnu::packed]] point {
+    char x [[maybe_unused]];
+    int y;
+};
+
+__attribute__((noreturn)) void fail(void);
+void also_fails(int code) __attribute__((__noreturn__, cold));
+[[nodiscard]] int compute(int x);
+
+static __attribute__((unused)) int spare;
+int counter __attribute__((weak, visibility("hidden"))) = 0;
+extern int maybe __attribute__((weak));
+__attribute__((section(".rodata.magic"))) const int magic = 42;
+_Alignas(8) int __attribute__((aligned(16))) aligned_more;
+
+static void init(void) __attribute__((constructor));
+static void fini(void) __attribute__((destructor));
+
+__attribute__((constructor)) static void init(void) {
+    counter = 1;
+}
+
+[[gnu::destructor]] static void fini(void) {
+    counter = 0;
+}
+
+int compute(int x [[maybe_unused]]) {
+    [[maybe_unused]] int unused_local = 0;
+    int __attribute__((unused)) also_unused;
+    tiny_t t;
+
+    switch (x) {
+        case 0:
+            t.s = (short) 1;
+            [[fallthrough]];
+        case 1:
+            break;
+    }
+
+    return (int) t.s;
+}
+
+void fail(void) {
+    while (1) {
+    }
+}
+
+int main(void) {
+    return compute(3);
+}
@@ -0,0 +1 @@
+int hidden_thing __attribute__((visibility("secret")));
@@ -0,0 +1,39 @@
+[[nodiscard]] int compute(int x);
+__attribute__((noreturn)) void fail(void);
+static int helper(void);
+static int used_helper(void);
+
+__attribute__((noreturn)) int not_a_function;
+__attribute__((packed)) int not_a_struct;
+typedef int __attribute__((aligned(8))) aligned_int;
+
+int compute(int x) {
+    int never;
+    __attribute__((weak)) int local = x;
+    static int counter;
+
+    compute(local);
+    (void) compute(local);
+
+    return used_helper();
+}
+
+void fail(void) {
+    return;
+}
+
+static int helper(void) {
+    return 1;
+}
+
+static int used_helper(void) {
+    return 2;
+}
+
+static int shared;
+int __attribute__((frobnicate)) odd;
+
+int scale(int x __attribute__((weak))) {
+    extern int shared;
+    return shared * x;
+}
@@ -0,0 +1,10 @@
+int f(int i) {
+again:
+    {
+        int q = i;
+        i = q + 1;
+    }
+
+    if (i < 3) goto again;
+    return i;
+}
@@ -0,0 +1,8 @@
+int k(int n) {
+    while (n) {
+        int z = n;
+        n = z - 1;
+    }
+
+    return 0;
+}
@@ -0,0 +1,7 @@
+int f(int x) {
+    while (x) {
+        x = x - 1;
+    }
+
+    return x;
+}
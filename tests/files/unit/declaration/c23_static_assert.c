@@ -0,0 +1,6 @@
+static_assert(sizeof(long) == 8);
+
+int main(void) {
+    static_assert(sizeof(int) == 4, "int is 32 bits");
+    return 0;
+}
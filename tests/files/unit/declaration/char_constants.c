@@ -0,0 +1,4 @@
+_Static_assert(_Generic('a', int: 1, default: 0), "character constants are ints");
+_Static_assert(_Generic(L'a', int: 1, default: 0), "wide ones are wchar_t");
+_Static_assert(_Generic(u'a', unsigned short: 1, default: 0), "UTF-16 ones are char16_t");
+_Static_assert('\xff' < 0, "plain char is signed");
@@ -0,0 +1,6 @@
+int value;
+
+int main(void) {
+    extern long value;
+    return 0;
+}
@@ -0,0 +1,5 @@
+struct small {
+    char c;
+};
+
+_Static_assert(sizeof(struct small) == 8, "small " "isn\x27t\tpadded");
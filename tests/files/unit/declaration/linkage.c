@@ -0,0 +1,15 @@
+static int helper(int x);
+int shared;
+int shared;
+static int hidden = 3;
+extern int elsewhere;
+int counter(void) {
+    static int count;
+    static int start = 5;
+    extern int shared;
+    register int r = 1;
+    count += r;
+    return count + start + hidden + elsewhere;
+}
+int helper(int y) { return y; }
+int main(void) { int printf(); return helper(counter()); }
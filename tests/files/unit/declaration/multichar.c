@@ -0,0 +1,6 @@
+_Static_assert('ab' == 24930, "the first char ends up highest");
+_Static_assert('\xff\xff\xff\xff' == -1, "they're ints");
+_Static_assert('é' == 50089, "a char can take more than one byte");
+_Static_assert('abcde' == 1650680933, "only the last four chars count");
+
+int c = 'ab';
@@ -0,0 +1,6 @@
+int limit = 4;
+
+int main(void) {
+    _Static_assert(limit == 4, "limit is four");
+    return 0;
+}
@@ -0,0 +1,2 @@
+static int count;
+int count;
@@ -0,0 +1,5 @@
+int main(void) {
+    register int r = 4;
+    int *p = &r;
+    return 0;
+}
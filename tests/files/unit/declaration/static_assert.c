@@ -0,0 +1,20 @@
+struct pair {
+    int first;
+    long second;
+};
+
+enum color { RED, GREEN, BLUE, COLOR_COUNT };
+
+_Static_assert(sizeof(struct pair) == 16, "pair should be " "two longs wide");
+_Static_assert(COLOR_COUNT <= 4, "colors fit in two bits");
+_Static_assert(_Alignof(struct pair) == 8);
+_Static_assert(!(-1 < sizeof(int)), "-1 becomes unsigned");
+_Static_assert(-1 < (short) 1 && (unsigned char) 255 + 1 == 256, "small types promote to int");
+_Static_assert(-8 >> 1 == -4 && (unsigned int) -1 / 2 == 2147483647, "signedness is kept");
+
+int main(void) {
+    _Static_assert(sizeof(int) == 4, "int is 32 bits");
+    int x = 1;
+    _Static_assert(BLUE == 2, "enums count from zero");
+    return x;
+}
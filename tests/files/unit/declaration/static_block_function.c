@@ -0,0 +1,4 @@
+int main(void) {
+    static int helper(void);
+    return 0;
+}
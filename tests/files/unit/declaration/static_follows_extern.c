@@ -0,0 +1,2 @@
+int total;
+static int total;
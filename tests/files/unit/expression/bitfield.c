@@ -0,0 +1,24 @@
+struct reg {
+    unsigned enable : 1;
+    unsigned mode : 3;
+    int offset : 5;
+    unsigned : 0;
+    unsigned long tag : 40;
+};
+
+struct reg defaults = { 1, 5, 29, 7 };
+
+int read_offset(struct reg *r) {
+    return r->offset;
+}
+
+unsigned set_mode(struct reg *r, unsigned mode) {
+    r->tag = (unsigned long)1;
+    r->mode += mode;
+    return r->mode = mode;
+}
+
+unsigned make(void) {
+    struct reg r = { .mode = (unsigned)2, .enable = (unsigned)1 };
+    return r.mode;
+}
@@ -0,0 +1,7 @@
+struct s {
+    unsigned flag : 1;
+};
+
+unsigned *f(struct s *x) {
+    return &x->flag;
+}
@@ -0,0 +1,33 @@
+struct mix {
+    unsigned char a : 4;
+    unsigned short b : 4;
+};
+
+struct mix shared = { 1, 2 };
+
+struct __attribute__((packed)) wire {
+    unsigned char version : 4;
+    unsigned char ihl : 4;
+    unsigned short length : 12;
+    unsigned short flags : 4;
+};
+
+struct wire header = { 4, 5, 2748, 9 };
+
+struct near {
+    int a : 4;
+    int b : 20;
+    char c;
+};
+
+unsigned read_flags(struct wire *w) {
+    return (unsigned) w->flags;
+}
+
+void write_flags(struct wire *w) {
+    w->flags = (unsigned short) 3;
+}
+
+void write_b(struct near *n) {
+    n->b = 7;
+}
@@ -0,0 +1,7 @@
+struct s {
+    unsigned flag : 1;
+};
+
+unsigned long f(struct s *x) {
+    return sizeof x->flag;
+}
@@ -0,0 +1,6 @@
+int seven(int a, int b, int c, int d, int e, int f, int g);
+int eight(int a, int b, int c, int d, int e, int f, int g, int h);
+
+int main() {
+    return seven(1, 2, 3, 4, 5, 6, 7) + eight(1, 2, 3, 4, 5, 6, 7, 8);
+}
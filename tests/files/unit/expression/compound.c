@@ -0,0 +1,14 @@
+int total;
+
+int step(int a, int b) {
+    int x = a, counts[4];
+
+    x += b;
+    x *= 3;
+    x <<= b;
+    counts[x, 2] = 7;
+    counts[2] %= x;
+    total -= 1, total |= x;
+
+    return x >>= 1;
+}
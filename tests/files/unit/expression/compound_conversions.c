@@ -0,0 +1,13 @@
+long l;
+unsigned char c;
+unsigned u;
+int *p;
+
+long f(int n) {
+    l += 1;
+    c *= 3;
+    u /= 2;
+    p += 2;
+    p -= n;
+    return l;
+}
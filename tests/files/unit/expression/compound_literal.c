@@ -0,0 +1,15 @@
+struct point { int x; int y; };
+
+int sum(int k, struct point *p) { return k + p->x + p->y; }
+
+int wrap(int k) {
+    return sum(k, &(struct point){ k, 2 });
+}
+
+int main() {
+    struct point *p = &(struct point){ .y = 5 };
+    int *a = (int[]){ 1, 2, 3 };
+    int n = (int){ 7 };
+    (struct point){ 1, 2 }.x = 3;
+    return p->y + a[2] + n + (int)sizeof((int[]){ 1, 2, 3, 4 });
+}
@@ -0,0 +1,12 @@
+int f(void) {
+    int i = 0;
+    int total = 0;
+
+    while (i < 3) {
+        int *p = (int[]){ 1, 2, 3 };
+        total = total + p[i];
+        i = i + 1;
+    }
+
+    return total;
+}
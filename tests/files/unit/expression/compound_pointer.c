@@ -0,0 +1,4 @@
+int f(int *p) {
+    p *= 2;
+    return 0;
+}
@@ -0,0 +1,28 @@
+typedef long count_t;
+
+int f_int(int x) { return x + 1; }
+long f_long(long x) { return x * (long)2; }
+int f_gen(char *s) { return 0; }
+
+int values[4];
+
+int main() {
+    const int c = 3;
+    long l = (long)5;
+    char name[8];
+    count_t n = (count_t)1;
+
+    int a = _Generic(c, int: f_int, long: f_long, default: f_gen)(c);
+    long b = _Generic(l, int: f_int, count_t: f_long, default: f_gen)(n);
+    int d = _Generic(name, char *: f_gen, default: f_int)(name);
+
+    _Generic(a, int: a, default: l) = 4;
+    int *p = &_Generic(values, int *: values, default: name)[1];
+
+    switch (a) {
+    case _Generic(l, long: 2, default: 3):
+        break;
+    }
+
+    return (int)sizeof(_Generic(a, int: values, default: name));
+}
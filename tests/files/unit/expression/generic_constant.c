@@ -0,0 +1,13 @@
+int lengths[_Generic(1, int: 3, default: 4)];
+
+enum {
+    A = _Generic(1, int: 5, default: 6),
+    B = _Generic((char)1 + (long)1, long: 7, default: 8),
+};
+
+int main(void) {
+    int local[_Generic('a', int: 2, default: 9)];
+    local[0] = A;
+    local[1] = B;
+    return local[0] + local[1] + (int)sizeof(lengths);
+}
@@ -0,0 +1,4 @@
+int main() {
+    int x = 1;
+    return _Generic(x, default: 1, int: 2, default: 3);
+}
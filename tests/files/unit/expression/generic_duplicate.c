@@ -0,0 +1,4 @@
+int main() {
+    int x = 1;
+    return _Generic(x, int: 1, signed int: 2, default: 3);
+}
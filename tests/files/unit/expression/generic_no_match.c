@@ -0,0 +1,4 @@
+int main() {
+    long l = (long)1;
+    return _Generic(l, int: 1, char: 2);
+}
@@ -0,0 +1,11 @@
+int main(void) {
+    char c = 'x';
+    int from_char = _Generic('a', int: 1, char: 2);
+    int from_sum = _Generic(c + c, int: 3, char: 4);
+    int from_compare = _Generic(c < c, int: 5, char: 6);
+
+    _Static_assert(_Generic('a', int: 1, char: 2) == 1, "character constants are ints");
+    _Static_assert(_Generic(c + c, int: 1, char: 2) == 1, "chars get promoted");
+
+    return from_char + from_sum + from_compare;
+}
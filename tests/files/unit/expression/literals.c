@@ -0,0 +1,29 @@
+const char *plain = "héllo\n";
+const char *utf8 = u8"é" "x";
+unsigned short *utf16 = u"a\U0001F600";
+unsigned int *utf32 = U"é";
+int *wide = L"w" "ide";
+char c = '\xff';
+int wc = L'é';
+
+typedef int wchar_t;
+wchar_t warray[] = L"hé";
+
+unsigned long size(void) {
+    return sizeof(L"ab");
+}
+
+unsigned long char_size(void) {
+    return sizeof('a');
+}
+
+unsigned long utf16_char_size(void) {
+    return sizeof(u'a');
+}
+
+int first(void) {
+    int *s = L"été";
+    unsigned short u = u'€';
+    char *p = "a" "b";
+    return s[0];
+}
@@ -0,0 +1,10 @@
+int twice(int x) { return x * 2; }
+
+int main() {
+    int n = 4;
+    int m = ({ int t = twice(n); if (t) t = t + 1; t; });
+    long l = ({ (long)m; });
+    ({ n = 0; });
+    ({ int n = 6; (void)n; });
+    return m + (int)l + n + twice(({ int u = 3; u; }));
+}
@@ -0,0 +1,5 @@
+int main() {
+    int n = ({ goto done; 1; });
+done:
+    return n;
+}
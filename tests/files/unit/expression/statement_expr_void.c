@@ -0,0 +1,5 @@
+int main() {
+    int n = 1;
+    int m = ({ if (n) n = 2; });
+    return m;
+}
@@ -0,0 +1,11 @@
+volatile int flag;
+
+int poll(void) {
+    int first = flag;
+    int second = flag;
+
+    flag = 1;
+    flag = 1;
+
+    return first + second;
+}
@@ -0,0 +1,11 @@
+long scale(x, factor, p)
+    long x;
+    register int *p;
+{
+    return x + (long) factor + (long) *p;
+}
+
+int main(void) {
+    int one = 1;
+    return (int) scale((long) 2, 3, &one);
+}
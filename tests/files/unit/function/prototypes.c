@@ -0,0 +1,16 @@
+int add(int, int);
+int count();
+int count(int n);
+long widen(long *, char[4]);
+
+int add(int a, int b) {
+    return a + b;
+}
+
+int count(int n) {
+    return n;
+}
+
+int main(void) {
+    return add(1, 2) + count(3);
+}
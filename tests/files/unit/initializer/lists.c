@@ -0,0 +1,16 @@
+struct point { int x; int y; };
+struct line { struct point from; struct point to; int tag; };
+union num { int i; long l; };
+
+int primes[] = { 2, 3, 5, 7, 11 };
+struct line diag = { { 1, 2 }, .to.y = 4, 3 };
+int grid[2][3] = { 1, 2, 3, [1][2] = 9 };
+union num n = { .l = 7 };
+struct point pts[] = { [2].y = 1, 4 };
+
+int main(void) {
+    int local[4] = { 1, [2] = 3 };
+    struct line l = { 1, 2, 3, 4, 5 };
+    int x = { 7 };
+    return local[2] + l.to.y + x;
+}
@@ -0,0 +1 @@
+char s[1] = "hi";
@@ -0,0 +1,3 @@
+struct point { int x; int y; };
+
+struct point p = { .z = 1 };
@@ -0,0 +1,2 @@
+int y = 3;
+int a[2] = { 1, y };
@@ -0,0 +1 @@
+int a[3] = { [3] = 1 };
@@ -0,0 +1 @@
+union pair { struct { int a, b, c; } s; int x; } p = 1;
@@ -0,0 +1 @@
+int n = "abc";
@@ -0,0 +1,24 @@
+char greeting[] = "hi";
+char exact[3] = "hi";
+char roomy[5] = "hi";
+char braced[] = { "hi" };
+char rows[2][3] = { "ab", "cd" };
+
+struct point {
+    int x;
+    int y;
+};
+
+struct line {
+    struct point from;
+    struct point to;
+};
+
+int main() {
+    char local[] = "hey";
+    struct point a = { 1, 2 };
+    struct point b = { 3, 4 };
+    struct line l = { a, b };
+    local[0] = local[1];
+    return l.to.y;
+}
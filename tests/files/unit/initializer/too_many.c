@@ -0,0 +1,3 @@
+struct point { int x; int y; };
+
+struct point p = { 1, 2, 3 };
@@ -0,0 +1,3 @@
+union pair { struct { int a, b; } s; int x; } both = { { 1, 2 } };
+union mixed { char c[3]; long l; } first = { { 4, 5 } };
+union mixed picked = { .l = 6 };
@@ -0,0 +1,3 @@
+struct flags {
+    _Alignas(4) unsigned ready : 1;
+};
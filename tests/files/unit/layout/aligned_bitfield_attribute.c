@@ -0,0 +1,5 @@
+struct flags {
+    __attribute__((aligned(4))) unsigned a : 3;
+};
+
+struct flags f;
@@ -0,0 +1,7 @@
+int a[4];
+int b[4];
+
+int main() {
+    a = b;
+    return 0;
+}
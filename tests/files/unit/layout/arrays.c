@@ -0,0 +1,31 @@
+enum { COUNT = 5 };
+
+int m[3][4];
+int *(*table[4])(void);
+char buf[COUNT];
+
+struct packet {
+    char tag;
+    int data[3];
+};
+
+int add(int a, int b) {
+    return a + b;
+}
+
+int apply(int (*op)(int, int), int x) {
+    return op(x, x) + (*op)(x, 1);
+}
+
+int main() {
+    int a[10];
+    int (*fp)(int, int) = add;
+    int (*row)[4] = &m[1];
+    struct packet p;
+
+    a[2] = 5;
+    m[1][2] = fp(a[2], 3);
+    p.data[0] = (*row)[2];
+
+    return apply(&add, (int)sizeof(a)) + (int)sizeof(buf);
+}
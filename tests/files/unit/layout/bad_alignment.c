@@ -0,0 +1 @@
+_Alignas(3) int x;
@@ -0,0 +1,3 @@
+struct s {
+    unsigned char c : 9;
+};
@@ -0,0 +1,3 @@
+struct s {
+    unsigned a : 0;
+};
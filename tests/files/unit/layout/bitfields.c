@@ -0,0 +1,34 @@
+struct flags {
+    unsigned ready : 1;
+    unsigned error : 1;
+    unsigned : 2;
+    unsigned mode : 3;
+    int level : 5;
+    unsigned char tail;
+};
+
+struct split {
+    unsigned char a : 5;
+    unsigned char b : 5;
+    unsigned : 0;
+    unsigned short c : 9;
+    long d : 40, e : 40;
+};
+
+struct __attribute__((packed)) wire {
+    unsigned char version : 4;
+    unsigned char ihl : 4;
+    unsigned short length : 12;
+    unsigned short flags : 4;
+};
+
+union bits {
+    unsigned a : 3;
+    unsigned char b;
+    long : 64;
+};
+
+struct lone {
+    char c;
+    long : 3;
+};
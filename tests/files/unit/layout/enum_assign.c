@@ -0,0 +1,6 @@
+enum { ONE = 1 };
+
+int f() {
+    ONE = 2;
+    return ONE;
+}
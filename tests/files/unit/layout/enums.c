@@ -0,0 +1,23 @@
+enum color { RED, GREEN = 5, BLUE, };
+
+enum { NEGATIVE = -3, AFTER_NEGATIVE, COMPUTED = BLUE * 2 + (1 << 4) };
+
+enum color favourite = 6;
+
+int pick(enum color c) {
+    enum color other = GREEN;
+
+    switch (c) {
+        case RED: return 1;
+        case BLUE: return AFTER_NEGATIVE;
+        default: break;
+    }
+
+    return c == other ? COMPUTED : 0;
+}
+
+int shadow() {
+    int RED = 10;
+    RED = RED + GREEN;
+    return RED;
+}
@@ -0,0 +1,6 @@
+struct later;
+
+int f() {
+    struct later x;
+    return 0;
+}
@@ -0,0 +1,13 @@
+struct spaced {
+    char c;
+    _Alignas(8) int i;
+    int _Alignas(16) _Alignas(4) j;
+};
+
+struct __attribute__((packed)) tight {
+    char c;
+    _Alignas(4) char d;
+};
+
+struct spaced s;
+struct tight t;
@@ -0,0 +1,26 @@
+struct header {
+    char tag;
+    int length __attribute__((packed));
+    short kind;
+};
+
+struct slot {
+    char tag;
+    int value __attribute__((aligned(16)));
+};
+
+struct mixed {
+    char tag;
+    __attribute__((aligned(2))) char small, other;
+    long big __attribute__((aligned(1)));
+};
+
+struct __attribute__((packed)) wire {
+    char tag;
+    int value __attribute__((aligned(4)));
+};
+
+struct header h;
+struct slot s;
+struct mixed m;
+struct wire w;
@@ -0,0 +1,24 @@
+struct __attribute__((packed)) header {
+    char kind;
+    int length;
+    long offset;
+};
+
+struct padded {
+    char c;
+    int i;
+} __attribute__((aligned(32)));
+
+struct [[gnu::packed, gnu::aligned(4)]] both {
+    char c;
+    long l;
+};
+
+struct weak {
+    long l;
+} __attribute__((aligned(2)));
+
+struct outer {
+    char c;
+    struct header h;
+};
@@ -0,0 +1,21 @@
+struct s { int a; };
+struct s g;
+
+long f(void) {
+    struct s { long x; long y; };
+    struct s local;
+    struct s *p = &local;
+
+    local.y = (long) 1;
+    g.a = 2;
+    return p->x;
+}
+
+int h(void) {
+    struct s;
+    struct s *p;
+    struct s { char c; };
+    struct s inner;
+    p = &inner;
+    return g.a;
+}
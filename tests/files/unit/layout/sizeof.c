@@ -0,0 +1,23 @@
+struct pair { char tag; long value; };
+
+enum {
+    PAIR_SIZE = sizeof(struct pair),
+    PAIR_ALIGN = _Alignof(struct pair),
+    PRODUCT = sizeof (int) * 3,
+};
+
+int table[sizeof(int) * 2];
+
+_Alignas(16) int aligned;
+_Alignas(long) char padded;
+static _Alignas(32) int wide;
+int _Alignas(8) _Alignas(16) mid;
+
+int main() {
+    int x = 1;
+    _Alignas(16) char buffer[3];
+    unsigned long n = sizeof(x = 5);
+    unsigned long m = sizeof x + _Alignof(long);
+
+    return x;
+}
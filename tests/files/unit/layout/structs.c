@@ -0,0 +1,37 @@
+struct node;
+
+struct point {
+    char tag;
+    int x;
+    int y;
+};
+
+struct node {
+    int value;
+    struct node *next;
+    struct point where;
+};
+
+struct { int a; char b; } anon;
+
+struct point origin;
+
+int get_x(struct point *p) {
+    return p->x;
+}
+
+int f(struct node *n) {
+    struct point p;
+    struct point q = p;
+    p.x = 5;
+    p.y = 6;
+    q = p;
+    n->where = q;
+    n->next->value = n->where.y;
+    anon.a = n->value;
+    return n->next->where.y;
+}
+
+unsigned long g() {
+    return sizeof(origin);
+}
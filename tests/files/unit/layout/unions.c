@@ -0,0 +1,20 @@
+union value {
+    char c;
+    long l;
+    struct { int a; int b; int c; } triple;
+};
+
+union small {
+    int i;
+    char c;
+};
+
+union value initialized = { 5 };
+
+int f() {
+    union small s = { 1 };
+    union value v = { };
+    v.triple.b = 3;
+    s.c = s.c;
+    return v.triple.b;
+}
@@ -0,0 +1,3 @@
+struct weak {
+    _Alignas(1) int i;
+};
@@ -0,0 +1,5 @@
+struct thing {
+    int x;
+};
+
+union thing u;
@@ -0,0 +1,13 @@
+typedef int T;
+
+int main() {
+    T x = (T){ 1 };
+    if (x) {
+        T T = 2;
+        x = T;
+    }
+    x = ({ T T = 3; T; });
+    while (x) x = (T){ 0 };
+    T y = x;
+    return y;
+}
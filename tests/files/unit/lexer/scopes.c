@@ -0,0 +1,20 @@
+typedef int T;
+
+long f(void) {
+    typedef long T;
+    T x = (long) 1;
+    return x;
+}
+
+T g(void) {
+    T y = 1;
+    return y;
+}
+
+int k(int T) {
+    return T * 2;
+}
+
+T m(void) {
+    return k(3);
+}
@@ -0,0 +1,17 @@
+typedef int T;
+
+int a;
+
+int f(T x) {
+    T * b;
+    int T = 2;
+    T * a;
+    return x;
+}
+
+T g() {
+    T T = 1;
+    return T;
+}
+
+T h;
@@ -0,0 +1,23 @@
+typedef unsigned long size_t;
+typedef struct point { int x; int y; } point_t;
+typedef point_t *point_ptr;
+typedef enum { OFF, ON } switch_t;
+typedef size_t length;
+
+struct point origin;
+
+length norm(point_ptr p) {
+    point_t copy = *p;
+    length result = (length) (copy.x + copy.y);
+    return result;
+}
+
+int flip(switch_t s) {
+    typedef int local_int;
+    local_int on = ON;
+    return s == on ? OFF : ON;
+}
+
+size_t size() {
+    return sizeof(origin);
+}
@@ -0,0 +1,2 @@
+struct { int x; } a;
+union { int y; char z; } b;
@@ -0,0 +1,17 @@
+int a, b = 2, *c;
+struct point { int x; int y; } origin, *cursor;
+typedef int T, *PT;
+T d, e;
+
+int main() {
+    int i = 1, j, k = i;
+    PT p = &i, q;
+    struct point here, there;
+
+    j = k;
+    q = p;
+    here.x = j;
+    there = here;
+
+    return *q + there.x;
+}
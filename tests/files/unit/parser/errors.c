@@ -0,0 +1,10 @@
+int a = ;
+int b;
+
+int main(void) {
+    int c = 1 +;
+    b = c;
+    return 0 0;
+}
+
+long d = 2 3;
@@ -0,0 +1 @@
+unsigned short c = u'\U0001F600';
@@ -0,0 +1 @@
+int *s = L"a" u"b";
@@ -0,0 +1,10 @@
+int f(void) {
+    int x = (1;
+    typedef char L;
+    L y = 0;
+    return y;
+}
+
+L leaked;
+typedef int T;
+T g;
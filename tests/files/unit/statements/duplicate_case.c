@@ -0,0 +1,7 @@
+int f(int x) {
+    switch (x) {
+        case 2: return 1;
+        case 1 + 1: return 2;
+    }
+    return 0;
+}
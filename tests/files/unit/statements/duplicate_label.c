@@ -0,0 +1,8 @@
+int f(int x) {
+done:
+    if (x) {
+    done:
+        return 1;
+    }
+    return 0;
+}
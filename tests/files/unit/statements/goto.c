@@ -0,0 +1,13 @@
+int f(int x) {
+    int y = 0;
+    if (x) goto fail;
+    y = 1;
+again:
+    {
+        y = y + 1;
+        if (y) goto again;
+    }
+    return y;
+fail:
+    return 0;
+}
@@ -0,0 +1,4 @@
+int f(int x) {
+    if (x) break;
+    return 0;
+}
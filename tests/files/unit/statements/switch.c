@@ -0,0 +1,16 @@
+int classify(int x) {
+    int y = 0;
+    switch (x) {
+        case 1: y = 10; break;
+        case 2:
+        case 3: y = 30; break;
+        default: y = 1;
+        case 4 + 1: {
+            while (y) {
+                if (x) break;
+                y = 0;
+            }
+        }
+    }
+    return y;
+}
@@ -0,0 +1,4 @@
+int f(int x) {
+    if (x) goto fail;
+    return 0;
+}
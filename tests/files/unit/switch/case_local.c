@@ -0,0 +1,14 @@
+int g(int x);
+
+int f(int x) {
+    switch (x) {
+        case 1: {
+            int y = 5;
+            x = y;
+        }
+        case 2:
+            g(1);
+    }
+
+    return x;
+}
@@ -0,0 +1,7 @@
+int f(char c) {
+    switch (c) {
+        case 0: return 1;
+        case 256: return 2;
+    }
+    return 0;
+}
@@ -0,0 +1,10 @@
+int f(int x) {
+    switch (x) {
+        case 1: return 10;
+        case 100: return 20;
+        case -1000: return 30;
+        case 5000: return 50;
+        case 7: return 70;
+    }
+    return 0;
+}
@@ -0,0 +1,15 @@
+_Bool set = 5;
+_Bool unset;
+
+_Bool non_null(int *p) {
+    return p;
+}
+
+int main() {
+    int x = 256;
+    _Bool b = x;
+    _Bool copy = b;
+
+    unset = (_Bool)x;
+    return (int)non_null(&x) + (int)b + (int)copy;
+}
@@ -0,0 +1,2 @@
+typedef int bool;
+bool flag;
@@ -0,0 +1,15 @@
+extern int a[];
+int a[3];
+extern int a[];
+
+int f();
+int f(int x);
+int f(const int x) {
+    return x;
+}
+
+int g(void) {
+    int (*p)[] = &a;
+    int (*q)[3] = p;
+    return (*q)[0];
+}
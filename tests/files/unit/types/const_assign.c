@@ -0,0 +1,6 @@
+const int limit = 10;
+
+int main() {
+    limit = 5;
+    return 0;
+}
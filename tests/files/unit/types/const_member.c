@@ -0,0 +1,7 @@
+struct point { const int x; int y; };
+
+int main() {
+    struct point pt;
+    pt.x = 1;
+    return 0;
+}
@@ -0,0 +1,6 @@
+int main() {
+    int x;
+    const int *p = &x;
+    *p += 1;
+    return 0;
+}
@@ -0,0 +1,8 @@
+struct point { int x; int y; };
+
+int main() {
+    const struct point pt;
+    const struct point *p = &pt;
+    p->y = 2;
+    return 0;
+}
@@ -0,0 +1,6 @@
+const char *message;
+
+int main() {
+    char *p = message;
+    return 0;
+}
@@ -0,0 +1,8 @@
+int length(char *s) {
+    return 0;
+}
+
+int main() {
+    const char c;
+    return length(&c);
+}
@@ -0,0 +1,2 @@
+int a[2];
+extern int a[3];
@@ -0,0 +1 @@
+unsigned double d;
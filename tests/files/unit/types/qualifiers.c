@@ -0,0 +1,29 @@
+typedef int T;
+
+const int limit = 10;
+int const also_limit = 20;
+const char *message;
+char *const fixed = 0;
+volatile int ticks;
+const volatile T status;
+
+struct point { const int x; int y; };
+
+int length(const char *restrict s) {
+    return 0;
+}
+
+int main() {
+    char buffer;
+    char *p = &buffer;
+    const char *q = p;
+    const T count = limit;
+    const int *const *r;
+    struct point pt;
+
+    q = message;
+    pt.y = count;
+    ticks = ticks + 1;
+
+    return length(p) + length(q) + (int)(const int)pt.x;
+}
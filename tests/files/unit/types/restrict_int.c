@@ -0,0 +1 @@
+restrict int x;
@@ -0,0 +1,21 @@
+unsigned u;
+short int s;
+long int l;
+long long ll;
+unsigned long long ull;
+long unsigned int lui;
+int long const cl;
+signed sg;
+signed char sc;
+unsigned char uc;
+char c;
+_Bool b;
+
+int main() {
+    unsigned short us = (unsigned short)3;
+    long long total = (long long)us + ll;
+    _Bool flag = total;
+    char *p = &c;
+
+    return (int)flag + (int)*p;
+}
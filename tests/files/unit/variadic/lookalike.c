@@ -0,0 +1,4 @@
+int first(int count, ...) {
+    unsigned long args[3];
+    return __builtin_va_arg(args, int);
+}
@@ -0,0 +1,4 @@
+int first(int count, ...) {
+    int args;
+    return __builtin_va_arg(args, int);
+}
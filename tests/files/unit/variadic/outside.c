@@ -0,0 +1,5 @@
+int first(int count) {
+    __builtin_va_list args;
+    __builtin_va_start(args, count);
+    return count;
+}
@@ -0,0 +1,7 @@
+struct point { int x; int y; };
+
+struct point *first(int count, ...) {
+    __builtin_va_list args;
+    __builtin_va_start(args, count);
+    return __builtin_va_arg(args, struct point);
+}
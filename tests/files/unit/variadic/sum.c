@@ -0,0 +1,26 @@
+int printf(const char *fmt, ...);
+
+long sum(int count, ...) {
+    __builtin_va_list args;
+    __builtin_va_list copy;
+    long total = (long)0;
+    int i = 0;
+
+    __builtin_va_start(args, count);
+    __builtin_va_copy(copy, args);
+
+    while (i < count) {
+        total += __builtin_va_arg(args, long);
+        i += 1;
+    }
+
+    __builtin_va_end(copy);
+    __builtin_va_end(args);
+    return total;
+}
+
+int main(const char *fmt) {
+    short s = (short)3;
+    printf(fmt, s);
+    return (int)sum(3, 1, 2, 3, 4, 5, 6, 7);
+}
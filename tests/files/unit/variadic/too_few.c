@@ -0,0 +1,5 @@
+int printf(const char *fmt, ...);
+
+int main(void) {
+    return printf();
+}